@@ -0,0 +1,65 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('CSSPropertyOperations', () => {
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+  })
+
+  it('should append px to numeric values except unitless ones', async () => {
+    const container = document.createElement('div')
+    ReactDOM.createRoot(container).render(
+      <div style={{width: 10, opacity: 0.5, zIndex: 2, margin: 0}} />
+    )
+    await sleep(10)
+    const style = container.firstChild.style
+    expect(style.width).toBe('10px')
+    expect(style.opacity).toBe('0.5')
+    expect(style.zIndex).toBe('2')
+    expect(style.margin).toBe('0px')
+  })
+
+  it('should remove styles dropped from the new style object', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(<div style={{color: 'red', height: 5}} />)
+    await sleep(10)
+    const style = container.firstChild.style
+    expect(style.color).toBe('red')
+
+    root.render(<div style={{height: 6}} />)
+    await sleep(10)
+    expect(style.color).toBe('')
+    expect(style.height).toBe('6px')
+
+    root.render(<div />)
+    await sleep(10)
+    expect(style.height).toBe('')
+  })
+
+  it('should set css custom properties', async () => {
+    const container = document.createElement('div')
+    ReactDOM.createRoot(container).render(<div style={{'--foo': 10}} />)
+    await sleep(10)
+    expect(container.firstChild.style.getPropertyValue('--foo')).toBe('10')
+  })
+})
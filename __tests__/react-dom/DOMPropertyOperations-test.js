@@ -0,0 +1,127 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('DOMPropertyOperations', () => {
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+  })
+
+  it('should map className and htmlFor to attributes', async () => {
+    const container = document.createElement('div')
+    ReactDOM.createRoot(container).render(
+      <label className='foo' htmlFor='bar' data-id='1' aria-label='label' />
+    )
+    await sleep(10)
+    const label = container.firstChild
+    expect(label.getAttribute('class')).toBe('foo')
+    expect(label.getAttribute('for')).toBe('bar')
+    expect(label.getAttribute('data-id')).toBe('1')
+    expect(label.getAttribute('aria-label')).toBe('label')
+  })
+
+  it('should handle boolean attributes', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(<button disabled={true} hidden={false} />)
+    await sleep(10)
+    const button = container.firstChild
+    expect(button.getAttribute('disabled')).toBe('')
+    expect(button.hasAttribute('hidden')).toBe(false)
+
+    root.render(<button disabled={false} hidden={true} />)
+    await sleep(10)
+    expect(button.hasAttribute('disabled')).toBe(false)
+    expect(button.getAttribute('hidden')).toBe('')
+  })
+
+  it('should set checked as a property', async () => {
+    const container = document.createElement('div')
+    ReactDOM.createRoot(container).render(
+      <input type='checkbox' checked={true} onChange={() => {}} />
+    )
+    await sleep(10)
+    expect(container.firstChild.checked).toBe(true)
+  })
+
+  it('should remove attributes that disappear on update', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(<div id='a' title='b' />)
+    await sleep(10)
+    const div = container.firstChild
+    expect(div.getAttribute('title')).toBe('b')
+
+    root.render(<div id='c' />)
+    await sleep(10)
+    expect(div.getAttribute('id')).toBe('c')
+    expect(div.hasAttribute('title')).toBe(false)
+  })
+
+  it('should not set event handlers or children as attributes', async () => {
+    const container = document.createElement('div')
+    ReactDOM.createRoot(container).render(
+      <div onClick={() => {}}>text</div>
+    )
+    await sleep(10)
+    const div = container.firstChild
+    expect(div.hasAttribute('onClick')).toBe(false)
+    expect(div.hasAttribute('children')).toBe(false)
+  })
+
+  it('should not stringify boolean values of the value attribute', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(<button value='a' draggable={true} />)
+    await sleep(10)
+    const button = container.firstChild
+    expect(button.getAttribute('value')).toBe('a')
+    expect(button.getAttribute('draggable')).toBe('true')
+
+    root.render(<button value={false} draggable={false} />)
+    await sleep(10)
+    expect(button.hasAttribute('value')).toBe(false)
+    expect(button.getAttribute('draggable')).toBe('false')
+  })
+
+  it('should stringify the attribute values like the DOM does', async () => {
+    const container = document.createElement('div')
+    const value = {
+      toString() {
+        return 'custom'
+      },
+    }
+    ReactDOM.createRoot(container).render(
+      <a
+        href={new URL('https://example.com/a')}
+        title={value}
+        data-infinity={Infinity}
+        data-custom={value}
+      />
+    )
+    await sleep(10)
+    const a = container.firstChild
+    expect(a.getAttribute('href')).toBe('https://example.com/a')
+    expect(a.getAttribute('title')).toBe('custom')
+    expect(a.getAttribute('data-infinity')).toBe('Infinity')
+    expect(a.getAttribute('data-custom')).toBe('custom')
+  })
+})
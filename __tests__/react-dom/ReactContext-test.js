@@ -0,0 +1,254 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactContext', () => {
+  let container
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+  })
+
+  it('renders the value of the closest provider with Context.Consumer', async () => {
+    const Context = React.createContext('default')
+    const root = ReactDOM.createRoot(container)
+    root.render(
+      <div>
+        <Context.Consumer>{(value) => <span>{value}</span>}</Context.Consumer>
+        <Context.Provider value="provided">
+          <Context.Consumer>{(value) => <span>{value}</span>}</Context.Consumer>
+        </Context.Provider>
+      </div>
+    )
+    await sleep(10)
+    expect(container.innerHTML).toBe(
+      '<div><span>default</span><span>provided</span></div>'
+    )
+  })
+
+  it('updates the consumer below a memoized component', async () => {
+    const Context = React.createContext(0)
+    const Static = React.memo(() => (
+      <Context.Consumer>{(value) => <span>{value}</span>}</Context.Consumer>
+    ))
+    let setValue
+    function App() {
+      const [value, _setValue] = React.useState(1)
+      setValue = _setValue
+      return (
+        <Context.Provider value={value}>
+          <Static />
+        </Context.Provider>
+      )
+    }
+    ReactDOM.createRoot(container).render(<App />)
+    await sleep(10)
+    expect(container.innerHTML).toBe('<span>1</span>')
+    setValue(2)
+    await sleep(10)
+    expect(container.innerHTML).toBe('<span>2</span>')
+  })
+
+  it('reads the context with use inside conditionals', async () => {
+    const Context = React.createContext('default')
+    const Static = React.memo(({show}) => {
+      if (show) {
+        return <span>{React.use(Context)}</span>
+      }
+      return <span>hidden</span>
+    })
+    let setValue
+    function App({show}) {
+      const [value, _setValue] = React.useState('a')
+      setValue = _setValue
+      return (
+        <Context.Provider value={value}>
+          <Static show={show} />
+        </Context.Provider>
+      )
+    }
+    const root = ReactDOM.createRoot(container)
+    root.render(<App show={false} />)
+    await sleep(10)
+    expect(container.innerHTML).toBe('<span>hidden</span>')
+    root.render(<App show={true} />)
+    await sleep(10)
+    expect(container.innerHTML).toBe('<span>a</span>')
+    setValue('b')
+    await sleep(10)
+    expect(container.innerHTML).toBe('<span>b</span>')
+  })
+
+  it('does not write the provider values to the shared context object', async () => {
+    const Context = React.createContext('default')
+    const containerB = document.createElement('div')
+    const Reader = () => <span>{React.useContext(Context)}</span>
+    ReactDOM.createRoot(container).render(
+      <Context.Provider value="a">
+        <Reader />
+      </Context.Provider>
+    )
+    ReactDOM.createRoot(containerB).render(<Reader />)
+    await sleep(10)
+    expect(container.innerHTML).toBe('<span>a</span>')
+    expect(containerB.innerHTML).toBe('<span>default</span>')
+    expect(Context._currentValue).toBe('default')
+  })
+
+  it('reads the right values after a transition render inside a provider is interrupted', async () => {
+    const Context = React.createContext('default')
+    const log = []
+    const items = Array.from({length: 50}, (_, i) => i)
+
+    function Item({index}) {
+      const value = React.useContext(Context)
+      log.push(`${index}:${value}`)
+      // Make the render take several time slices
+      const start = Date.now()
+      while (Date.now() - start < 1) {}
+      return <li>{value}</li>
+    }
+    let setOutside
+    function Outside() {
+      const [count, _setCount] = React.useState(0)
+      setOutside = _setCount
+      log.push(`outside ${count}:${React.useContext(Context)}`)
+      return <span>{count}</span>
+    }
+    let setValue
+    let startTransition
+    function App() {
+      const [value, _setValue] = React.useState('a')
+      const [, _startTransition] = React.useTransition()
+      setValue = _setValue
+      startTransition = _startTransition
+      return (
+        <div>
+          <Context.Provider value={value}>
+            <ul>
+              {items.map((item) => (
+                <Item key={item} index={item} />
+              ))}
+            </ul>
+          </Context.Provider>
+          <Outside />
+        </div>
+      )
+    }
+    ReactDOM.createRoot(container).render(<App />)
+    await sleep(200)
+    log.length = 0
+
+    startTransition(() => setValue('b'))
+    // The urgent update is rendered in the middle of the transition render
+    setTimeout(() => setOutside(1))
+    await sleep(500)
+
+    const urgentRender = log.indexOf('outside 1:default')
+    expect(urgentRender).toBeGreaterThan(0)
+    expect(urgentRender).toBeLessThan(log.length - 1)
+    // Outside of the provider, in the urgent render and in the transition render
+    expect(
+      log.filter(
+        (entry) => entry.startsWith('outside') && !entry.endsWith(':default')
+      )
+    ).toEqual([])
+    expect(
+      log.filter((entry) => !entry.startsWith('outside') && !entry.endsWith(':b'))
+    ).toEqual([])
+    expect(container.querySelector('span').textContent).toBe('1')
+    expect(
+      Array.from(container.querySelectorAll('li')).map((li) => li.textContent)
+    ).toEqual(items.map(() => 'b'))
+  })
+
+  it('keeps the provider values of a react-dom root and a react-noop root apart', async () => {
+    const ReactNoop = require('../../dist/react-noop')
+    const Context = React.createContext('default')
+    const log = []
+    const items = Array.from({length: 50}, (_, i) => i)
+
+    function Item({index}) {
+      const value = React.useContext(Context)
+      log.push(`${index}:${value}`)
+      const start = Date.now()
+      while (Date.now() - start < 1) {}
+      return <li>{value}</li>
+    }
+    let setDOMValue
+    let startTransition
+    function DOMApp() {
+      const [value, _setValue] = React.useState('dom 0')
+      const [, _startTransition] = React.useTransition()
+      setDOMValue = _setValue
+      startTransition = _startTransition
+      return (
+        <Context.Provider value={value}>
+          <ul>
+            {items.map((item) => (
+              <Item key={item} index={item} />
+            ))}
+          </ul>
+        </Context.Provider>
+      )
+    }
+    function NoopReader() {
+      const value = React.useContext(Context)
+      log.push(`noop reader:${value}`)
+      return value
+    }
+    let setNoopValue
+    function NoopApp() {
+      const [value, _setValue] = React.useState('noop 0')
+      setNoopValue = _setValue
+      return (
+        <Context.Provider value={value}>
+          <NoopReader />
+        </Context.Provider>
+      )
+    }
+    ReactDOM.createRoot(container).render(<DOMApp />)
+    const noopRoot = ReactNoop.createRoot()
+    noopRoot.render(<NoopApp />)
+    await sleep(200)
+    expect(noopRoot.getChildrenAsJSX()).toBe('noop 0')
+    log.length = 0
+
+    startTransition(() => setDOMValue('dom 1'))
+    // The react-noop root renders in the middle of the transition render of react-dom
+    setTimeout(() => setNoopValue('noop 1'))
+    await sleep(500)
+
+    const noopRender = log.indexOf('noop reader:noop 1')
+    expect(noopRender).toBeGreaterThan(0)
+    expect(noopRender).toBeLessThan(log.length - 1)
+    expect(
+      log.filter(
+        (entry) => !entry.startsWith('noop reader') && !entry.endsWith(':dom 1')
+      )
+    ).toEqual([])
+    expect(noopRoot.getChildrenAsJSX()).toBe('noop 1')
+    expect(
+      Array.from(container.querySelectorAll('li')).map((li) => li.textContent)
+    ).toEqual(items.map(() => 'dom 1'))
+  })
+})
@@ -0,0 +1,109 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactDOMComponent', () => {
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+  })
+
+  it('should set a single text child as text content', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(<div>hello</div>)
+    await sleep(10)
+    const div = container.firstChild
+    expect(div.childNodes.length).toBe(1)
+    expect(div.textContent).toBe('hello')
+
+    root.render(<div>{10}</div>)
+    await sleep(10)
+    expect(div.textContent).toBe('10')
+  })
+
+  it('should switch between text content and element children', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(<div>hello</div>)
+    await sleep(10)
+    const div = container.firstChild
+
+    root.render(
+      <div>
+        <span>a</span>
+        <span>b</span>
+      </div>
+    )
+    await sleep(10)
+    expect(div.innerHTML).toBe('<span>a</span><span>b</span>')
+
+    root.render(<div>world</div>)
+    await sleep(10)
+    expect(div.innerHTML).toBe('world')
+
+    root.render(<div />)
+    await sleep(10)
+    expect(div.innerHTML).toBe('')
+  })
+
+  it('should set and update dangerouslySetInnerHTML', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(<div dangerouslySetInnerHTML={{__html: '<b>bold</b>'}} />)
+    await sleep(10)
+    const div = container.firstChild
+    expect(div.innerHTML).toBe('<b>bold</b>')
+
+    root.render(<div dangerouslySetInnerHTML={{__html: '<i>italic</i>'}} />)
+    await sleep(10)
+    expect(div.innerHTML).toBe('<i>italic</i>')
+
+    root.render(
+      <div>
+        <span />
+      </div>
+    )
+    await sleep(10)
+    expect(div.innerHTML).toBe('<span></span>')
+  })
+
+  it('should not write the attributes when the props are the same', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(<div id="a" title="b" data-foo="c" hidden={true} />)
+    await sleep(10)
+    const div = container.firstChild
+    const setAttribute = jest.spyOn(div, 'setAttribute')
+    const removeAttribute = jest.spyOn(div, 'removeAttribute')
+
+    root.render(<div id="a" title="b" data-foo="c" hidden={true} />)
+    await sleep(10)
+    expect(setAttribute).not.toHaveBeenCalled()
+    expect(removeAttribute).not.toHaveBeenCalled()
+
+    root.render(<div id="a" title="d" data-foo="c" hidden={true} />)
+    await sleep(10)
+    expect(setAttribute).toHaveBeenCalledTimes(1)
+    expect(setAttribute).toHaveBeenCalledWith('title', 'd')
+    expect(removeAttribute).not.toHaveBeenCalled()
+    expect(div.getAttribute('title')).toBe('d')
+  })
+})
@@ -0,0 +1,133 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+function setUntrackedValue(node, value) {
+  node.value = value
+  node.dispatchEvent(new Event('input', {bubbles: true}))
+}
+
+describe('ReactDOMInput', () => {
+  let container
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+    document.body.appendChild(container)
+  })
+
+  afterEach(() => {
+    document.body.removeChild(container)
+  })
+
+  it('should set the initial value from value or defaultValue', async () => {
+    ReactDOM.createRoot(container).render(
+      <div>
+        <input value="controlled" onChange={() => {}} />
+        <input defaultValue="uncontrolled" />
+        <input type="checkbox" defaultChecked={true} />
+        <textarea defaultValue="text" />
+      </div>
+    )
+    await sleep(10)
+    const [controlled, uncontrolled, checkbox] =
+      container.querySelectorAll('input')
+    expect(controlled.value).toBe('controlled')
+    expect(uncontrolled.value).toBe('uncontrolled')
+    expect(checkbox.checked).toBe(true)
+    expect(container.querySelector('textarea').value).toBe('text')
+  })
+
+  it('should fire onChange on every input event', async () => {
+    const log = []
+    function App() {
+      const [value, setValue] = React.useState('')
+      return (
+        <input
+          value={value}
+          onChange={(e) => {
+            log.push(e.target.value)
+            setValue(e.target.value)
+          }}
+        />
+      )
+    }
+    ReactDOM.createRoot(container).render(<App />)
+    await sleep(10)
+    const input = container.querySelector('input')
+    setUntrackedValue(input, 'a')
+    await sleep(10)
+    setUntrackedValue(input, 'ab')
+    await sleep(10)
+    expect(log).toEqual(['a', 'ab'])
+    expect(input.value).toBe('ab')
+  })
+
+  it('should restore the controlled value if the state did not change', async () => {
+    ReactDOM.createRoot(container).render(
+      <input value="fixed" onChange={() => {}} />
+    )
+    await sleep(10)
+    const input = container.querySelector('input')
+    setUntrackedValue(input, 'changed')
+    await sleep(10)
+    expect(input.value).toBe('fixed')
+  })
+
+  it('should restore a controlled checkbox after a click', async () => {
+    ReactDOM.createRoot(container).render(
+      <input type="checkbox" checked={false} onChange={() => {}} />
+    )
+    await sleep(10)
+    const input = container.querySelector('input')
+    input.click()
+    await sleep(10)
+    expect(input.checked).toBe(false)
+  })
+
+  it('should select the options of a multiple select', async () => {
+    const root = ReactDOM.createRoot(container)
+    root.render(
+      <select multiple={true} value={['a', 'c']} onChange={() => {}}>
+        <option value="a">a</option>
+        <option value="b">b</option>
+        <option value="c">c</option>
+      </select>
+    )
+    await sleep(10)
+    const options = container.querySelector('select').options
+    expect(options[0].selected).toBe(true)
+    expect(options[1].selected).toBe(false)
+    expect(options[2].selected).toBe(true)
+
+    root.render(
+      <select multiple={true} value={['b']} onChange={() => {}}>
+        <option value="a">a</option>
+        <option value="b">b</option>
+        <option value="c">c</option>
+      </select>
+    )
+    await sleep(10)
+    expect(options[0].selected).toBe(false)
+    expect(options[1].selected).toBe(true)
+    expect(options[2].selected).toBe(false)
+  })
+})
@@ -0,0 +1,139 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactDOMPortal', () => {
+  let container
+  let portalContainer
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+    portalContainer = document.createElement('div')
+    document.body.appendChild(container)
+    document.body.appendChild(portalContainer)
+  })
+
+  afterEach(() => {
+    document.body.removeChild(container)
+    document.body.removeChild(portalContainer)
+  })
+
+  it('renders the children into the portal container', async () => {
+    const root = ReactDOM.createRoot(container)
+    root.render(
+      <div>
+        <span>outside</span>
+        {ReactDOM.createPortal(<p>portal</p>, portalContainer)}
+      </div>
+    )
+    await sleep(10)
+    expect(container.innerHTML).toBe('<div><span>outside</span></div>')
+    expect(portalContainer.innerHTML).toBe('<p>portal</p>')
+
+    root.render(
+      <div>
+        <span>outside</span>
+        {ReactDOM.createPortal(
+          [<p key="a">a</p>, <p key="b">b</p>],
+          portalContainer
+        )}
+      </div>
+    )
+    await sleep(10)
+    expect(container.innerHTML).toBe('<div><span>outside</span></div>')
+    expect(portalContainer.innerHTML).toBe('<p>a</p><p>b</p>')
+  })
+
+  it('removes the portal children when the portal is unmounted', async () => {
+    const root = ReactDOM.createRoot(container)
+    root.render(
+      <div>{ReactDOM.createPortal(<p>portal</p>, portalContainer)}</div>
+    )
+    await sleep(10)
+    expect(portalContainer.innerHTML).toBe('<p>portal</p>')
+
+    root.render(<div />)
+    await sleep(10)
+    expect(container.innerHTML).toBe('<div></div>')
+    expect(portalContainer.innerHTML).toBe('')
+
+    root.render(
+      <div>{ReactDOM.createPortal(<p>portal</p>, portalContainer)}</div>
+    )
+    await sleep(10)
+    root.unmount()
+    await sleep(10)
+    expect(portalContainer.innerHTML).toBe('')
+  })
+
+  it('bubbles the events in the portal to the React ancestors', async () => {
+    const log = []
+    ReactDOM.createRoot(container).render(
+      <div
+        onClickCapture={() => log.push('outer capture')}
+        onClick={() => log.push('outer bubble')}>
+        {ReactDOM.createPortal(
+          <button onClick={() => log.push('inner bubble')}>click</button>,
+          portalContainer
+        )}
+      </div>
+    )
+    await sleep(10)
+    portalContainer.querySelector('button').click()
+    expect(log).toEqual(['outer capture', 'inner bubble', 'outer bubble'])
+  })
+
+  it('stops listening to the portal container when the root is unmounted', async () => {
+    const log = []
+    const root = ReactDOM.createRoot(container)
+    root.render(
+      <div onClick={() => log.push('outer')}>
+        {ReactDOM.createPortal(
+          <button onClick={() => log.push('portal')}>click</button>,
+          portalContainer
+        )}
+      </div>
+    )
+    await sleep(10)
+    const button = portalContainer.querySelector('button')
+    button.click()
+    expect(log).toEqual(['portal', 'outer'])
+
+    root.unmount()
+    await sleep(10)
+    expect(portalContainer.innerHTML).toBe('')
+    // The node still points to its unmounted fiber
+    portalContainer.appendChild(button)
+    button.click()
+    portalContainer.click()
+    expect(log).toEqual(['portal', 'outer'])
+    portalContainer.removeChild(button)
+
+    // The old portal container can be the container of a new root
+    ReactDOM.createRoot(portalContainer).render(
+      <button onClick={() => log.push('new root')}>click</button>
+    )
+    await sleep(10)
+    portalContainer.querySelector('button').click()
+    expect(log).toEqual(['portal', 'outer', 'new root'])
+  })
+})
@@ -0,0 +1,140 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactDOMRoot', () => {
+  let containerA
+  let containerB
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    containerA = document.createElement('div')
+    containerB = document.createElement('div')
+  })
+
+  it('renders several roots in the same tick', async () => {
+    const rootA = ReactDOM.createRoot(containerA)
+    const rootB = ReactDOM.createRoot(containerB)
+    rootA.render(<span>A</span>)
+    rootB.render(<span>B</span>)
+    await sleep(10)
+    expect(containerA.innerHTML).toBe('<span>A</span>')
+    expect(containerB.innerHTML).toBe('<span>B</span>')
+
+    rootB.unmount()
+    rootA.render(<span>A2</span>)
+    await sleep(10)
+    expect(containerA.innerHTML).toBe('<span>A2</span>')
+    expect(containerB.innerHTML).toBe('')
+  })
+
+  it('resumes the interrupted render of a root after another root renders in the middle of it', async () => {
+    const log = []
+    let itemsOfAWhenBCommitted = null
+
+    function Item({value}) {
+      log.push(`A${value}`)
+      // Make the render of A take several time slices
+      const start = Date.now()
+      while (Date.now() - start < 1) {}
+      return <li>{value}</li>
+    }
+    let startTransition
+    let setSlowItems
+    function Slow() {
+      const [items, _setItems] = React.useState([])
+      const [, _startTransition] = React.useTransition()
+      setSlowItems = _setItems
+      startTransition = _startTransition
+      return (
+        <ul>
+          {items.map((item) => (
+            <Item key={item} value={item} />
+          ))}
+        </ul>
+      )
+    }
+    let setFast
+    function Fast() {
+      const [value, _setValue] = React.useState(0)
+      setFast = _setValue
+      React.useLayoutEffect(() => {
+        log.push(`B${value}`)
+        itemsOfAWhenBCommitted = containerA.querySelectorAll('li').length
+      })
+      return <span>{value}</span>
+    }
+    ReactDOM.createRoot(containerA).render(<Slow />)
+    ReactDOM.createRoot(containerB).render(<Fast />)
+    await sleep(10)
+    log.length = 0
+
+    const items = Array.from({length: 100}, (_, i) => i)
+    startTransition(() => setSlowItems(items))
+    // Fired between the time slices of the render of A
+    setTimeout(() => setFast(1))
+    await sleep(500)
+
+    // B is committed while A is rendering, A has not been committed yet
+    const commitOfB = log.indexOf('B1')
+    expect(commitOfB).toBeGreaterThan(0)
+    expect(commitOfB).toBeLessThan(log.length - 1)
+    expect(itemsOfAWhenBCommitted).toBe(0)
+    // The render of A goes on from where it was interrupted
+    expect(log.filter((entry) => entry.startsWith('A'))).toEqual(
+      items.map((item) => `A${item}`)
+    )
+
+    expect(containerB.innerHTML).toBe('<span>1</span>')
+    expect(containerA.querySelectorAll('li').length).toBe(100)
+  })
+
+  it('renders a react-dom root and a react-noop root together', async () => {
+    const ReactNoop = require('../../dist/react-noop')
+
+    let setDOMValue
+    function DOMCounter() {
+      const [value, _setValue] = React.useState(0)
+      setDOMValue = _setValue
+      return <span>{value}</span>
+    }
+    let setNoopValue
+    function NoopCounter() {
+      const [value, _setValue] = React.useState(0)
+      setNoopValue = _setValue
+      return `noop ${value}`
+    }
+
+    const domRoot = ReactDOM.createRoot(containerA)
+    const noopRoot = ReactNoop.createRoot()
+    domRoot.render(<DOMCounter />)
+    noopRoot.render(<NoopCounter />)
+    await sleep(10)
+    expect(containerA.innerHTML).toBe('<span>0</span>')
+    expect(noopRoot.getChildrenAsJSX()).toBe('noop 0')
+
+    setDOMValue(1)
+    setNoopValue(2)
+    await sleep(10)
+    expect(containerA.innerHTML).toBe('<span>1</span>')
+    expect(noopRoot.getChildrenAsJSX()).toBe('noop 2')
+  })
+})
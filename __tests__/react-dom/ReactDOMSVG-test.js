@@ -0,0 +1,97 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+const SVG_NAMESPACE = 'http://www.w3.org/2000/svg'
+const HTML_NAMESPACE = 'http://www.w3.org/1999/xhtml'
+const MATH_NAMESPACE = 'http://www.w3.org/1998/Math/MathML'
+const XLINK_NAMESPACE = 'http://www.w3.org/1999/xlink'
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactDOMSVG', () => {
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+  })
+
+  it('creates elements in the svg namespace', async () => {
+    const container = document.createElement('div')
+    ReactDOM.createRoot(container).render(
+      <svg>
+        <g>
+          <path strokeWidth={2} />
+        </g>
+        <foreignObject>
+          <div />
+        </foreignObject>
+      </svg>
+    )
+    await sleep(10)
+    const svg = container.firstChild
+    expect(svg.namespaceURI).toBe(SVG_NAMESPACE)
+    expect(svg.querySelector('path').namespaceURI).toBe(SVG_NAMESPACE)
+    expect(svg.querySelector('path').getAttribute('stroke-width')).toBe('2')
+    const foreignObject = svg.querySelector('foreignObject')
+    expect(foreignObject.namespaceURI).toBe(SVG_NAMESPACE)
+    expect(foreignObject.firstChild.namespaceURI).toBe(HTML_NAMESPACE)
+  })
+
+  it('creates elements in the mathml namespace', async () => {
+    const container = document.createElement('div')
+    ReactDOM.createRoot(container).render(
+      <math>
+        <mi>x</mi>
+      </math>
+    )
+    await sleep(10)
+    expect(container.firstChild.namespaceURI).toBe(MATH_NAMESPACE)
+    expect(container.firstChild.firstChild.namespaceURI).toBe(MATH_NAMESPACE)
+  })
+
+  it('creates svg children when rendered into an svg container', async () => {
+    const container = document.createElementNS(SVG_NAMESPACE, 'svg')
+    ReactDOM.createRoot(container).render(<circle />)
+    await sleep(10)
+    expect(container.firstChild.namespaceURI).toBe(SVG_NAMESPACE)
+  })
+
+  it('sets namespaced attributes', async () => {
+    const container = document.createElement('div')
+    const root = ReactDOM.createRoot(container)
+    root.render(
+      <svg xmlnsXlink="http://www.w3.org/1999/xlink">
+        <use xlinkHref="#a" />
+      </svg>
+    )
+    await sleep(10)
+    const use = container.querySelector('use')
+    expect(use.getAttributeNS(XLINK_NAMESPACE, 'href')).toBe('#a')
+    expect(container.firstChild.getAttribute('xmlns:xlink')).toBe(
+      XLINK_NAMESPACE
+    )
+
+    root.render(
+      <svg xmlnsXlink="http://www.w3.org/1999/xlink">
+        <use />
+      </svg>
+    )
+    await sleep(10)
+    expect(use.hasAttributeNS(XLINK_NAMESPACE, 'href')).toBe(false)
+  })
+})
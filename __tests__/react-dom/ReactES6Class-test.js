@@ -0,0 +1,188 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactES6Class', () => {
+  let container
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+  })
+
+  it('renders a simple stateless component with prop', async () => {
+    class Foo extends React.Component {
+      render() {
+        return <div className={this.props.bar} />
+      }
+    }
+    const root = ReactDOM.createRoot(container)
+    root.render(<Foo bar='foo' />)
+    await sleep(10)
+    expect(container.firstChild.className).toBe('foo')
+    root.render(<Foo bar='bar' />)
+    await sleep(10)
+    expect(container.firstChild.className).toBe('bar')
+  })
+
+  it('merges the partial state and calls the setState callback', async () => {
+    let instance
+    const callback = jest.fn()
+    class Foo extends React.Component {
+      constructor(props) {
+        super(props)
+        this.state = {bar: 'foo', baz: 1}
+        instance = this
+      }
+      render() {
+        return <span>{this.state.bar + this.state.baz}</span>
+      }
+    }
+    ReactDOM.createRoot(container).render(<Foo />)
+    await sleep(10)
+    expect(container.textContent).toBe('foo1')
+
+    instance.setState({bar: 'bar'}, callback)
+    instance.setState((state) => ({baz: state.baz + 1}))
+    await sleep(10)
+    expect(container.textContent).toBe('bar2')
+    expect(callback).toHaveBeenCalledTimes(1)
+  })
+
+  it('supports getDerivedStateFromProps', async () => {
+    class Foo extends React.Component {
+      constructor(props) {
+        super(props)
+        this.state = {}
+      }
+      static getDerivedStateFromProps(nextProps) {
+        return {bar: nextProps.baz + '!'}
+      }
+      render() {
+        return <div>{this.state.bar}</div>
+      }
+    }
+    const root = ReactDOM.createRoot(container)
+    root.render(<Foo baz='foo' />)
+    await sleep(10)
+    expect(container.textContent).toBe('foo!')
+    root.render(<Foo baz='bar' />)
+    await sleep(10)
+    expect(container.textContent).toBe('bar!')
+  })
+
+  it('calls the lifecycle methods in order', async () => {
+    const log = []
+    class Foo extends React.Component {
+      componentDidMount() {
+        log.push('didMount ' + this.props.value)
+      }
+      shouldComponentUpdate(nextProps) {
+        log.push('shouldUpdate ' + nextProps.value)
+        return nextProps.value !== 'skip'
+      }
+      getSnapshotBeforeUpdate(prevProps) {
+        log.push('snapshot ' + prevProps.value)
+        return 'snapshot'
+      }
+      componentDidUpdate(prevProps, prevState, snapshot) {
+        log.push('didUpdate ' + prevProps.value + ' ' + snapshot)
+      }
+      componentWillUnmount() {
+        log.push('willUnmount')
+      }
+      render() {
+        return <div>{this.props.value}</div>
+      }
+    }
+    const root = ReactDOM.createRoot(container)
+    root.render(<Foo value='a' />)
+    await sleep(10)
+    root.render(<Foo value='b' />)
+    await sleep(10)
+    root.render(<Foo value='skip' />)
+    await sleep(10)
+    expect(container.textContent).toBe('b')
+    root.render(<div />)
+    await sleep(10)
+    expect(log).toEqual([
+      'didMount a',
+      'shouldUpdate b',
+      'snapshot a',
+      'didUpdate a snapshot',
+      'shouldUpdate skip',
+      'willUnmount',
+    ])
+  })
+
+  it('does not re-render a PureComponent with shallow equal props', async () => {
+    let renderCount = 0
+    class Foo extends React.PureComponent {
+      render() {
+        renderCount++
+        return <div>{this.props.value}</div>
+      }
+    }
+    const root = ReactDOM.createRoot(container)
+    root.render(<Foo value='a' />)
+    await sleep(10)
+    root.render(<Foo value='a' />)
+    await sleep(10)
+    expect(renderCount).toBe(1)
+    root.render(<Foo value='b' />)
+    await sleep(10)
+    expect(renderCount).toBe(2)
+  })
+
+  it('forceUpdate re-renders even if shouldComponentUpdate returns false', async () => {
+    let instance
+    let renderCount = 0
+    class Foo extends React.Component {
+      shouldComponentUpdate() {
+        return false
+      }
+      render() {
+        instance = this
+        renderCount++
+        return <div />
+      }
+    }
+    ReactDOM.createRoot(container).render(<Foo />)
+    await sleep(10)
+    instance.forceUpdate()
+    await sleep(10)
+    expect(renderCount).toBe(2)
+  })
+
+  it('attaches the instance to the ref', async () => {
+    let instance
+    const ref = {current: null}
+    class Foo extends React.Component {
+      render() {
+        instance = this
+        return <div />
+      }
+    }
+    ReactDOM.createRoot(container).render(<Foo ref={ref} />)
+    await sleep(10)
+    expect(ref.current).toBe(instance)
+  })
+})
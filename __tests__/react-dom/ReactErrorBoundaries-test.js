@@ -0,0 +1,186 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactErrorBoundaries', () => {
+  let container
+  let ErrorBoundary
+  let BrokenRender
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+    jest.spyOn(console, 'error').mockImplementation(() => {})
+
+    ErrorBoundary = class extends React.Component {
+      constructor(props) {
+        super(props)
+        this.state = {error: null}
+      }
+      static getDerivedStateFromError(error) {
+        return {error}
+      }
+      render() {
+        if (this.state.error) {
+          return <div>Caught: {this.state.error.message}</div>
+        }
+        return this.props.children
+      }
+    }
+
+    BrokenRender = function BrokenRender() {
+      throw new Error('Hello')
+    }
+  })
+
+  afterEach(() => {
+    console.error.mockRestore()
+  })
+
+  it('renders the fallback when a child throws during mount', async () => {
+    ReactDOM.createRoot(container).render(
+      <ErrorBoundary>
+        <BrokenRender />
+      </ErrorBoundary>
+    )
+    await sleep(10)
+    expect(container.textContent).toBe('Caught: Hello')
+  })
+
+  it('renders the fallback when a child throws during update', async () => {
+    function Child({broken}) {
+      if (broken) {
+        throw new Error('Hello')
+      }
+      return <span>ok</span>
+    }
+    const root = ReactDOM.createRoot(container)
+    root.render(
+      <ErrorBoundary>
+        <Child broken={false} />
+      </ErrorBoundary>
+    )
+    await sleep(10)
+    expect(container.textContent).toBe('ok')
+    root.render(
+      <ErrorBoundary>
+        <Child broken={true} />
+      </ErrorBoundary>
+    )
+    await sleep(10)
+    expect(container.textContent).toBe('Caught: Hello')
+  })
+
+  it('calls componentDidCatch with the component stack', async () => {
+    const log = []
+    class Boundary extends React.Component {
+      constructor(props) {
+        super(props)
+        this.state = {error: null}
+      }
+      componentDidCatch(error, info) {
+        log.push(error.message)
+        log.push(info.componentStack.indexOf('BrokenRender') !== -1)
+        this.setState({error})
+      }
+      render() {
+        return this.state.error ? <div>didCatch</div> : this.props.children
+      }
+    }
+    ReactDOM.createRoot(container).render(
+      <Boundary>
+        <BrokenRender />
+      </Boundary>
+    )
+    await sleep(10)
+    expect(log).toEqual(['Hello', true])
+    expect(container.textContent).toBe('didCatch')
+  })
+
+  it('propagates the error to the outer boundary when the fallback throws', async () => {
+    class BrokenBoundary extends React.Component {
+      constructor(props) {
+        super(props)
+        this.state = {error: null}
+      }
+      static getDerivedStateFromError(error) {
+        return {error}
+      }
+      render() {
+        if (this.state.error) {
+          throw new Error('Fallback')
+        }
+        return this.props.children
+      }
+    }
+    ReactDOM.createRoot(container).render(
+      <ErrorBoundary>
+        <BrokenBoundary>
+          <BrokenRender />
+        </BrokenBoundary>
+      </ErrorBoundary>
+    )
+    await sleep(10)
+    expect(container.textContent).toBe('Caught: Fallback')
+  })
+
+  it('catches errors thrown in lifecycles and effects', async () => {
+    class BrokenDidMount extends React.Component {
+      componentDidMount() {
+        throw new Error('didMount')
+      }
+      render() {
+        return <span>mounted</span>
+      }
+    }
+    function BrokenEffect() {
+      React.useEffect(() => {
+        throw new Error('effect')
+      }, [])
+      return <span>effect</span>
+    }
+    const container2 = document.createElement('div')
+    ReactDOM.createRoot(container).render(
+      <ErrorBoundary>
+        <BrokenDidMount />
+      </ErrorBoundary>
+    )
+    ReactDOM.createRoot(container2).render(
+      <ErrorBoundary>
+        <BrokenEffect />
+      </ErrorBoundary>
+    )
+    await sleep(50)
+    expect(container.textContent).toBe('Caught: didMount')
+    expect(container2.textContent).toBe('Caught: effect')
+  })
+
+  it('unmounts the root and reports the error when there is no boundary', async () => {
+    const root = ReactDOM.createRoot(container)
+    root.render(<div>before</div>)
+    await sleep(10)
+    expect(container.textContent).toBe('before')
+    root.render(<BrokenRender />)
+    await sleep(10)
+    expect(container.textContent).toBe('')
+    expect(console.error).toHaveBeenCalled()
+  })
+})
@@ -0,0 +1,128 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('forwardRef', () => {
+  let container
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+  })
+
+  it('forwards the ref to the inner host component', async () => {
+    const FancyInput = React.forwardRef((props, ref) => (
+      <input ref={ref} value={props.value} />
+    ))
+    const ref = {current: null}
+    ReactDOM.createRoot(container).render(<FancyInput ref={ref} value='a' />)
+    await sleep(10)
+    expect(ref.current).toBe(container.firstChild)
+  })
+
+  it('does not pass the ref in the props', async () => {
+    let receivedProps
+    const Foo = React.forwardRef((props) => {
+      receivedProps = props
+      return null
+    })
+    ReactDOM.createRoot(container).render(<Foo ref={{current: null}} a={1} />)
+    await sleep(10)
+    expect(receivedProps).toEqual({a: 1})
+  })
+
+  it('works with memo', async () => {
+    let renderCount = 0
+    const Foo = React.memo(
+      React.forwardRef((props, ref) => {
+        renderCount++
+        return <span ref={ref}>{props.text}</span>
+      })
+    )
+    const ref = {current: null}
+    const root = ReactDOM.createRoot(container)
+    root.render(<Foo ref={ref} text='a' />)
+    await sleep(10)
+    expect(ref.current).toBe(container.firstChild)
+
+    root.render(<Foo ref={ref} text='a' />)
+    await sleep(10)
+    expect(renderCount).toBe(1)
+  })
+
+  describe('useImperativeHandle', () => {
+    it('attaches the handle to an object ref', async () => {
+      const Foo = React.forwardRef((props, ref) => {
+        React.useImperativeHandle(ref, () => ({value: props.value}), [
+          props.value,
+        ])
+        return null
+      })
+      const ref = {current: null}
+      const root = ReactDOM.createRoot(container)
+      root.render(<Foo ref={ref} value={1} />)
+      await sleep(10)
+      expect(ref.current).toEqual({value: 1})
+
+      root.render(<Foo ref={ref} value={2} />)
+      await sleep(10)
+      expect(ref.current).toEqual({value: 2})
+
+      root.render(<div />)
+      await sleep(10)
+      expect(ref.current).toBe(null)
+    })
+
+    it('calls a callback ref with the handle and null on unmount', async () => {
+      const log = []
+      const Foo = React.forwardRef((props, ref) => {
+        React.useImperativeHandle(ref, () => 'handle', [])
+        return null
+      })
+      const root = ReactDOM.createRoot(container)
+      root.render(<Foo ref={(value) => log.push(value)} />)
+      await sleep(10)
+      expect(log).toEqual(['handle'])
+
+      root.render(<div />)
+      await sleep(10)
+      expect(log).toEqual(['handle', null])
+    })
+
+    it('is available in the layout effects of the parent', async () => {
+      let value
+      const Child = React.forwardRef((props, ref) => {
+        React.useImperativeHandle(ref, () => ({focus: () => 'focused'}))
+        return null
+      })
+      function Parent() {
+        const ref = React.useRef(null)
+        React.useLayoutEffect(() => {
+          value = ref.current.focus()
+        }, [])
+        return <Child ref={ref} />
+      }
+      ReactDOM.createRoot(container).render(<Parent />)
+      await sleep(10)
+      expect(value).toBe('focused')
+    })
+  })
+})
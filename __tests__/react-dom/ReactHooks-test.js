@@ -0,0 +1,469 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactHooks', () => {
+  let container
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+  })
+
+  describe('useReducer', () => {
+    function reducer(state, action) {
+      switch (action.type) {
+        case 'increment':
+          return {count: state.count + 1}
+        case 'decrement':
+          return {count: state.count - 1}
+        default:
+          return state
+      }
+    }
+
+    it('computes the state with the reducer', async () => {
+      let dispatch
+      function Counter() {
+        const [state, _dispatch] = React.useReducer(reducer, {count: 0})
+        dispatch = _dispatch
+        return <span>{state.count}</span>
+      }
+      ReactDOM.createRoot(container).render(<Counter />)
+      await sleep(10)
+      expect(container.textContent).toBe('0')
+
+      dispatch({type: 'increment'})
+      dispatch({type: 'increment'})
+      await sleep(10)
+      expect(container.textContent).toBe('2')
+
+      dispatch({type: 'decrement'})
+      await sleep(10)
+      expect(container.textContent).toBe('1')
+    })
+
+    it('lazily initializes the state with init', async () => {
+      const init = jest.fn((initialCount) => ({count: initialCount * 10}))
+      let dispatch
+      function Counter({initialCount}) {
+        const [state, _dispatch] = React.useReducer(reducer, initialCount, init)
+        dispatch = _dispatch
+        return <span>{state.count}</span>
+      }
+      ReactDOM.createRoot(container).render(<Counter initialCount={1} />)
+      await sleep(10)
+      expect(container.textContent).toBe('10')
+
+      dispatch({type: 'increment'})
+      await sleep(10)
+      expect(container.textContent).toBe('11')
+      expect(init).toHaveBeenCalledTimes(1)
+    })
+
+    it('bails out when the reducer returns the same state', async () => {
+      let dispatch
+      let renderCount = 0
+      function Counter() {
+        const [state, _dispatch] = React.useReducer(reducer, {count: 0})
+        dispatch = _dispatch
+        renderCount++
+        return <span>{state.count}</span>
+      }
+      ReactDOM.createRoot(container).render(<Counter />)
+      await sleep(10)
+      expect(renderCount).toBe(1)
+
+      dispatch({type: 'unknown'})
+      await sleep(10)
+      expect(renderCount).toBe(1)
+      expect(container.textContent).toBe('0')
+    })
+
+    it('uses the reducer of the current render', async () => {
+      let dispatch
+      function Counter({step}) {
+        const [count, _dispatch] = React.useReducer(
+          (state, action) => state + action * step,
+          0
+        )
+        dispatch = _dispatch
+        return <span>{count}</span>
+      }
+      const root = ReactDOM.createRoot(container)
+      root.render(<Counter step={1} />)
+      await sleep(10)
+      root.render(<Counter step={10} />)
+      await sleep(10)
+
+      dispatch(1)
+      await sleep(10)
+      expect(container.textContent).toBe('10')
+    })
+  })
+
+  describe('useLayoutEffect', () => {
+    it('fires after mutation and before passive effects', async () => {
+      const log = []
+      function Foo({value}) {
+        React.useLayoutEffect(() => {
+          log.push('layout create ' + container.textContent)
+          return () => log.push('layout destroy ' + container.textContent)
+        }, [value])
+        React.useEffect(() => {
+          log.push('passive create')
+          return () => log.push('passive destroy')
+        }, [value])
+        return <span>{value}</span>
+      }
+      const root = ReactDOM.createRoot(container)
+      root.render(<Foo value='a' />)
+      await sleep(10)
+      expect(log).toEqual(['layout create a', 'passive create'])
+
+      log.length = 0
+      root.render(<Foo value='b' />)
+      await sleep(10)
+      expect(log).toEqual([
+        'layout destroy b',
+        'layout create b',
+        'passive destroy',
+        'passive create',
+      ])
+
+      log.length = 0
+      root.render(<div />)
+      await sleep(10)
+      expect(log[0]).toBe('layout destroy b')
+    })
+
+    it('flushes the updates scheduled in layout effects synchronously', async () => {
+      const log = []
+      function Foo() {
+        const [width, setWidth] = React.useState(0)
+        React.useLayoutEffect(() => {
+          setWidth(100)
+        }, [])
+        log.push(width)
+        return <span>{width}</span>
+      }
+      ReactDOM.createRoot(container).render(<Foo />)
+      await sleep(10)
+      expect(container.textContent).toBe('100')
+      expect(log).toEqual([0, 100])
+    })
+
+    it('flushes the layout update before the passive effects run', async () => {
+      const log = []
+      function Foo() {
+        const [value, setValue] = React.useState('initial')
+        React.useLayoutEffect(() => {
+          setValue('layout')
+        }, [])
+        React.useEffect(() => {
+          log.push(container.textContent)
+        }, [])
+        return <span>{value}</span>
+      }
+      ReactDOM.createRoot(container).render(<Foo />)
+      await sleep(10)
+      expect(log).toEqual(['layout'])
+    })
+  })
+
+  describe('useInsertionEffect', () => {
+    it('fires before the layout effects', async () => {
+      const log = []
+      function Foo({value}) {
+        React.useInsertionEffect(() => {
+          log.push('insertion create ' + value)
+          return () => log.push('insertion destroy ' + value)
+        }, [value])
+        React.useLayoutEffect(() => {
+          log.push('layout create ' + value)
+          return () => log.push('layout destroy ' + value)
+        }, [value])
+        return <span>{value}</span>
+      }
+      const root = ReactDOM.createRoot(container)
+      root.render(<Foo value='a' />)
+      await sleep(10)
+      expect(log).toEqual(['insertion create a', 'layout create a'])
+
+      log.length = 0
+      root.render(<Foo value='b' />)
+      await sleep(10)
+      expect(log).toEqual([
+        'insertion destroy a',
+        'insertion create b',
+        'layout destroy a',
+        'layout create b',
+      ])
+
+      log.length = 0
+      root.render(<div />)
+      await sleep(10)
+      expect(log).toEqual(['insertion destroy b', 'layout destroy b'])
+    })
+
+    it('forbids state updates', async () => {
+      let error
+      function Foo() {
+        const [, setState] = React.useState(0)
+        React.useInsertionEffect(() => {
+          try {
+            setState(1)
+          } catch (e) {
+            error = e
+          }
+        }, [])
+        return <span />
+      }
+      ReactDOM.createRoot(container).render(<Foo />)
+      await sleep(10)
+      expect(error.message).toBe('useInsertionEffect must not schedule updates.')
+    })
+  })
+
+  describe('useId', () => {
+    it('generates ids from the tree position in the first render', async () => {
+      const ids = []
+      function Child() {
+        const id = React.useId()
+        ids.push(id)
+        return <label htmlFor={id} />
+      }
+      function App() {
+        return (
+          <div>
+            <Child />
+            <Child />
+          </div>
+        )
+      }
+      ReactDOM.createRoot(container).render(<App />)
+      await sleep(10)
+      expect(ids.length).toBe(2)
+      expect(ids[0]).toMatch(/^:R[0-9a-v]+:$/)
+      expect(ids[0]).not.toBe(ids[1])
+
+      const container2 = document.createElement('div')
+      ReactDOM.createRoot(container2).render(<App />)
+      await sleep(10)
+      expect(ids.slice(2)).toEqual(ids.slice(0, 2))
+    })
+
+    it('distinguishes the ids in the same component', async () => {
+      let ids
+      function Foo() {
+        ids = [React.useId(), React.useId()]
+        return null
+      }
+      ReactDOM.createRoot(container).render(<Foo />)
+      await sleep(10)
+      expect(ids[0]).not.toBe(ids[1])
+      expect(ids[1]).toMatch(/H1:$/)
+    })
+
+    it('keeps the id stable across updates', async () => {
+      let id
+      let setCount
+      function Foo() {
+        const [count, _setCount] = React.useState(0)
+        setCount = _setCount
+        id = React.useId()
+        return <span>{count}</span>
+      }
+      ReactDOM.createRoot(container).render(<Foo />)
+      await sleep(10)
+      const firstId = id
+      setCount(1)
+      await sleep(10)
+      expect(id).toBe(firstId)
+    })
+
+    it('uses the client counter for the components mounted later', async () => {
+      const ids = []
+      function Child() {
+        ids.push(React.useId())
+        return null
+      }
+      const root = ReactDOM.createRoot(container)
+      root.render(<div />)
+      await sleep(10)
+      root.render(
+        <div>
+          <Child />
+          <Child />
+        </div>
+      )
+      await sleep(10)
+      expect(ids[0]).toMatch(/^:r[0-9a-v]+:$/)
+      expect(ids[0]).not.toBe(ids[1])
+    })
+
+    it('prepends the identifierPrefix option of createRoot', async () => {
+      let id
+      function Foo() {
+        id = React.useId()
+        return null
+      }
+      ReactDOM.createRoot(container, {identifierPrefix: 'app-'}).render(<Foo />)
+      await sleep(10)
+      expect(id.startsWith(':app-')).toBe(true)
+    })
+  })
+
+  describe('useSyncExternalStore', () => {
+    function createExternalStore(initialState) {
+      const listeners = new Set()
+      let currentState = initialState
+      return {
+        set(state) {
+          currentState = state
+          listeners.forEach((listener) => listener())
+        },
+        subscribe(listener) {
+          listeners.add(listener)
+          return () => listeners.delete(listener)
+        },
+        getState() {
+          return currentState
+        },
+        getSubscriberCount() {
+          return listeners.size
+        },
+      }
+    }
+
+    it('renders the snapshot and updates when the store changes', async () => {
+      const store = createExternalStore('a')
+      function App() {
+        const text = React.useSyncExternalStore(store.subscribe, store.getState)
+        return <span>{text}</span>
+      }
+      const root = ReactDOM.createRoot(container)
+      root.render(<App />)
+      await sleep(10)
+      expect(container.textContent).toBe('a')
+      expect(store.getSubscriberCount()).toBe(1)
+
+      store.set('b')
+      await sleep(10)
+      expect(container.textContent).toBe('b')
+
+      root.render(<div />)
+      await sleep(10)
+      expect(store.getSubscriberCount()).toBe(0)
+    })
+
+    it('re-renders when the store changes before the subscription', async () => {
+      const store = createExternalStore('a')
+      function App() {
+        const text = React.useSyncExternalStore(store.subscribe, store.getState)
+        React.useLayoutEffect(() => {
+          if (text === 'a') {
+            store.set('b')
+          }
+        }, [text])
+        return <span>{text}</span>
+      }
+      ReactDOM.createRoot(container).render(<App />)
+      await sleep(10)
+      expect(container.textContent).toBe('b')
+    })
+
+    it('resubscribes when the subscribe function changes', async () => {
+      const storeA = createExternalStore('a')
+      const storeB = createExternalStore('b')
+      function App({store}) {
+        const text = React.useSyncExternalStore(store.subscribe, store.getState)
+        return <span>{text}</span>
+      }
+      const root = ReactDOM.createRoot(container)
+      root.render(<App store={storeA} />)
+      await sleep(10)
+      root.render(<App store={storeB} />)
+      await sleep(10)
+      expect(container.textContent).toBe('b')
+      expect(storeA.getSubscriberCount()).toBe(0)
+      expect(storeB.getSubscriberCount()).toBe(1)
+    })
+  })
+
+  describe('useDeferredValue', () => {
+    it('renders the previous value urgently and the new value later', async () => {
+      const log = []
+      let setText
+      function App() {
+        const [text, _setText] = React.useState('a')
+        setText = _setText
+        const deferredText = React.useDeferredValue(text)
+        log.push(text + deferredText)
+        return <span>{deferredText}</span>
+      }
+      ReactDOM.createRoot(container).render(<App />)
+      await sleep(10)
+      expect(log).toEqual(['aa'])
+
+      log.length = 0
+      setText('b')
+      await sleep(50)
+      expect(log).toEqual(['ba', 'bb'])
+      expect(container.textContent).toBe('b')
+    })
+
+    it('renders the initial value first when it is provided', async () => {
+      const log = []
+      function App() {
+        const value = React.useDeferredValue('final', 'initial')
+        log.push(value)
+        return <span>{value}</span>
+      }
+      ReactDOM.createRoot(container).render(<App />)
+      await sleep(50)
+      expect(log).toEqual(['initial', 'final'])
+      expect(container.textContent).toBe('final')
+    })
+
+    it('does not defer the value in a transition', async () => {
+      const log = []
+      let startTransition
+      let setText
+      function App() {
+        const [text, _setText] = React.useState('a')
+        const [, _startTransition] = React.useTransition()
+        setText = _setText
+        startTransition = _startTransition
+        log.push(text + React.useDeferredValue(text))
+        return null
+      }
+      ReactDOM.createRoot(container).render(<App />)
+      await sleep(10)
+
+      log.length = 0
+      startTransition(() => setText('b'))
+      await sleep(50)
+      // The pending state is rendered urgently, the text is rendered in the transition
+      expect(log).toEqual(['aa', 'bb'])
+    })
+  })
+})
@@ -0,0 +1,117 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('ReactRefs', () => {
+  let container
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+  })
+
+  it('creates a sealed object ref with createRef', () => {
+    const ref = React.createRef()
+    expect(ref).toEqual({current: null})
+    expect(Object.isSealed(ref)).toBe(true)
+  })
+
+  it('attaches the object ref created by createRef', async () => {
+    class Foo extends React.Component {
+      constructor(props) {
+        super(props)
+        this.divRef = React.createRef()
+      }
+      render() {
+        return <div ref={this.divRef} />
+      }
+    }
+    let instance
+    ReactDOM.createRoot(container).render(<Foo ref={(i) => (instance = i)} />)
+    await sleep(10)
+    expect(instance.divRef.current).toBe(container.firstChild)
+  })
+
+  it('calls the cleanup returned by a callback ref instead of null', async () => {
+    const log = []
+    const ref = (node) => {
+      log.push('attach ' + node.tagName)
+      return () => log.push('cleanup')
+    }
+    const root = ReactDOM.createRoot(container)
+    root.render(<div ref={ref} />)
+    await sleep(10)
+    expect(log).toEqual(['attach DIV'])
+
+    root.render(<span />)
+    await sleep(10)
+    expect(log).toEqual(['attach DIV', 'cleanup'])
+  })
+
+  it('calls the cleanup when the callback ref changes', async () => {
+    const log = []
+    function createRef(name) {
+      return (node) => {
+        log.push(name + ' ' + (node && node.tagName))
+        return () => log.push(name + ' cleanup')
+      }
+    }
+    const root = ReactDOM.createRoot(container)
+    root.render(<div ref={createRef('a')} />)
+    await sleep(10)
+    root.render(<div ref={createRef('b')} />)
+    await sleep(10)
+    expect(log).toEqual(['a DIV', 'a cleanup', 'b DIV'])
+  })
+
+  it('still calls a callback ref without cleanup with null', async () => {
+    const log = []
+    const root = ReactDOM.createRoot(container)
+    root.render(<div ref={(node) => log.push(node && node.tagName)} />)
+    await sleep(10)
+    root.render(null)
+    await sleep(10)
+    expect(log).toEqual(['DIV', null])
+  })
+
+  it('attaches the public instance of the host config to the refs', async () => {
+    const ReactNoop = require('../../dist/react-noop')
+    const objectRef = React.createRef()
+    const log = []
+    const root = ReactNoop.createRoot()
+    root.render(
+      <div ref={objectRef}>
+        <span ref={(instance) => log.push(instance)} />
+      </div>
+    )
+    await sleep(10)
+    // The instances of react-noop are plain objects instead of DOM nodes
+    expect(objectRef.current.type).toBe('div')
+    expect(log.length).toBe(1)
+    expect(log[0].type).toBe('span')
+    expect(objectRef.current.children[0]).toBe(log[0])
+
+    root.render(null)
+    await sleep(10)
+    expect(objectRef.current).toBe(null)
+    expect(log[1]).toBe(null)
+  })
+})
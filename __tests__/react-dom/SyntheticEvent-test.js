@@ -0,0 +1,187 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+let ReactDOM
+
+function sleep(ms) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, ms)
+  })
+}
+
+describe('SyntheticEvent', () => {
+  let container
+
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+    ReactDOM = require('../../dist/react-dom')
+    container = document.createElement('div')
+    document.body.appendChild(container)
+  })
+
+  afterEach(() => {
+    document.body.removeChild(container)
+  })
+
+  it('should dispatch capture and bubble handlers for non-click events', async () => {
+    const log = []
+    ReactDOM.createRoot(container).render(
+      <div
+        onKeyDownCapture={() => log.push('outer capture')}
+        onKeyDown={() => log.push('outer bubble')}>
+        <input
+          onKeyDownCapture={() => log.push('inner capture')}
+          onKeyDown={() => log.push('inner bubble')}
+        />
+      </div>
+    )
+    await sleep(10)
+    container
+      .querySelector('input')
+      .dispatchEvent(new KeyboardEvent('keydown', {bubbles: true}))
+    expect(log).toEqual([
+      'outer capture',
+      'inner capture',
+      'inner bubble',
+      'outer bubble',
+    ])
+  })
+
+  it('should map focusin and focusout to onFocus and onBlur', async () => {
+    const log = []
+    ReactDOM.createRoot(container).render(
+      <div onFocus={() => log.push('focus')} onBlur={() => log.push('blur')}>
+        <input />
+      </div>
+    )
+    await sleep(10)
+    const input = container.querySelector('input')
+    input.dispatchEvent(new FocusEvent('focusin', {bubbles: true}))
+    input.dispatchEvent(new FocusEvent('focusout', {bubbles: true}))
+    expect(log).toEqual(['focus', 'blur'])
+  })
+
+  it('should not bubble onScroll to ancestors', async () => {
+    const log = []
+    ReactDOM.createRoot(container).render(
+      <div onScroll={() => log.push('outer')}>
+        <div onScroll={() => log.push('inner')} />
+      </div>
+    )
+    await sleep(10)
+    container.firstChild.firstChild.dispatchEvent(
+      new Event('scroll', {bubbles: false})
+    )
+    expect(log).toEqual(['inner'])
+  })
+
+  it('should only listen once per root container', async () => {
+    const log = []
+    const root = ReactDOM.createRoot(container)
+    root.render(<button onClick={() => log.push('click')} />)
+    await sleep(10)
+    root.render(<button onClick={() => log.push('click')} />)
+    await sleep(10)
+    container.firstChild.click()
+    expect(log).toEqual(['click'])
+  })
+
+  it('should not dispatch events after the root is unmounted', async () => {
+    const log = []
+    const root = ReactDOM.createRoot(container)
+    root.render(<button onClick={() => log.push('click')} />)
+    await sleep(10)
+    const button = container.firstChild
+    root.unmount()
+    container.appendChild(button)
+    button.click()
+    expect(log).toEqual([])
+  })
+
+  it('should not dispatch nested root events twice', async () => {
+    const log = []
+    ReactDOM.createRoot(container).render(
+      <div onClick={() => log.push('outer')}>
+        <div id="inner-container" />
+      </div>
+    )
+    await sleep(10)
+    const innerContainer = container.querySelector('#inner-container')
+    ReactDOM.createRoot(innerContainer).render(
+      <button onClick={() => log.push('inner')} />
+    )
+    await sleep(10)
+    innerContainer.firstChild.click()
+    expect(log).toEqual(['inner', 'outer'])
+  })
+
+  it('should set currentTarget and expose the native event', async () => {
+    const log = []
+    ReactDOM.createRoot(container).render(
+      <div
+        id="outer"
+        onClickCapture={(e) => log.push(['capture', e.currentTarget.id])}
+        onClick={(e) => {
+          log.push(['bubble', e.currentTarget.id])
+          log.push(['target', e.target.id])
+          log.push(['native', e.nativeEvent instanceof MouseEvent])
+          log.push(['clientX', e.clientX])
+        }}>
+        <button id="inner" onClick={(e) => log.push(['bubble', e.currentTarget.id])} />
+      </div>
+    )
+    await sleep(10)
+    container
+      .querySelector('button')
+      .dispatchEvent(new MouseEvent('click', {bubbles: true, clientX: 5}))
+    expect(log).toEqual([
+      ['capture', 'outer'],
+      ['bubble', 'inner'],
+      ['bubble', 'outer'],
+      ['target', 'inner'],
+      ['native', true],
+      ['clientX', 5],
+    ])
+  })
+
+  it('should track stopPropagation and preventDefault', async () => {
+    const log = []
+    let syntheticEvent
+    ReactDOM.createRoot(container).render(
+      <div onClick={() => log.push('outer')}>
+        <a
+          href="#"
+          onClick={(e) => {
+            syntheticEvent = e
+            expect(e.isPropagationStopped()).toBe(false)
+            expect(e.isDefaultPrevented()).toBe(false)
+            e.stopPropagation()
+            e.preventDefault()
+            log.push('inner')
+          }}
+        />
+      </div>
+    )
+    await sleep(10)
+    const nativeEvent = new MouseEvent('click', {
+      bubbles: true,
+      cancelable: true,
+    })
+    container.querySelector('a').dispatchEvent(nativeEvent)
+    expect(log).toEqual(['inner'])
+    expect(syntheticEvent.isPropagationStopped()).toBe(true)
+    expect(syntheticEvent.isDefaultPrevented()).toBe(true)
+    expect(nativeEvent.defaultPrevented).toBe(true)
+    expect(syntheticEvent.currentTarget).toBe(null)
+  })
+})
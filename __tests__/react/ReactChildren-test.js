@@ -0,0 +1,114 @@
+/**
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @emails react-core
+ */
+
+'use strict'
+
+let React
+
+describe('ReactChildren', () => {
+  beforeEach(() => {
+    jest.resetModules()
+    React = require('../../dist/react')
+  })
+
+  it('should support identity for simple', () => {
+    const context = {}
+    const callback = jest.fn().mockImplementation(function (kid, index) {
+      expect(this).toBe(context)
+      return kid
+    })
+
+    const simpleKid = <span key="simple" />
+    const instance = <div>{simpleKid}</div>
+    React.Children.forEach(instance.props.children, callback, context)
+    expect(callback).toHaveBeenCalledWith(simpleKid, 0)
+    callback.mockClear()
+    const mappedChildren = React.Children.map(
+      instance.props.children,
+      callback,
+      context
+    )
+    expect(callback).toHaveBeenCalledWith(simpleKid, 0)
+    expect(mappedChildren[0]).toEqual(<span key=".$simple" />)
+  })
+
+  it('should escape the keys of nested arrays', () => {
+    const zero = <div key="keyZero" />
+    const one = <div key="keyOne" />
+    const two = <div />
+    const three = <div key="a:b=c" />
+    const mapped = React.Children.map(
+      [[zero, one], [two], three],
+      (kid) => kid
+    )
+    expect(mapped.map((kid) => kid.key)).toEqual([
+      '.0:$keyZero',
+      '.0:$keyOne',
+      '.1:0',
+      '.$a=2b=0c',
+    ])
+  })
+
+  it('should prefix the key returned by the callback', () => {
+    const mapped = React.Children.map([<div key="a" />], (kid) => (
+      <span key="b/c" />
+    ))
+    expect(mapped[0].key).toBe('b//c/.$a')
+
+    const nested = React.Children.map([<div key="a" />], (kid) => [
+      <span key="x" />,
+      <span key="y" />,
+    ])
+    expect(nested.map((kid) => kid.key)).toEqual(['.$a/.$x', '.$a/.$y'])
+  })
+
+  it('should count and convert the children', () => {
+    const children = [<div key="a" />, null, 'text', [<span key="b" />, 1]]
+    expect(React.Children.count(children)).toBe(5)
+    expect(React.Children.count(null)).toBe(0)
+    expect(React.Children.toArray(null)).toEqual([])
+    expect(React.Children.toArray(children).map((kid) => kid.key)).toEqual([
+      '.$a',
+      undefined,
+      '.3:$b',
+      undefined,
+    ])
+  })
+
+  it('should only accept a single element in Children.only', () => {
+    const child = <div />
+    expect(React.Children.only(child)).toBe(child)
+    expect(() => React.Children.only([child, <span />])).toThrow(
+      'React.Children.only expected to receive a single React element child.'
+    )
+    expect(() => React.Children.only(null)).toThrow()
+  })
+
+  it('should keep the key and ref in cloneElement unless overridden', () => {
+    const ref = React.createRef()
+    const element = <div key="a" ref={ref} className="foo" />
+    const clone = React.cloneElement(element, {id: 'bar'})
+    expect(clone.key).toBe('a')
+    expect(clone.ref).toBe(ref)
+    expect(clone.props).toEqual({className: 'foo', id: 'bar'})
+
+    const override = React.cloneElement(element, {key: 'b', ref: null})
+    expect(override.key).toBe('b')
+    expect(override.ref).toBe(null)
+  })
+
+  it('should replace the children in cloneElement', () => {
+    const element = <div>old</div>
+    expect(React.cloneElement(element, null, 'new').props.children).toBe('new')
+    expect(
+      React.cloneElement(element, null, 'a', 'b').props.children
+    ).toEqual(['a', 'b'])
+    expect(React.cloneElement(element).props.children).toBe('old')
+  })
+})
@@ -0,0 +1,45 @@
+use web_sys::Element;
+
+use shared::derive_from_js_value;
+
+use crate::input_value_tracking::update_value_if_changed;
+
+// Input types which don't have a value that the user can change
+static UNSUPPORTED_INPUT_TYPES: [&str; 5] = ["button", "hidden", "image", "reset", "submit"];
+
+// Return the native event which triggers onChange for the target element
+fn get_target_change_event_type(target: &Element) -> Option<&'static str> {
+    match target.local_name().as_str() {
+        "select" => Some("change"),
+        "textarea" => Some("input"),
+        "input" => {
+            let _type = derive_from_js_value(target, "type")
+                .as_string()
+                .unwrap_or_default()
+                .to_lowercase();
+            match _type.as_str() {
+                "checkbox" | "radio" => Some("click"),
+                "file" => Some("change"),
+                _ if UNSUPPORTED_INPUT_TYPES.contains(&_type.as_str()) => None,
+                _ => Some("input"),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Whether the native event should be treated as a change of a form element
+pub fn is_change_event_target(target: &Element, event_type: &str) -> bool {
+    get_target_change_event_type(target) == Some(event_type)
+}
+
+// onChange only fires when the value has been changed by the user
+pub fn should_dispatch_change_event(target: &Element, event_type: &str) -> bool {
+    if !is_change_event_target(target, event_type) {
+        return false;
+    }
+    match target.local_name().as_str() {
+        "input" | "textarea" => update_value_if_changed(target),
+        _ => true,
+    }
+}
@@ -0,0 +1,140 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::js_sys::{Object, Reflect};
+use web_sys::{CssStyleDeclaration, Element};
+
+use shared::{derive_from_js_value, to_string, type_of};
+
+// CSS properties which accept numbers but are not in units of "px"
+static UNITLESS_NUMBERS: [&str; 43] = [
+    "animationIterationCount",
+    "aspectRatio",
+    "borderImageOutset",
+    "borderImageSlice",
+    "borderImageWidth",
+    "boxFlex",
+    "boxFlexGroup",
+    "boxOrdinalGroup",
+    "columnCount",
+    "columns",
+    "flex",
+    "flexGrow",
+    "flexPositive",
+    "flexShrink",
+    "flexNegative",
+    "flexOrder",
+    "gridArea",
+    "gridRow",
+    "gridRowEnd",
+    "gridRowSpan",
+    "gridRowStart",
+    "gridColumn",
+    "gridColumnEnd",
+    "gridColumnSpan",
+    "gridColumnStart",
+    "fontWeight",
+    "lineClamp",
+    "lineHeight",
+    "opacity",
+    "order",
+    "orphans",
+    "tabSize",
+    "widows",
+    "zIndex",
+    "zoom",
+    // SVG-related properties
+    "fillOpacity",
+    "floodOpacity",
+    "stopOpacity",
+    "strokeDasharray",
+    "strokeDashoffset",
+    "strokeMiterlimit",
+    "strokeOpacity",
+    "strokeWidth",
+];
+
+static VENDOR_PREFIXES: [&str; 4] = ["Webkit", "ms", "Moz", "O"];
+
+fn is_unitless_number(style_name: &str) -> bool {
+    if UNITLESS_NUMBERS.contains(&style_name) {
+        return true;
+    }
+    // WebkitFlex -> flex
+    for prefix in VENDOR_PREFIXES {
+        if let Some(rest) = style_name.strip_prefix(prefix) {
+            let mut chars = rest.chars();
+            if let Some(first) = chars.next() {
+                if first.is_ascii_uppercase() {
+                    let unprefixed = format!("{}{}", first.to_ascii_lowercase(), chars.as_str());
+                    return UNITLESS_NUMBERS.contains(&unprefixed.as_str());
+                }
+            }
+        }
+    }
+    false
+}
+
+pub fn is_custom_property(style_name: &str) -> bool {
+    style_name.starts_with("--")
+}
+
+/**
+ * Convert a value into the proper css writable value, add "px" to numeric
+ * values unless the property is unitless.
+ */
+pub fn dangerous_style_value(name: &str, value: &JsValue, is_custom_property: bool) -> String {
+    if value.is_null() || value.is_undefined() || type_of(value, "boolean") {
+        return "".to_string();
+    }
+
+    if let Some(number) = value.as_f64() {
+        if !is_custom_property && number != 0.0 && !is_unitless_number(name) {
+            return format!("{}px", to_string(value));
+        }
+    }
+
+    to_string(value).trim().to_string()
+}
+
+fn get_style(node: &Element) -> Option<CssStyleDeclaration> {
+    Reflect::get(node, &"style".into())
+        .ok()
+        .and_then(|style| style.dyn_into::<CssStyleDeclaration>().ok())
+}
+
+/**
+ * Sets the value for multiple styles on a node. If a value is "", the
+ * corresponding style property will be cleared.
+ */
+pub fn set_value_for_styles(node: &Element, styles: &JsValue) {
+    let style = match get_style(node) {
+        Some(style) => style,
+        None => return,
+    };
+    let styles_object = match styles.dyn_ref::<Object>() {
+        Some(styles_object) => styles_object,
+        None => return,
+    };
+
+    for style_name in Object::keys(styles_object).iter() {
+        let style_name = style_name.as_string().unwrap();
+        let is_custom_property = is_custom_property(&style_name);
+        let style_value = dangerous_style_value(
+            &style_name,
+            &derive_from_js_value(styles, &style_name),
+            is_custom_property,
+        );
+        if is_custom_property {
+            style
+                .set_property(&style_name, &style_value)
+                .expect("TODO: panic set_property");
+        } else {
+            let style_name = if style_name == "float" {
+                "cssFloat"
+            } else {
+                style_name.as_str()
+            };
+            Reflect::set(&style, &style_name.into(), &style_value.into())
+                .expect("TODO: panic set style");
+        }
+    }
+}
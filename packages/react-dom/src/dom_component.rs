@@ -0,0 +1,308 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::js_sys::{Array, Object, Reflect};
+use web_sys::Element;
+
+use shared::{derive_from_js_value, is_dev, to_string, type_of};
+
+use crate::css_property_operations::set_value_for_styles;
+use crate::dom_input::{
+    init_input, restore_controlled_input_state, update_input, INPUT_CONTROLLED_PROPS,
+};
+use crate::dom_property::{is_custom_component, set_value_for_property};
+use crate::dom_select::{
+    init_select, restore_controlled_select_state, update_select, SELECT_CONTROLLED_PROPS,
+};
+use crate::dom_textarea::{
+    init_textarea, restore_controlled_textarea_state, update_textarea, TEXTAREA_CONTROLLED_PROPS,
+};
+use crate::utils::is_nullish;
+
+static STYLE: &str = "style";
+static CHILDREN: &str = "children";
+static DANGEROUSLY_SET_INNER_HTML: &str = "dangerouslySetInnerHTML";
+static HTML: &str = "__html";
+
+fn is_text_child(children: &JsValue) -> bool {
+    type_of(children, "string") || type_of(children, "number")
+}
+
+fn get_inner_html(dangerously_set_inner_html: &JsValue) -> JsValue {
+    if dangerously_set_inner_html.is_object() {
+        derive_from_js_value(dangerously_set_inner_html, HTML)
+    } else {
+        JsValue::undefined()
+    }
+}
+
+// Whether the children of the element are set as its text content or inner html
+pub fn should_set_text_content(tag: &str, props: &JsValue) -> bool {
+    tag == "textarea"
+        || tag == "noscript"
+        || is_text_child(&derive_from_js_value(props, CHILDREN))
+        || !is_nullish(&get_inner_html(&derive_from_js_value(
+            props,
+            DANGEROUSLY_SET_INNER_HTML,
+        )))
+}
+
+fn assert_valid_props(props: &JsValue) {
+    let dangerously_set_inner_html = derive_from_js_value(props, DANGEROUSLY_SET_INNER_HTML);
+    if is_nullish(&dangerously_set_inner_html) {
+        return;
+    }
+    if !is_nullish(&derive_from_js_value(props, CHILDREN)) {
+        panic!("Can only set one of `children` or `props.dangerouslySetInnerHTML`.");
+    }
+    if !dangerously_set_inner_html.is_object()
+        || !dangerously_set_inner_html
+            .unchecked_ref::<Object>()
+            .has_own_property(&HTML.into())
+    {
+        panic!(
+            "{}",
+            "`props.dangerouslySetInnerHTML` must be in the form `{__html: ...}`. \
+             Please visit https://reactjs.org/link/dangerously-set-inner-html for more information."
+        );
+    }
+}
+
+fn set_text_child(dom_element: &Element, tag: &str, children: &JsValue) {
+    // The children of textarea are its initial value
+    if tag != "textarea" && is_text_child(children) {
+        dom_element.set_text_content(Some(to_string(children).as_str()));
+    }
+}
+
+fn set_inner_html(dom_element: &Element, dangerously_set_inner_html: &JsValue) {
+    let html = get_inner_html(dangerously_set_inner_html);
+    if !is_nullish(&html) {
+        dom_element.set_inner_html(to_string(&html).as_str());
+    }
+}
+
+fn own_keys(props: &JsValue) -> Vec<String> {
+    match props.dyn_ref::<Object>() {
+        Some(object) => Object::keys(object)
+            .iter()
+            .filter_map(|key| key.as_string())
+            .collect(),
+        None => vec![],
+    }
+}
+
+fn has_own_property(props: &JsValue, key: &str) -> bool {
+    match props.dyn_ref::<Object>() {
+        Some(object) => object.has_own_property(&key.into()),
+        None => false,
+    }
+}
+
+// The value of form elements is set by their wrappers
+fn is_controlled_prop(tag: &str, prop_key: &str) -> bool {
+    match tag {
+        "input" => INPUT_CONTROLLED_PROPS.contains(&prop_key),
+        "textarea" => TEXTAREA_CONTROLLED_PROPS.contains(&prop_key),
+        "select" => SELECT_CONTROLLED_PROPS.contains(&prop_key),
+        _ => false,
+    }
+}
+
+pub fn set_initial_properties(dom_element: &Element, tag: &str, props: &JsValue) {
+    if is_dev() {
+        assert_valid_props(props);
+    }
+    let is_custom_component_tag = is_custom_component(tag, props);
+    for prop_key in own_keys(props) {
+        let next_prop = derive_from_js_value(props, prop_key.as_str());
+        if next_prop.is_null() || next_prop.is_undefined() {
+            continue;
+        }
+        if is_controlled_prop(tag, prop_key.as_str()) {
+            continue;
+        }
+        if prop_key == STYLE {
+            set_value_for_styles(dom_element, &next_prop);
+            continue;
+        }
+        if prop_key == CHILDREN {
+            set_text_child(dom_element, tag, &next_prop);
+            continue;
+        }
+        if prop_key == DANGEROUSLY_SET_INNER_HTML {
+            set_inner_html(dom_element, &next_prop);
+            continue;
+        }
+        set_value_for_property(
+            dom_element,
+            prop_key.as_str(),
+            &next_prop,
+            is_custom_component_tag,
+        );
+    }
+
+    match tag {
+        "input" => init_input(dom_element, props),
+        "textarea" => init_textarea(dom_element, props),
+        "select" => init_select(dom_element, props),
+        _ => {}
+    }
+}
+
+fn set_style_update(style_updates: &mut Option<Object>, style_name: &JsValue, value: &JsValue) {
+    if style_updates.is_none() {
+        *style_updates = Some(Object::new());
+    }
+    Reflect::set(style_updates.as_ref().unwrap(), style_name, value)
+        .expect("TODO: panic set style_updates");
+}
+
+// Calculate the diff between the two objects, the result is [key1, value1, key2, value2, ...]
+pub fn diff_properties(
+    _dom_element: &Element,
+    _tag: &str,
+    last_props: &JsValue,
+    next_props: &JsValue,
+) -> Option<JsValue> {
+    let update_payload = Array::new();
+    let mut style_updates: Option<Object> = None;
+
+    for prop_key in own_keys(last_props) {
+        let last_prop = derive_from_js_value(last_props, prop_key.as_str());
+        if has_own_property(next_props, prop_key.as_str())
+            || last_prop.is_null()
+            || last_prop.is_undefined()
+        {
+            continue;
+        }
+        if prop_key == CHILDREN || prop_key == DANGEROUSLY_SET_INNER_HTML {
+            // The old text content is reset by the reconciler when the new children are inserted
+            continue;
+        }
+        if prop_key == STYLE {
+            for style_name in own_keys(&last_prop) {
+                set_style_update(&mut style_updates, &style_name.into(), &"".into());
+            }
+            continue;
+        }
+        update_payload.push(&prop_key.into());
+        update_payload.push(&JsValue::null());
+    }
+
+    for prop_key in own_keys(next_props) {
+        let next_prop = derive_from_js_value(next_props, prop_key.as_str());
+        let last_prop = derive_from_js_value(last_props, prop_key.as_str());
+        if Object::is(&next_prop, &last_prop)
+            || ((next_prop.is_null() || next_prop.is_undefined())
+                && (last_prop.is_null() || last_prop.is_undefined()))
+        {
+            continue;
+        }
+        if prop_key == CHILDREN {
+            // Only text children are set by the renderer, the others are reconciled as fibers
+            if is_text_child(&next_prop) {
+                update_payload.push(&prop_key.into());
+                update_payload.push(&next_prop);
+            }
+            continue;
+        }
+        if prop_key == DANGEROUSLY_SET_INNER_HTML {
+            let next_html = get_inner_html(&next_prop);
+            if !is_nullish(&next_html) && !Object::is(&next_html, &get_inner_html(&last_prop)) {
+                update_payload.push(&prop_key.into());
+                update_payload.push(&next_prop);
+            }
+            continue;
+        }
+        if prop_key == STYLE {
+            if last_prop.is_object() {
+                // Unset styles on `last_prop` but not on `next_prop`
+                for style_name in own_keys(&last_prop) {
+                    if !has_own_property(&next_prop, style_name.as_str()) {
+                        set_style_update(&mut style_updates, &style_name.into(), &"".into());
+                    }
+                }
+                // Update styles that changed since `last_prop`
+                for style_name in own_keys(&next_prop) {
+                    let next_value = derive_from_js_value(&next_prop, style_name.as_str());
+                    let last_value = derive_from_js_value(&last_prop, style_name.as_str());
+                    if !Object::is(&next_value, &last_value) {
+                        set_style_update(&mut style_updates, &style_name.into(), &next_value);
+                    }
+                }
+            } else {
+                for style_name in own_keys(&next_prop) {
+                    let next_value = derive_from_js_value(&next_prop, style_name.as_str());
+                    set_style_update(&mut style_updates, &style_name.into(), &next_value);
+                }
+            }
+            continue;
+        }
+        update_payload.push(&prop_key.into());
+        update_payload.push(&next_prop);
+    }
+
+    if let Some(style_updates) = style_updates {
+        update_payload.push(&STYLE.into());
+        update_payload.push(&style_updates);
+    }
+
+    if update_payload.length() == 0 {
+        return None;
+    }
+    Some(update_payload.into())
+}
+
+// Apply the diff calculated by diff_properties
+pub fn update_properties(
+    dom_element: &Element,
+    tag: &str,
+    update_payload: &JsValue,
+    next_props: &JsValue,
+) {
+    let is_custom_component_tag = is_custom_component(tag, next_props);
+    let update_payload = update_payload.dyn_ref::<Array>().unwrap();
+    let mut i = 0;
+    while i < update_payload.length() {
+        let prop_key = update_payload.get(i).as_string().unwrap();
+        let prop_value = update_payload.get(i + 1);
+        i += 2;
+        if is_controlled_prop(tag, prop_key.as_str()) {
+            continue;
+        }
+        if prop_key == STYLE {
+            set_value_for_styles(dom_element, &prop_value);
+            continue;
+        }
+        if prop_key == CHILDREN {
+            set_text_child(dom_element, tag, &prop_value);
+            continue;
+        }
+        if prop_key == DANGEROUSLY_SET_INNER_HTML {
+            set_inner_html(dom_element, &prop_value);
+            continue;
+        }
+        set_value_for_property(
+            dom_element,
+            prop_key.as_str(),
+            &prop_value,
+            is_custom_component_tag,
+        );
+    }
+
+    match tag {
+        "input" => update_input(dom_element, next_props),
+        "textarea" => update_textarea(dom_element, next_props),
+        "select" => update_select(dom_element, next_props),
+        _ => {}
+    }
+}
+
+// Restore the value of a controlled form element after an event, in case the state was not changed
+pub fn restore_controlled_state(dom_element: &Element, tag: &str, props: &JsValue) {
+    match tag {
+        "input" => restore_controlled_input_state(dom_element, props),
+        "textarea" => restore_controlled_textarea_state(dom_element, props),
+        "select" => restore_controlled_select_state(dom_element, props),
+        _ => {}
+    }
+}
@@ -0,0 +1,73 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::JsValue;
+use web_sys::js_sys::{Object, Reflect};
+use web_sys::Node;
+
+use react_reconciler::fiber::FiberNode;
+use shared::derive_from_js_value;
+
+static INTERNAL_INSTANCE_KEY: &str = "__reactFiber";
+
+// The fibers can't be stored on the node directly, the node keeps the id of its fiber instead
+struct FiberInstances {
+    instances: HashMap<u32, Weak<RefCell<FiberNode>>>,
+    next_instance_id: u32,
+    // Drop the fibers which are gone when the map grows over this size
+    prune_threshold: usize,
+}
+
+thread_local! {
+    static FIBER_INSTANCES: RefCell<FiberInstances> = RefCell::new(FiberInstances {
+        instances: HashMap::new(),
+        next_instance_id: 0,
+        prune_threshold: 256,
+    });
+}
+
+pub fn precache_fiber_node(fiber: Rc<RefCell<FiberNode>>, node: &Node) {
+    let id = FIBER_INSTANCES.with(|fiber_instances| {
+        let mut fiber_instances = fiber_instances.borrow_mut();
+        if fiber_instances.instances.len() >= fiber_instances.prune_threshold {
+            fiber_instances
+                .instances
+                .retain(|_, fiber| fiber.strong_count() > 0);
+            fiber_instances.prune_threshold =
+                (fiber_instances.instances.len() * 2).max(fiber_instances.prune_threshold);
+        }
+        fiber_instances.next_instance_id += 1;
+        let id = fiber_instances.next_instance_id;
+        fiber_instances.instances.insert(id, Rc::downgrade(&fiber));
+        id
+    });
+    Reflect::set(node, &INTERNAL_INSTANCE_KEY.into(), &JsValue::from(id))
+        .expect("TODO: panic set INTERNAL_INSTANCE_KEY");
+}
+
+fn get_fiber_from_node(node: &Node) -> Option<Rc<RefCell<FiberNode>>> {
+    let id = derive_from_js_value(node, INTERNAL_INSTANCE_KEY).as_f64()? as u32;
+    FIBER_INSTANCES.with(|fiber_instances| fiber_instances.borrow().instances.get(&id)?.upgrade())
+}
+
+// The text nodes and the nodes not rendered by React don't have fibers, find the closest
+// ancestor which has one, without going beyond the stop node
+pub fn get_closest_fiber_from_node(
+    node: Option<Node>,
+    stop: Option<&Node>,
+) -> Option<Rc<RefCell<FiberNode>>> {
+    let mut node = node;
+    while let Some(n) = node {
+        if let Some(stop) = stop {
+            if Object::is(&n, stop) {
+                return None;
+            }
+        }
+        if let Some(fiber) = get_fiber_from_node(&n) {
+            return Some(fiber);
+        }
+        node = n.parent_node();
+    }
+    None
+}
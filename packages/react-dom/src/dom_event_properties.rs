@@ -0,0 +1,188 @@
+use react_reconciler::fiber_lanes::Lane;
+
+// [native event name, react event name], onXxx and onXxxCapture will be registered
+static SIMPLE_EVENTS: [(&str, &str); 83] = [
+    ("abort", "Abort"),
+    ("animationend", "AnimationEnd"),
+    ("animationiteration", "AnimationIteration"),
+    ("animationstart", "AnimationStart"),
+    ("auxclick", "AuxClick"),
+    ("beforeinput", "BeforeInput"),
+    ("cancel", "Cancel"),
+    ("canplay", "CanPlay"),
+    ("canplaythrough", "CanPlayThrough"),
+    ("click", "Click"),
+    ("close", "Close"),
+    ("compositionend", "CompositionEnd"),
+    ("compositionstart", "CompositionStart"),
+    ("compositionupdate", "CompositionUpdate"),
+    ("contextmenu", "ContextMenu"),
+    ("copy", "Copy"),
+    ("cut", "Cut"),
+    ("dblclick", "DoubleClick"),
+    ("drag", "Drag"),
+    ("dragend", "DragEnd"),
+    ("dragenter", "DragEnter"),
+    ("dragexit", "DragExit"),
+    ("dragleave", "DragLeave"),
+    ("dragover", "DragOver"),
+    ("dragstart", "DragStart"),
+    ("drop", "Drop"),
+    ("durationchange", "DurationChange"),
+    ("emptied", "Emptied"),
+    ("encrypted", "Encrypted"),
+    ("ended", "Ended"),
+    ("error", "Error"),
+    ("focusin", "Focus"),
+    ("focusout", "Blur"),
+    ("gotpointercapture", "GotPointerCapture"),
+    ("input", "Input"),
+    ("invalid", "Invalid"),
+    ("keydown", "KeyDown"),
+    ("keypress", "KeyPress"),
+    ("keyup", "KeyUp"),
+    ("load", "Load"),
+    ("loadeddata", "LoadedData"),
+    ("loadedmetadata", "LoadedMetadata"),
+    ("loadstart", "LoadStart"),
+    ("lostpointercapture", "LostPointerCapture"),
+    ("mousedown", "MouseDown"),
+    ("mouseenter", "MouseEnter"),
+    ("mouseleave", "MouseLeave"),
+    ("mousemove", "MouseMove"),
+    ("mouseout", "MouseOut"),
+    ("mouseover", "MouseOver"),
+    ("mouseup", "MouseUp"),
+    ("paste", "Paste"),
+    ("pause", "Pause"),
+    ("play", "Play"),
+    ("playing", "Playing"),
+    ("pointercancel", "PointerCancel"),
+    ("pointerdown", "PointerDown"),
+    ("pointerenter", "PointerEnter"),
+    ("pointerleave", "PointerLeave"),
+    ("pointermove", "PointerMove"),
+    ("pointerout", "PointerOut"),
+    ("pointerover", "PointerOver"),
+    ("pointerup", "PointerUp"),
+    ("progress", "Progress"),
+    ("ratechange", "RateChange"),
+    ("reset", "Reset"),
+    ("resize", "Resize"),
+    ("scroll", "Scroll"),
+    ("seeked", "Seeked"),
+    ("seeking", "Seeking"),
+    ("stalled", "Stalled"),
+    ("submit", "Submit"),
+    ("suspend", "Suspend"),
+    ("timeupdate", "TimeUpdate"),
+    ("toggle", "Toggle"),
+    ("touchcancel", "TouchCancel"),
+    ("touchend", "TouchEnd"),
+    ("touchmove", "TouchMove"),
+    ("touchstart", "TouchStart"),
+    ("transitionend", "TransitionEnd"),
+    ("volumechange", "VolumeChange"),
+    ("waiting", "Waiting"),
+    ("wheel", "Wheel"),
+];
+
+// These events don't bubble in the DOM, we listen to them in the capture phase
+// on the root and only dispatch the bubble handlers of the target.
+static NON_DELEGATED_EVENTS: [&str; 34] = [
+    "abort",
+    "cancel",
+    "canplay",
+    "canplaythrough",
+    "close",
+    "durationchange",
+    "emptied",
+    "encrypted",
+    "ended",
+    "error",
+    "invalid",
+    "load",
+    "loadeddata",
+    "loadedmetadata",
+    "loadstart",
+    "mouseenter",
+    "mouseleave",
+    "pause",
+    "play",
+    "playing",
+    "pointerenter",
+    "pointerleave",
+    "progress",
+    "ratechange",
+    "resize",
+    "scroll",
+    "seeked",
+    "seeking",
+    "stalled",
+    "suspend",
+    "timeupdate",
+    "toggle",
+    "volumechange",
+    "waiting",
+];
+
+// Listeners for these events are registered as passive, so they can't preventDefault
+static PASSIVE_EVENTS: [&str; 3] = ["touchstart", "touchmove", "wheel"];
+
+// onChange is dispatched by the change event plugin, from one of these native events
+// depending on the target element
+pub static CHANGE_EVENT_DEPENDENCIES: [&str; 3] = ["change", "click", "input"];
+
+pub fn all_native_events() -> impl Iterator<Item = &'static str> {
+    SIMPLE_EVENTS
+        .iter()
+        .map(|(native_event, _)| *native_event)
+        .chain(CHANGE_EVENT_DEPENDENCIES.iter().copied().filter(|event| {
+            !SIMPLE_EVENTS
+                .iter()
+                .any(|(native_event, _)| native_event == event)
+        }))
+}
+
+pub fn is_non_delegated_event(event_type: &str) -> bool {
+    NON_DELEGATED_EVENTS.contains(&event_type)
+}
+
+pub fn is_passive_event(event_type: &str) -> bool {
+    PASSIVE_EVENTS.contains(&event_type)
+}
+
+// Return [onXxxCapture, onXxx]
+pub fn get_event_callback_name_from_event_type(event_type: &str) -> Option<Vec<String>> {
+    SIMPLE_EVENTS
+        .iter()
+        .find(|(native_event, _)| *native_event == event_type)
+        .map(|(_, react_name)| {
+            vec![
+                format!("on{}Capture", react_name),
+                format!("on{}", react_name),
+            ]
+        })
+}
+
+pub fn event_type_to_event_lane(event_type: &str) -> Lane {
+    match event_type {
+        // Discrete events
+        "cancel" | "click" | "close" | "contextmenu" | "copy" | "cut" | "auxclick" | "dblclick"
+        | "dragend" | "dragstart" | "drop" | "focusin" | "focusout" | "input" | "invalid"
+        | "keydown" | "keypress" | "keyup" | "mousedown" | "mouseup" | "paste" | "pause"
+        | "play" | "pointercancel" | "pointerdown" | "pointerup" | "ratechange" | "reset"
+        | "resize" | "seeked" | "submit" | "touchcancel" | "touchend" | "touchstart"
+        | "volumechange" | "change" | "selectionchange" | "textInput" | "compositionstart"
+        | "compositionend" | "compositionupdate" | "beforeinput" | "blur" | "focus" | "select" => {
+            Lane::SyncLane
+        }
+        // Continuous events
+        "drag" | "dragenter" | "dragexit" | "dragleave" | "dragover" | "mousemove" | "mouseout"
+        | "mouseover" | "pointermove" | "pointerout" | "pointerover" | "scroll" | "toggle"
+        | "touchmove" | "wheel" | "mouseenter" | "mouseleave" | "pointerenter" | "pointerleave" => {
+            Lane::InputContinuousLane
+        }
+        _ => Lane::DefaultLane,
+    }
+}
@@ -0,0 +1,104 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::js_sys::{Reflect, JSON};
+use web_sys::Element;
+
+use shared::{derive_from_js_value, to_string};
+
+use crate::input_value_tracking::track;
+use crate::synthetic_event::ELEMENT_EVENT_PROPS_KEY;
+use crate::utils::is_nullish;
+
+// Props of input which are set by the wrapper instead of set_value_for_property
+pub static INPUT_CONTROLLED_PROPS: [&str; 2] = ["value", "checked"];
+
+fn set_node_property(node: &Element, key: &str, value: &JsValue) {
+    Reflect::set(node, &key.into(), value).expect("TODO: panic set input property");
+}
+
+pub fn init_input(node: &Element, props: &JsValue) {
+    let value = derive_from_js_value(props, "value");
+    let default_value = derive_from_js_value(props, "defaultValue");
+    let initial_value = if is_nullish(&value) {
+        default_value
+    } else {
+        value
+    };
+    if !is_nullish(&initial_value) {
+        let initial_value = JsValue::from_str(to_string(&initial_value).as_str());
+        set_node_property(node, "defaultValue", &initial_value);
+        set_node_property(node, "value", &initial_value);
+    }
+
+    let checked = derive_from_js_value(props, "checked");
+    let default_checked = derive_from_js_value(props, "defaultChecked");
+    let initial_checked = if is_nullish(&checked) {
+        default_checked
+    } else {
+        checked
+    };
+    if !is_nullish(&initial_checked) {
+        let initial_checked = JsValue::from_bool(initial_checked.is_truthy());
+        set_node_property(node, "defaultChecked", &initial_checked);
+        set_node_property(node, "checked", &initial_checked);
+    }
+    track(node);
+}
+
+pub fn update_input(node: &Element, props: &JsValue) {
+    let checked = derive_from_js_value(props, "checked");
+    if !is_nullish(&checked) {
+        set_node_property(node, "checked", &JsValue::from_bool(checked.is_truthy()));
+    }
+
+    let value = derive_from_js_value(props, "value");
+    if !is_nullish(&value) {
+        let value = to_string(&value);
+        if derive_from_js_value(node, "value").as_string() != Some(value.clone()) {
+            set_node_property(node, "value", &value.into());
+        }
+    } else {
+        let default_value = derive_from_js_value(props, "defaultValue");
+        if !is_nullish(&default_value) {
+            set_node_property(
+                node,
+                "defaultValue",
+                &to_string(&default_value).as_str().into(),
+            );
+        }
+    }
+    track(node);
+}
+
+pub fn restore_controlled_input_state(node: &Element, props: &JsValue) {
+    update_input(node, props);
+
+    // The browser unchecks the other radios of the same group, restore them as well
+    let name = derive_from_js_value(props, "name");
+    if derive_from_js_value(props, "type").as_string().as_deref() != Some("radio")
+        || name.as_string().is_none()
+    {
+        return;
+    }
+    let document = match node.owner_document() {
+        Some(document) => document,
+        None => return,
+    };
+    let selector = format!(
+        "input[name={}][type=\"radio\"]",
+        JSON::stringify(&name).unwrap()
+    );
+    let group = match document.query_selector_all(selector.as_str()) {
+        Ok(group) => group,
+        Err(_) => return,
+    };
+    for i in 0..group.length() {
+        let other_node = group.get(i).unwrap().dyn_into::<Element>().unwrap();
+        if other_node == *node {
+            continue;
+        }
+        let other_props = derive_from_js_value(&other_node, ELEMENT_EVENT_PROPS_KEY);
+        if other_props.is_object() {
+            update_input(&other_node, &other_props);
+        }
+    }
+}
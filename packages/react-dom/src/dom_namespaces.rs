@@ -0,0 +1,30 @@
+pub static HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+pub static MATH_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+pub static SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+pub static XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+pub static XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+pub static XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+// The namespace of an element created in an HTML parent
+pub fn get_intrinsic_namespace(_type: &str) -> &'static str {
+    match _type {
+        "svg" => SVG_NAMESPACE,
+        "math" => MATH_NAMESPACE,
+        _ => HTML_NAMESPACE,
+    }
+}
+
+// The namespace of the children of an element with the given type
+pub fn get_child_namespace(parent_namespace: &str, _type: &str) -> &'static str {
+    if parent_namespace == SVG_NAMESPACE {
+        return if _type == "foreignObject" {
+            HTML_NAMESPACE
+        } else {
+            SVG_NAMESPACE
+        };
+    }
+    if parent_namespace == MATH_NAMESPACE {
+        return MATH_NAMESPACE;
+    }
+    get_intrinsic_namespace(_type)
+}
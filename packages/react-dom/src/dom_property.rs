@@ -0,0 +1,460 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsValue;
+use web_sys::js_sys::Reflect;
+use web_sys::Element;
+
+use shared::type_of;
+
+use crate::dom_namespaces::{XLINK_NAMESPACE, XMLNS_NAMESPACE, XML_NAMESPACE};
+
+#[wasm_bindgen]
+extern "C" {
+    // String(value), the attribute values are stringified the same way as the DOM does,
+    // e.g. the toString of objects is used and Infinity stays Infinity
+    #[wasm_bindgen(js_name = String)]
+    fn to_attribute_string(value: &JsValue) -> String;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyType {
+    // A simple string attribute, e.g. `id`, `href`
+    String,
+    // A string attribute that accepts booleans, e.g. `draggable="true"`
+    BooleanishString,
+    // Present or absent, e.g. `disabled`
+    Boolean,
+    // Either a boolean or a string, e.g. `download`, `download="file.txt"`
+    OverloadedBoolean,
+    // Must be a number, removed when NaN
+    Numeric,
+    // Must be a number greater than 0, removed otherwise
+    PositiveNumeric,
+}
+
+#[derive(Debug, Clone)]
+pub struct PropertyInfo {
+    pub property_type: PropertyType,
+    pub property_name: String,
+    pub attribute_name: String,
+    pub attribute_namespace: Option<&'static str>,
+    pub must_use_property: bool,
+}
+
+impl PropertyInfo {
+    fn new(
+        name: &str,
+        property_type: PropertyType,
+        must_use_property: bool,
+        attribute_name: &str,
+        attribute_namespace: Option<&'static str>,
+    ) -> Self {
+        Self {
+            property_type,
+            property_name: name.to_string(),
+            attribute_name: attribute_name.to_string(),
+            attribute_namespace,
+            must_use_property,
+        }
+    }
+
+    fn accepts_booleans(&self) -> bool {
+        self.property_type == PropertyType::Boolean
+            || self.property_type == PropertyType::BooleanishString
+            || self.property_type == PropertyType::OverloadedBoolean
+    }
+}
+
+// Props that are handled by the renderer itself and never reach the element
+static RESERVED_PROPS: [&str; 10] = [
+    "children",
+    "dangerouslySetInnerHTML",
+    "defaultValue",
+    "defaultChecked",
+    "innerHTML",
+    "suppressContentEditableWarning",
+    "suppressHydrationWarning",
+    "style",
+    "key",
+    "ref",
+];
+
+pub fn is_reserved_prop(name: &str) -> bool {
+    RESERVED_PROPS.contains(&name)
+}
+
+// SVG attributes which are written in camelCase in JSX, e.g. strokeWidth -> stroke-width
+fn get_hyphenated_svg_attribute(name: &str) -> Option<&'static str> {
+    let attribute_name = match name {
+        "accentHeight" => "accent-height",
+        "alignmentBaseline" => "alignment-baseline",
+        "arabicForm" => "arabic-form",
+        "baselineShift" => "baseline-shift",
+        "capHeight" => "cap-height",
+        "clipPath" => "clip-path",
+        "clipRule" => "clip-rule",
+        "colorInterpolation" => "color-interpolation",
+        "colorInterpolationFilters" => "color-interpolation-filters",
+        "colorProfile" => "color-profile",
+        "colorRendering" => "color-rendering",
+        "dominantBaseline" => "dominant-baseline",
+        "enableBackground" => "enable-background",
+        "fillOpacity" => "fill-opacity",
+        "fillRule" => "fill-rule",
+        "floodColor" => "flood-color",
+        "floodOpacity" => "flood-opacity",
+        "fontFamily" => "font-family",
+        "fontSize" => "font-size",
+        "fontSizeAdjust" => "font-size-adjust",
+        "fontStretch" => "font-stretch",
+        "fontStyle" => "font-style",
+        "fontVariant" => "font-variant",
+        "fontWeight" => "font-weight",
+        "glyphName" => "glyph-name",
+        "glyphOrientationHorizontal" => "glyph-orientation-horizontal",
+        "glyphOrientationVertical" => "glyph-orientation-vertical",
+        "horizAdvX" => "horiz-adv-x",
+        "horizOriginX" => "horiz-origin-x",
+        "imageRendering" => "image-rendering",
+        "letterSpacing" => "letter-spacing",
+        "lightingColor" => "lighting-color",
+        "markerEnd" => "marker-end",
+        "markerMid" => "marker-mid",
+        "markerStart" => "marker-start",
+        "overlinePosition" => "overline-position",
+        "overlineThickness" => "overline-thickness",
+        "paintOrder" => "paint-order",
+        "panose1" => "panose-1",
+        "pointerEvents" => "pointer-events",
+        "renderingIntent" => "rendering-intent",
+        "shapeRendering" => "shape-rendering",
+        "stopColor" => "stop-color",
+        "stopOpacity" => "stop-opacity",
+        "strikethroughPosition" => "strikethrough-position",
+        "strikethroughThickness" => "strikethrough-thickness",
+        "strokeDasharray" => "stroke-dasharray",
+        "strokeDashoffset" => "stroke-dashoffset",
+        "strokeLinecap" => "stroke-linecap",
+        "strokeLinejoin" => "stroke-linejoin",
+        "strokeMiterlimit" => "stroke-miterlimit",
+        "strokeOpacity" => "stroke-opacity",
+        "strokeWidth" => "stroke-width",
+        "textAnchor" => "text-anchor",
+        "textDecoration" => "text-decoration",
+        "textRendering" => "text-rendering",
+        "underlinePosition" => "underline-position",
+        "underlineThickness" => "underline-thickness",
+        "unicodeBidi" => "unicode-bidi",
+        "unicodeRange" => "unicode-range",
+        "unitsPerEm" => "units-per-em",
+        "vAlphabetic" => "v-alphabetic",
+        "vHanging" => "v-hanging",
+        "vIdeographic" => "v-ideographic",
+        "vMathematical" => "v-mathematical",
+        "vectorEffect" => "vector-effect",
+        "vertAdvY" => "vert-adv-y",
+        "vertOriginX" => "vert-origin-x",
+        "vertOriginY" => "vert-origin-y",
+        "wordSpacing" => "word-spacing",
+        "writingMode" => "writing-mode",
+        "xHeight" => "x-height",
+        _ => return None,
+    };
+    Some(attribute_name)
+}
+
+pub fn get_property_info(name: &str) -> Option<PropertyInfo> {
+    let info = match name {
+        // Renamed attributes
+        "acceptCharset" => {
+            PropertyInfo::new(name, PropertyType::String, false, "accept-charset", None)
+        }
+        "className" => PropertyInfo::new(name, PropertyType::String, false, "class", None),
+        "htmlFor" => PropertyInfo::new(name, PropertyType::String, false, "for", None),
+        "httpEquiv" => PropertyInfo::new(name, PropertyType::String, false, "http-equiv", None),
+        // Case-insensitive HTML attributes, lowercased
+        "tabIndex" | "crossOrigin" | "encType" | "hrefLang" | "inputMode" | "maxLength"
+        | "minLength" | "autoComplete" | "autoCapitalize" | "formAction" | "formMethod"
+        | "formTarget" | "srcSet" | "useMap" | "dateTime" | "referrerPolicy" | "enterKeyHint" => {
+            PropertyInfo::new(
+                name,
+                PropertyType::String,
+                false,
+                name.to_lowercase().as_str(),
+                None,
+            )
+        }
+        // `true`/`false` are stringified, lowercased
+        "contentEditable" | "draggable" | "spellCheck" => PropertyInfo::new(
+            name,
+            PropertyType::BooleanishString,
+            false,
+            name.to_lowercase().as_str(),
+            None,
+        ),
+        "allowFullScreen"
+        | "async"
+        | "autoFocus"
+        | "autoPlay"
+        | "controls"
+        | "default"
+        | "defer"
+        | "disabled"
+        | "disablePictureInPicture"
+        | "disableRemotePlayback"
+        | "formNoValidate"
+        | "hidden"
+        | "loop"
+        | "noModule"
+        | "noValidate"
+        | "open"
+        | "playsInline"
+        | "readOnly"
+        | "required"
+        | "reversed"
+        | "scoped"
+        | "seamless"
+        | "itemScope" => PropertyInfo::new(
+            name,
+            PropertyType::Boolean,
+            false,
+            name.to_lowercase().as_str(),
+            None,
+        ),
+        // The DOM property and the attribute can diverge, so we need to set the property
+        "checked" | "multiple" | "muted" | "selected" => {
+            PropertyInfo::new(name, PropertyType::Boolean, true, name, None)
+        }
+        "capture" | "download" => {
+            PropertyInfo::new(name, PropertyType::OverloadedBoolean, false, name, None)
+        }
+        "cols" | "rows" | "size" | "span" => {
+            PropertyInfo::new(name, PropertyType::PositiveNumeric, false, name, None)
+        }
+        "rowSpan" | "start" => PropertyInfo::new(
+            name,
+            PropertyType::Numeric,
+            false,
+            name.to_lowercase().as_str(),
+            None,
+        ),
+        // Namespaced attributes, xlinkHref -> xlink:href
+        "xlinkActuate" | "xlinkArcrole" | "xlinkHref" | "xlinkRole" | "xlinkShow"
+        | "xlinkTitle" | "xlinkType" => PropertyInfo::new(
+            name,
+            PropertyType::String,
+            false,
+            format!("xlink:{}", name["xlink".len()..].to_lowercase()).as_str(),
+            Some(XLINK_NAMESPACE),
+        ),
+        "xmlBase" | "xmlLang" | "xmlSpace" => PropertyInfo::new(
+            name,
+            PropertyType::String,
+            false,
+            format!("xml:{}", name["xml".len()..].to_lowercase()).as_str(),
+            Some(XML_NAMESPACE),
+        ),
+        "xmlnsXlink" | "xmlns:xlink" => PropertyInfo::new(
+            name,
+            PropertyType::String,
+            false,
+            "xmlns:xlink",
+            Some(XMLNS_NAMESPACE),
+        ),
+        _ => match get_hyphenated_svg_attribute(name) {
+            Some(attribute_name) => {
+                PropertyInfo::new(name, PropertyType::String, false, attribute_name, None)
+            }
+            None => return None,
+        },
+    };
+    Some(info)
+}
+
+fn is_attribute_name_safe(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == ':' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':' || c == '-' || c == '.')
+}
+
+pub fn is_custom_component(tag: &str, props: &JsValue) -> bool {
+    if !tag.contains('-') {
+        return !Reflect::get(props, &"is".into())
+            .unwrap_or(JsValue::undefined())
+            .is_undefined();
+    }
+    // These are reserved SVG and MathML elements.
+    !matches!(
+        tag,
+        "annotation-xml"
+            | "color-profile"
+            | "font-face"
+            | "font-face-src"
+            | "font-face-uri"
+            | "font-face-format"
+            | "font-face-name"
+            | "missing-glyph"
+    )
+}
+
+fn is_event_prop(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() > 2 && &bytes[0..2] == b"on" && bytes[2].is_ascii_uppercase()
+}
+
+pub fn should_ignore_attribute(name: &str, property_info: Option<&PropertyInfo>) -> bool {
+    if is_reserved_prop(name) {
+        return true;
+    }
+    if property_info.is_some() {
+        return false;
+    }
+    // Event handlers are handled by the synthetic event system
+    is_event_prop(name)
+}
+
+fn should_remove_attribute_with_warning(
+    name: &str,
+    value: &JsValue,
+    property_info: Option<&PropertyInfo>,
+    is_custom_component_tag: bool,
+) -> bool {
+    if value.is_function() || value.is_symbol() {
+        return true;
+    }
+    if type_of(value, "boolean") {
+        if is_custom_component_tag {
+            return false;
+        }
+        return match property_info {
+            Some(info) => !info.accepts_booleans(),
+            None => {
+                let prefix = name.to_lowercase();
+                !prefix.starts_with("data-") && !prefix.starts_with("aria-")
+            }
+        };
+    }
+    false
+}
+
+pub fn should_remove_attribute(
+    name: &str,
+    value: &JsValue,
+    property_info: Option<&PropertyInfo>,
+    is_custom_component_tag: bool,
+) -> bool {
+    if value.is_null() || value.is_undefined() {
+        return true;
+    }
+    if should_remove_attribute_with_warning(name, value, property_info, is_custom_component_tag) {
+        return true;
+    }
+    if is_custom_component_tag {
+        return false;
+    }
+    match property_info {
+        Some(info) => match info.property_type {
+            PropertyType::Boolean => !value.is_truthy(),
+            PropertyType::OverloadedBoolean => value.as_bool() == Some(false),
+            PropertyType::Numeric => to_number(value).is_nan(),
+            PropertyType::PositiveNumeric => {
+                let n = to_number(value);
+                n.is_nan() || n < 1.0
+            }
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+fn to_number(value: &JsValue) -> f64 {
+    match value.as_f64() {
+        Some(n) => n,
+        None => match value.as_string() {
+            Some(s) => s.trim().parse::<f64>().unwrap_or(f64::NAN),
+            None => f64::NAN,
+        },
+    }
+}
+
+/**
+ * Sets the value for a property on a node, choosing between the DOM property
+ * and the attribute the same way React does.
+ */
+pub fn set_value_for_property(
+    node: &Element,
+    name: &str,
+    value: &JsValue,
+    is_custom_component_tag: bool,
+) {
+    let property_info = get_property_info(name);
+    if should_ignore_attribute(name, property_info.as_ref()) {
+        return;
+    }
+
+    let should_remove =
+        should_remove_attribute(name, value, property_info.as_ref(), is_custom_component_tag);
+
+    if is_custom_component_tag || property_info.is_none() {
+        if is_attribute_name_safe(name) {
+            if should_remove {
+                node.remove_attribute(name)
+                    .expect("TODO: panic remove_attribute");
+            } else {
+                node.set_attribute(name, to_attribute_string(value).as_str())
+                    .expect("TODO: panic set_attribute");
+            }
+        }
+        return;
+    }
+
+    let property_info = property_info.unwrap();
+    if property_info.must_use_property {
+        let property_name: JsValue = property_info.property_name.as_str().into();
+        if should_remove {
+            let empty_value = if property_info.property_type == PropertyType::Boolean {
+                JsValue::from_bool(false)
+            } else {
+                JsValue::from_str("")
+            };
+            Reflect::set(node, &property_name, &empty_value).expect("TODO: panic set property");
+        } else {
+            Reflect::set(node, &property_name, value).expect("TODO: panic set property");
+        }
+        return;
+    }
+
+    let attribute_name = property_info.attribute_name.as_str();
+    if should_remove {
+        match property_info.attribute_namespace {
+            // remove_attribute_ns takes the local name, xlink:href -> href
+            Some(namespace) => node
+                .remove_attribute_ns(Some(namespace), attribute_name.rsplit(':').next().unwrap())
+                .expect("TODO: panic remove_attribute_ns"),
+            None => node
+                .remove_attribute(attribute_name)
+                .expect("TODO: panic remove_attribute"),
+        }
+        return;
+    }
+
+    let attribute_value = if property_info.property_type == PropertyType::Boolean
+        || (property_info.property_type == PropertyType::OverloadedBoolean
+            && value.as_bool() == Some(true))
+    {
+        "".to_string()
+    } else {
+        to_attribute_string(value)
+    };
+
+    match property_info.attribute_namespace {
+        Some(namespace) => node
+            .set_attribute_ns(Some(namespace), attribute_name, attribute_value.as_str())
+            .expect("TODO: panic set_attribute_ns"),
+        None => node
+            .set_attribute(attribute_name, attribute_value.as_str())
+            .expect("TODO: panic set_attribute"),
+    }
+}
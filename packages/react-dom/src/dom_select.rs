@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use wasm_bindgen::JsValue;
+use web_sys::js_sys::{Array, Reflect};
+use web_sys::Element;
+
+use shared::{derive_from_js_value, to_string};
+
+use crate::utils::is_nullish;
+
+// Props of select which are set by the wrapper instead of set_value_for_property
+pub static SELECT_CONTROLLED_PROPS: [&str; 2] = ["value", "multiple"];
+
+fn set_option_property(option: &JsValue, key: &str, value: bool) {
+    Reflect::set(option, &key.into(), &JsValue::from_bool(value))
+        .expect("TODO: panic set option property");
+}
+
+fn update_options(node: &Element, multiple: bool, value: &JsValue, set_default_selected: bool) {
+    let options = derive_from_js_value(node, "options");
+    let length = derive_from_js_value(&options, "length")
+        .as_f64()
+        .unwrap_or(0.0) as u32;
+    let options: Vec<JsValue> = (0..length)
+        .map(|i| Reflect::get_u32(&options, i).unwrap())
+        .collect();
+
+    if multiple {
+        let selected_values: HashSet<String> = Array::from(value)
+            .iter()
+            .map(|value| to_string(&value))
+            .collect();
+        for option in options {
+            let option_value = to_string(&derive_from_js_value(&option, "value"));
+            let selected = selected_values.contains(&option_value);
+            if derive_from_js_value(&option, "selected").is_truthy() != selected {
+                set_option_property(&option, "selected", selected);
+            }
+            if selected && set_default_selected {
+                set_option_property(&option, "defaultSelected", true);
+            }
+        }
+        return;
+    }
+
+    // Select the first option whose value matches, or the first enabled one
+    let selected_value = to_string(value);
+    let mut default_selected: Option<JsValue> = None;
+    for option in options {
+        if to_string(&derive_from_js_value(&option, "value")) == selected_value {
+            set_option_property(&option, "selected", true);
+            if set_default_selected {
+                set_option_property(&option, "defaultSelected", true);
+            }
+            return;
+        }
+        if default_selected.is_none() && !derive_from_js_value(&option, "disabled").is_truthy() {
+            default_selected = Some(option);
+        }
+    }
+    if let Some(option) = default_selected {
+        set_option_property(&option, "selected", true);
+    }
+}
+
+// The options have to be appended before, so it's called after the children are appended
+pub fn init_select(node: &Element, props: &JsValue) {
+    let multiple = derive_from_js_value(props, "multiple").is_truthy();
+    Reflect::set(node, &"multiple".into(), &JsValue::from_bool(multiple))
+        .expect("TODO: panic set multiple");
+    let value = derive_from_js_value(props, "value");
+    if !is_nullish(&value) {
+        update_options(node, multiple, &value, false);
+    } else {
+        let default_value = derive_from_js_value(props, "defaultValue");
+        if !is_nullish(&default_value) {
+            update_options(node, multiple, &default_value, true);
+        }
+    }
+}
+
+pub fn update_select(node: &Element, props: &JsValue) {
+    let was_multiple = derive_from_js_value(node, "multiple").is_truthy();
+    let multiple = derive_from_js_value(props, "multiple").is_truthy();
+    if was_multiple != multiple {
+        Reflect::set(node, &"multiple".into(), &JsValue::from_bool(multiple))
+            .expect("TODO: panic set multiple");
+    }
+
+    let value = derive_from_js_value(props, "value");
+    if !is_nullish(&value) {
+        update_options(node, multiple, &value, false);
+    } else if was_multiple != multiple {
+        // Switching between single and multiple, reset the selection
+        let default_value = derive_from_js_value(props, "defaultValue");
+        if !is_nullish(&default_value) {
+            update_options(node, multiple, &default_value, true);
+        } else if multiple {
+            update_options(node, multiple, &Array::new().into(), false);
+        } else {
+            update_options(node, multiple, &"".into(), false);
+        }
+    }
+}
+
+pub fn restore_controlled_select_state(node: &Element, props: &JsValue) {
+    let value = derive_from_js_value(props, "value");
+    if !is_nullish(&value) {
+        let multiple = derive_from_js_value(props, "multiple").is_truthy();
+        update_options(node, multiple, &value, false);
+    }
+}
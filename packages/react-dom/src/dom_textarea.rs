@@ -0,0 +1,48 @@
+use wasm_bindgen::JsValue;
+use web_sys::js_sys::Reflect;
+use web_sys::Element;
+
+use shared::{derive_from_js_value, to_string};
+
+use crate::input_value_tracking::track;
+use crate::utils::is_nullish;
+
+// Props of textarea which are set by the wrapper instead of set_value_for_property
+pub static TEXTAREA_CONTROLLED_PROPS: [&str; 1] = ["value"];
+
+pub fn init_textarea(node: &Element, props: &JsValue) {
+    let value = derive_from_js_value(props, "value");
+    let mut initial_value = if is_nullish(&value) {
+        derive_from_js_value(props, "defaultValue")
+    } else {
+        value
+    };
+    if is_nullish(&initial_value) {
+        // Use the text children as the default value
+        initial_value = derive_from_js_value(props, "children");
+    }
+    let initial_value = if is_nullish(&initial_value) {
+        "".to_string()
+    } else {
+        to_string(&initial_value)
+    };
+    Reflect::set(node, &"value".into(), &initial_value.as_str().into())
+        .expect("TODO: panic set textarea value");
+    track(node);
+}
+
+pub fn update_textarea(node: &Element, props: &JsValue) {
+    let value = derive_from_js_value(props, "value");
+    if !is_nullish(&value) {
+        let value = to_string(&value);
+        if derive_from_js_value(node, "value").as_string() != Some(value.clone()) {
+            Reflect::set(node, &"value".into(), &value.as_str().into())
+                .expect("TODO: panic set textarea value");
+        }
+    }
+    track(node);
+}
+
+pub fn restore_controlled_textarea_state(node: &Element, props: &JsValue) {
+    update_textarea(node, props);
+}
@@ -3,7 +3,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use js_sys::JSON::stringify;
-use js_sys::{global, Function, Promise};
+use js_sys::{global, Function, Object, Promise};
 use react_reconciler::work_tags::WorkTag;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsValue;
@@ -13,9 +13,20 @@ use react_reconciler::fiber::FiberNode;
 use react_reconciler::HostConfig;
 use shared::{derive_from_js_value, log, type_of};
 
-use crate::synthetic_event::update_fiber_props;
+use crate::dom_component::{
+    diff_properties, set_initial_properties, should_set_text_content, update_properties,
+};
+use crate::dom_component_tree::precache_fiber_node;
+use crate::dom_namespaces::{get_child_namespace, get_intrinsic_namespace, HTML_NAMESPACE};
+use crate::synthetic_event::{
+    listen_to_portal_container, unlisten_to_portal_container, update_fiber_props,
+};
 
-pub struct ReactDomHostConfig;
+#[derive(Default)]
+pub struct ReactDomHostConfig {
+    // The containers this root renders portals into
+    portal_containers: RefCell<Vec<Element>>,
+}
 
 pub fn to_string(js_value: &JsValue) -> String {
     js_value.as_string().unwrap_or_else(|| {
@@ -51,6 +62,13 @@ extern "C" {
 }
 
 impl ReactDomHostConfig {
+    // Called when the root is unmounted, the portals are gone with it
+    pub fn unlisten_to_portal_containers(&self) {
+        for container in self.portal_containers.borrow_mut().drain(..) {
+            unlisten_to_portal_container(&container);
+        }
+    }
+
     fn commit_text_update(&self, text_instance: Rc<dyn Any>, content: &JsValue) {
         let text_instance = text_instance.clone().downcast::<Node>().unwrap();
         text_instance.set_node_value(Some(to_string(content).as_str()));
@@ -66,15 +84,54 @@ impl HostConfig for ReactDomHostConfig {
         ))
     }
 
-    fn create_instance(&self, _type: String, props: Rc<dyn Any>) -> Rc<dyn Any> {
+    fn get_root_host_context(&self, container: Rc<dyn Any>) -> JsValue {
+        let node = container.downcast::<Node>().unwrap();
+        let namespace = match node.dyn_ref::<Element>() {
+            Some(element) => get_child_namespace(
+                element
+                    .namespace_uri()
+                    .unwrap_or(HTML_NAMESPACE.to_string())
+                    .as_str(),
+                element.local_name().as_str(),
+            ),
+            None => HTML_NAMESPACE,
+        };
+        namespace.into()
+    }
+
+    fn get_child_host_context(&self, parent_host_context: &JsValue, _type: &str) -> JsValue {
+        let parent_namespace = parent_host_context
+            .as_string()
+            .unwrap_or(HTML_NAMESPACE.to_string());
+        get_child_namespace(parent_namespace.as_str(), _type).into()
+    }
+
+    fn create_instance(
+        &self,
+        _type: String,
+        props: Rc<dyn Any>,
+        host_context: &JsValue,
+    ) -> Rc<dyn Any> {
         let window = window().expect("no global `window` exists");
         let document = window.document().expect("should have a document on window");
-        match document.create_element(_type.as_ref()) {
+        // host_context is the namespace of the parent element, e.g. <path> inside <svg>
+        let parent_namespace = host_context
+            .as_string()
+            .unwrap_or(HTML_NAMESPACE.to_string());
+        let namespace = if parent_namespace == HTML_NAMESPACE {
+            get_intrinsic_namespace(_type.as_str())
+        } else {
+            parent_namespace.as_str()
+        };
+        let element = if namespace == HTML_NAMESPACE {
+            document.create_element(_type.as_ref())
+        } else {
+            document.create_element_ns(Some(namespace), _type.as_ref())
+        };
+        match element {
             Ok(element) => {
-                update_fiber_props(
-                    &element.clone(),
-                    &*props.clone().downcast::<JsValue>().unwrap(),
-                );
+                let props = &*props.clone().downcast::<JsValue>().unwrap();
+                update_fiber_props(&element.clone(), props);
                 Rc::new(Node::from(element))
             }
             Err(_) => {
@@ -104,6 +161,12 @@ impl HostConfig for ReactDomHostConfig {
         }
     }
 
+    fn finalize_initial_children(&self, instance: Rc<dyn Any>, _type: String, props: &JsValue) {
+        let node = instance.downcast::<Node>().unwrap();
+        let element = node.dyn_ref::<Element>().unwrap();
+        set_initial_properties(element, _type.as_str(), props);
+    }
+
     fn append_child_to_container(&self, child: Rc<dyn Any>, parent: Rc<dyn Any>) {
         self.append_initial_child(parent, child)
     }
@@ -203,6 +266,61 @@ impl HostConfig for ReactDomHostConfig {
         }
     }
 
+    fn prepare_update(
+        &self,
+        instance: Rc<dyn Any>,
+        _type: String,
+        old_props: &JsValue,
+        new_props: &JsValue,
+    ) -> Option<JsValue> {
+        let node = instance.downcast::<Node>().unwrap();
+        let element = node.dyn_ref::<Element>().unwrap();
+        diff_properties(element, &_type, old_props, new_props)
+    }
+
+    fn should_set_text_content(&self, _type: &str, props: &JsValue) -> bool {
+        should_set_text_content(_type, props)
+    }
+
+    fn reset_text_content(&self, instance: Rc<dyn Any>) {
+        let node = instance.downcast::<Node>().unwrap();
+        node.set_text_content(Some(""));
+    }
+
+    fn get_public_instance(&self, instance: Rc<dyn Any>) -> JsValue {
+        let node = instance.downcast::<Node>().unwrap();
+        (*node).clone().into()
+    }
+
+    fn get_portal_container(&self, container_info: &JsValue) -> Rc<dyn Any> {
+        match container_info.clone().dyn_into::<Node>() {
+            Ok(node) => Rc::new(node),
+            Err(_) => panic!("Target container is not a DOM element"),
+        }
+    }
+
+    fn prepare_portal_mount(&self, container: Rc<dyn Any>) {
+        let element = container
+            .downcast::<Node>()
+            .unwrap()
+            .dyn_ref::<Element>()
+            .expect("portal container is not element")
+            .clone();
+        let mut portal_containers = self.portal_containers.borrow_mut();
+        if !portal_containers
+            .iter()
+            .any(|portal_container| Object::is(portal_container, &element))
+        {
+            listen_to_portal_container(&element);
+            portal_containers.push(element);
+        }
+    }
+
+    fn precache_fiber_node(&self, fiber: Rc<RefCell<FiberNode>>, instance: Rc<dyn Any>) {
+        let node = instance.downcast::<Node>().unwrap();
+        precache_fiber_node(fiber, &node);
+    }
+
     fn commit_update(&self, fiber: Rc<RefCell<FiberNode>>) {
         let instance = FiberNode::derive_state_node(fiber.clone());
         let memoized_props = fiber.borrow().memoized_props.clone();
@@ -212,15 +330,14 @@ impl HostConfig for ReactDomHostConfig {
                 self.commit_text_update(instance.unwrap(), &text);
             }
             WorkTag::HostComponent => {
-                update_fiber_props(
-                    instance
-                        .unwrap()
-                        .downcast::<Node>()
-                        .unwrap()
-                        .dyn_ref::<Element>()
-                        .unwrap(),
-                    &memoized_props,
-                );
+                let node = instance.unwrap().downcast::<Node>().unwrap();
+                let element = node.dyn_ref::<Element>().unwrap();
+                update_fiber_props(element, &memoized_props);
+                let update_payload = fiber.borrow().update_payload.clone();
+                if let Some(update_payload) = update_payload {
+                    let _type = fiber.borrow()._type.as_string().unwrap();
+                    update_properties(element, &_type, &update_payload, &memoized_props);
+                }
             }
             _ => {
                 log!("Unsupported update type")
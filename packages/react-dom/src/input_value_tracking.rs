@@ -0,0 +1,47 @@
+use wasm_bindgen::JsValue;
+use web_sys::js_sys::Reflect;
+use web_sys::Element;
+
+use shared::derive_from_js_value;
+
+static VALUE_TRACKER_KEY: &str = "__valueTracker";
+
+fn is_checkable(node: &Element) -> bool {
+    let _type = derive_from_js_value(node, "type").as_string();
+    node.local_name() == "input" && matches!(_type.as_deref(), Some("checkbox") | Some("radio"))
+}
+
+fn get_value_from_node(node: &Element) -> String {
+    if is_checkable(node) {
+        if derive_from_js_value(node, "checked").is_truthy() {
+            "true".to_string()
+        } else {
+            "false".to_string()
+        }
+    } else {
+        derive_from_js_value(node, "value")
+            .as_string()
+            .unwrap_or_default()
+    }
+}
+
+// Remember the current value of the node, should be called every time the renderer sets it
+pub fn track(node: &Element) {
+    Reflect::set(
+        node,
+        &VALUE_TRACKER_KEY.into(),
+        &JsValue::from_str(get_value_from_node(node).as_str()),
+    )
+    .expect("TODO: panic set VALUE_TRACKER_KEY");
+}
+
+// Return true if the value has been changed by the user since it was tracked
+pub fn update_value_if_changed(node: &Element) -> bool {
+    let tracked_value = derive_from_js_value(node, VALUE_TRACKER_KEY).as_string();
+    let next_value = get_value_from_node(node);
+    if tracked_value.as_deref() == Some(next_value.as_str()) {
+        return false;
+    }
+    track(node);
+    true
+}
@@ -13,22 +13,36 @@ use scheduler::{
     unstable_cancel_callback, unstable_schedule_callback as origin_unstable_schedule_callback,
     unstable_should_yield_to_host, Priority,
 };
+use shared::{derive_from_js_value, REACT_PORTAL_TYPE};
 
-use crate::host_config::ReactDomHostConfig;
+use crate::host_config::{to_string, ReactDomHostConfig};
 use crate::renderer::Renderer;
 use crate::utils::set_panic_hook;
 
+mod change_event_plugin;
+mod css_property_operations;
+mod dom_component;
+mod dom_component_tree;
+mod dom_event_properties;
+mod dom_input;
+mod dom_namespaces;
+mod dom_property;
+mod dom_select;
+mod dom_textarea;
 mod host_config;
+mod input_value_tracking;
 mod renderer;
 mod synthetic_event;
+mod synthetic_event_interface;
 mod utils;
 
 // static mut CONTAINER_TO_ROOT: Option<HashMap<JsValue, Rc<RefCell<FiberRootNode>>>> = None;
 
 #[wasm_bindgen(js_name = createRoot)]
-pub fn create_root(container: &JsValue) -> Renderer {
+pub fn create_root(container: &JsValue, options: &JsValue) -> Renderer {
     set_panic_hook();
-    let reconciler = Reconciler::new(Rc::new(ReactDomHostConfig));
+    let host_config = Rc::new(ReactDomHostConfig::default());
+    let reconciler = Reconciler::new(host_config.clone());
     let node = match container.clone().dyn_into::<Node>() {
         Ok(node) => node,
         Err(_) => {
@@ -52,6 +66,40 @@ pub fn create_root(container: &JsValue) -> Renderer {
     // }
 
     let root = reconciler.create_container(Rc::new(node));
-    let renderer = Renderer::new(root, reconciler, container);
+    if options.is_object() {
+        let identifier_prefix = derive_from_js_value(options, "identifierPrefix");
+        if let Some(identifier_prefix) = identifier_prefix.as_string() {
+            root.borrow_mut().identifier_prefix = identifier_prefix;
+        }
+    }
+    let renderer = Renderer::new(root, reconciler, host_config, container);
     renderer
 }
+
+#[wasm_bindgen(js_name = createPortal)]
+pub fn create_portal(children: &JsValue, container: &JsValue, key: &JsValue) -> JsValue {
+    if container.clone().dyn_into::<Node>().is_err() {
+        panic!("Target container is not a DOM element")
+    }
+    let portal = Object::new();
+    Reflect::set(
+        &portal,
+        &"$$typeof".into(),
+        &JsValue::from_str(REACT_PORTAL_TYPE),
+    )
+    .expect("TODO: panic set $$typeof");
+    let key = if key.is_undefined() || key.is_null() {
+        JsValue::null()
+    } else if key.is_string() {
+        key.clone()
+    } else {
+        JsValue::from_str(&to_string(key))
+    };
+    Reflect::set(&portal, &"key".into(), &key).expect("TODO: panic set key");
+    Reflect::set(&portal, &"children".into(), children).expect("TODO: panic set children");
+    Reflect::set(&portal, &"containerInfo".into(), container)
+        .expect("TODO: panic set containerInfo");
+    Reflect::set(&portal, &"implementation".into(), &JsValue::null())
+        .expect("TODO: panic set implementation");
+    portal.into()
+}
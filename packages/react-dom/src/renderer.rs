@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use gloo::events::EventListener;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsValue;
 
@@ -8,25 +9,32 @@ use react_reconciler::fiber::FiberRootNode;
 use react_reconciler::Reconciler;
 use web_sys::Element;
 
-use crate::synthetic_event::init_event;
+use crate::host_config::ReactDomHostConfig;
+use crate::synthetic_event::{listen_to_all_supported_events, remove_all_event_listeners};
 
 #[wasm_bindgen]
 pub struct Renderer {
     container: JsValue,
     root: Rc<RefCell<FiberRootNode>>,
     reconciler: Reconciler,
+    host_config: Rc<ReactDomHostConfig>,
+    // Native event listeners on the container, None if another root listens to it already
+    listeners: RefCell<Option<Vec<EventListener>>>,
 }
 
 impl Renderer {
     pub fn new(
         root: Rc<RefCell<FiberRootNode>>,
         reconciler: Reconciler,
+        host_config: Rc<ReactDomHostConfig>,
         container: &JsValue,
     ) -> Self {
         Self {
             root,
             reconciler,
+            host_config,
             container: container.clone(),
+            listeners: RefCell::new(listen_to_all_supported_events(container)),
         }
     }
 
@@ -43,13 +51,18 @@ impl Renderer {
 #[wasm_bindgen]
 impl Renderer {
     pub fn render(&self, element: &JsValue) -> JsValue {
-        init_event(self.container.clone(), "click".to_string());
         self.reconciler
             .update_container(element.clone(), self.root.clone())
     }
 
     pub fn unmount(&self) -> JsValue {
-        self.reconciler
-            .update_container(JsValue::null(), self.root.clone())
+        let result = self
+            .reconciler
+            .update_container(JsValue::null(), self.root.clone());
+        if let Some(listeners) = self.listeners.borrow_mut().take() {
+            remove_all_event_listeners(&self.container, listeners);
+        }
+        self.host_config.unlisten_to_portal_containers();
+        result
     }
 }
@@ -1,19 +1,37 @@
-use gloo::events::EventListener;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gloo::events::{EventListener, EventListenerOptions, EventListenerPhase};
 use scheduler::{unstable_cancel_callback, unstable_run_with_priority, Priority};
-use wasm_bindgen::closure::Closure;
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::js_sys::{Function, Object, Reflect};
-use web_sys::{Element, Event};
+use web_sys::{Element, Event, Node};
 
-use react_reconciler::fiber_lanes::{lanes_to_scheduler_priority, Lane};
+use react_reconciler::fiber::FiberNode;
+use react_reconciler::fiber_lanes::lanes_to_scheduler_priority;
+use react_reconciler::work_tags::WorkTag;
+use react_reconciler::HostConfig;
 use shared::{derive_from_js_value, is_dev, log};
 
-static VALID_EVENT_TYPE_LIST: [&str; 1] = ["click"];
-static ELEMENT_EVENT_PROPS_KEY: &str = "__props";
+use crate::change_event_plugin::{is_change_event_target, should_dispatch_change_event};
+use crate::dom_component::restore_controlled_state;
+use crate::dom_component_tree::get_closest_fiber_from_node;
+use crate::dom_event_properties::{
+    all_native_events, event_type_to_event_lane, get_event_callback_name_from_event_type,
+    is_non_delegated_event, is_passive_event,
+};
+use crate::host_config::ReactDomHostConfig;
+use crate::synthetic_event_interface::{
+    create_synthetic_event, is_propagation_stopped, set_current_target,
+};
+
+pub static ELEMENT_EVENT_PROPS_KEY: &str = "__props";
+static LISTENING_MARKER: &str = "__reactListening";
 
+// The callbacks with the element which they are attached to
 struct Paths {
-    capture: Vec<Function>,
-    bubble: Vec<Function>,
+    capture: Vec<(Element, Function)>,
+    bubble: Vec<(Element, Function)>,
 }
 
 impl Paths {
@@ -26,57 +44,21 @@ impl Paths {
 }
 
 fn event_type_to_event_priority(event_type: &str) -> Priority {
-    let lane = match event_type {
-        "click" | "keydown" | "keyup" => Lane::SyncLane,
-        "scroll" => Lane::InputContinuousLane,
-        _ => Lane::DefaultLane,
-    };
-    lanes_to_scheduler_priority(lane)
-}
-
-fn create_synthetic_event(e: Event) -> Event {
-    Reflect::set(&*e, &"__stopPropagation".into(), &JsValue::from_bool(false))
-        .expect("TODO: panic set __stopPropagation");
-
-    let e_cloned = e.clone();
-    let origin_stop_propagation = derive_from_js_value(&*e, "stopPropagation");
-    let closure = Closure::wrap(Box::new(move || {
-        Reflect::set(
-            &*e_cloned,
-            &"__stopPropagation".into(),
-            &JsValue::from_bool(true),
-        )
-        .expect("TODO: panic __stopPropagation");
-        if origin_stop_propagation.is_function() {
-            let origin_stop_propagation = origin_stop_propagation.dyn_ref::<Function>().unwrap();
-            origin_stop_propagation
-                .call0(&JsValue::null())
-                .expect("TODO: panic origin_stop_propagation");
-        }
-    }) as Box<dyn Fn()>);
-    let function = closure.as_ref().unchecked_ref::<Function>().clone();
-    closure.forget();
-    Reflect::set(&*e.clone(), &"stopPropagation".into(), &function.into())
-        .expect("TODO: panic set stopPropagation");
-    e
+    lanes_to_scheduler_priority(event_type_to_event_lane(event_type))
 }
 
-fn trigger_event_flow(paths: Vec<Function>, se: &Event) {
-    for callback in paths {
+fn trigger_event_flow(paths: Vec<(Element, Function)>, se: &JsValue, event_type: &str) {
+    for (current_target, callback) in paths {
+        set_current_target(se, &current_target);
         unstable_run_with_priority(
-            event_type_to_event_priority(se.type_().as_str()),
+            event_type_to_event_priority(event_type),
             &callback.bind1(&JsValue::null(), se),
         );
-        // callback
-        //     .call1(&JsValue::null(), se)
-        //     .expect("TODO: panic call callback");
-        if derive_from_js_value(se, "__stopPropagation")
-            .as_bool()
-            .unwrap()
-        {
+        if is_propagation_stopped(se) {
             break;
         }
     }
+    set_current_target(se, &JsValue::null());
 }
 
 fn dispatch_event(container: &Element, event_type: String, e: &Event) {
@@ -84,112 +66,275 @@ fn dispatch_event(container: &Element, event_type: String, e: &Event) {
         return;
     }
 
-    let target_element = e.target().unwrap().dyn_into::<Element>().unwrap();
-    let Paths { capture, bubble } =
-        collect_paths(Some(target_element), container, event_type.as_str());
+    // The target may be a text node, start from its parent element
+    let target_node = e.target().unwrap().dyn_into::<Node>().unwrap();
+    let target_element = match target_node.dyn_into::<Element>() {
+        Ok(element) => Some(element),
+        Err(node) => node.parent_element(),
+    };
 
-    let se = create_synthetic_event(e.clone());
+    if let Some(callback_name_list) = get_event_callback_name_from_event_type(event_type.as_str()) {
+        let paths = collect_paths(
+            target_element.clone(),
+            container,
+            &callback_name_list,
+            is_non_delegated_event(event_type.as_str()),
+        );
+        let se = create_synthetic_event(e, event_type.as_str());
+        dispatch_paths(paths, &se, event_type.as_str());
+    }
 
+    // Change event plugin, onChange fires on input/click/change depending on the target
+    if let Some(target_element) = target_element {
+        if !is_change_event_target(&target_element, event_type.as_str()) {
+            return;
+        }
+        if should_dispatch_change_event(&target_element, event_type.as_str()) {
+            let callback_name_list = vec!["onChangeCapture".to_string(), "onChange".to_string()];
+            let paths = collect_paths(
+                Some(target_element.clone()),
+                container,
+                &callback_name_list,
+                false,
+            );
+            let se = create_synthetic_event(e, "change");
+            dispatch_paths(paths, &se, event_type.as_str());
+        }
+        enqueue_state_restore(target_element);
+    }
+}
+
+fn dispatch_paths(paths: Paths, se: &JsValue, event_type: &str) {
+    let Paths { capture, bubble } = paths;
     if is_dev() {
         log!("Event {} capture phase", event_type);
     }
 
-    trigger_event_flow(capture, &se);
-    if !derive_from_js_value(&se, "__stopPropagation")
-        .as_bool()
-        .unwrap()
-    {
+    trigger_event_flow(capture, se, event_type);
+    if !is_propagation_stopped(se) {
         if is_dev() {
             log!("Event {} bubble phase", event_type);
         }
-        trigger_event_flow(bubble, &se);
+        trigger_event_flow(bubble, se, event_type);
     }
 }
 
+// The updates triggered by the event are flushed in a microtask, restore the controlled
+// value after them, in case the state was not changed
+fn enqueue_state_restore(target_element: Element) {
+    ReactDomHostConfig::default().schedule_microtask(Box::new(move || {
+        let props = derive_from_js_value(&target_element, ELEMENT_EVENT_PROPS_KEY);
+        if props.is_object() {
+            restore_controlled_state(
+                &target_element,
+                target_element.local_name().as_str(),
+                &props,
+            );
+        }
+    }));
+}
+
+fn get_host_node(fiber: &Rc<RefCell<FiberNode>>) -> Option<Node> {
+    FiberNode::derive_state_node(fiber.clone())
+        .and_then(|node| node.downcast::<Node>().ok())
+        .map(|node| (*node).clone())
+}
+
+// callback_name_list is [onXxxCapture, onXxx]
+// The callbacks are collected along the fiber tree instead of the DOM tree, so the events in
+// a portal propagate to its React ancestors
 fn collect_paths(
-    mut target_element: Option<Element>,
+    target_element: Option<Element>,
     container: &Element,
-    event_type: &str,
+    callback_name_list: &[String],
+    target_only: bool,
 ) -> Paths {
     let mut paths = Paths::new();
-    while target_element.is_some() && !Object::is(target_element.as_ref().unwrap(), container) {
-        let event_props =
-            derive_from_js_value(target_element.as_ref().unwrap(), ELEMENT_EVENT_PROPS_KEY);
-        if event_props.is_object() {
-            let callback_name_list = get_event_callback_name_from_event_type(event_type);
-            if callback_name_list.is_some() {
-                for (i, callback_name) in callback_name_list.as_ref().unwrap().iter().enumerate() {
-                    let event_callback = derive_from_js_value(&event_props, *callback_name);
-                    if event_callback.is_function() {
-                        let event_callback = event_callback.dyn_ref::<Function>().unwrap();
-                        if i == 0 {
-                            paths.capture.insert(0, event_callback.clone());
-                        } else {
-                            paths.bubble.push(event_callback.clone());
+    // Non-delegated events don't bubble, only the target's bubble callback is triggered
+    let mut is_target = true;
+    // The event happened in a portal which is rendered into this container
+    let mut is_in_portal = false;
+    let container_node: &Node = container;
+    let mut fiber = get_closest_fiber_from_node(
+        target_element.map(|element| element.into()),
+        Some(container_node),
+    );
+    while let Some(f) = fiber {
+        let tag = { f.borrow().tag.clone() };
+        match tag {
+            WorkTag::HostComponent => {
+                let element = get_host_node(&f).and_then(|node| node.dyn_into::<Element>().ok());
+                if let Some(element) = element {
+                    let event_props = derive_from_js_value(&element, ELEMENT_EVENT_PROPS_KEY);
+                    if event_props.is_object() {
+                        for (i, callback_name) in callback_name_list.iter().enumerate() {
+                            if i == 1 && target_only && !is_target {
+                                continue;
+                            }
+                            let event_callback = derive_from_js_value(&event_props, callback_name);
+                            if event_callback.is_function() {
+                                let event_callback = event_callback.dyn_ref::<Function>().unwrap();
+                                if i == 0 {
+                                    paths
+                                        .capture
+                                        .insert(0, (element.clone(), event_callback.clone()));
+                                } else {
+                                    paths.bubble.push((element.clone(), event_callback.clone()));
+                                }
+                            }
                         }
                     }
+                    is_target = false;
+                }
+            }
+            WorkTag::HostPortal => {
+                let portal_container = get_host_node(&f);
+                match portal_container {
+                    Some(node) if Object::is(&node, container) => is_in_portal = true,
+                    // The portal container listens to the events itself, and dispatches them
+                    // to the ancestors of the portal too
+                    _ => return Paths::new(),
+                }
+            }
+            WorkTag::HostRoot => {
+                let root_container = get_host_node(&f);
+                match root_container {
+                    Some(node) if !is_in_portal && !Object::is(&node, container) => {
+                        // The target is inside a nested root which has dispatched the event to
+                        // its own tree already, only the callbacks outside of it belong to
+                        // this root
+                        paths = Paths::new();
+                        is_target = false;
+                        fiber =
+                            get_closest_fiber_from_node(node.parent_node(), Some(container_node));
+                        continue;
+                    }
+                    _ => break,
                 }
             }
+            _ => {}
         }
-        target_element = target_element.unwrap().parent_element();
+        fiber = f.borrow()._return.clone();
     }
     paths
 }
 
-fn get_event_callback_name_from_event_type(event_type: &str) -> Option<Vec<&str>> {
-    if event_type == "click" {
-        return Some(vec!["onClickCapture", "onClick"]);
-    }
-    None
+fn is_root_container(element: &Element) -> bool {
+    derive_from_js_value(element, LISTENING_MARKER).is_truthy()
 }
 
-pub fn init_event(container: JsValue, event_type: String) {
-    if !VALID_EVENT_TYPE_LIST.contains(&event_type.clone().as_str()) {
-        log!("Unsupported event type: {:?}", event_type);
-        return;
-    }
-
-    if is_dev() {
-        log!("Init event {:?}", event_type);
-    }
+fn listen_to_native_event(container: &Element, event_type: &'static str) -> EventListener {
+    // Non-delegated events don't bubble to the container, so we catch them in the capture phase
+    let options = EventListenerOptions {
+        phase: if is_non_delegated_event(event_type) {
+            EventListenerPhase::Capture
+        } else {
+            EventListenerPhase::Bubble
+        },
+        passive: is_passive_event(event_type),
+    };
+    let element = container.clone();
+    EventListener::new_with_options(container, event_type, options, move |event| {
+        dispatch_event(&element, event_type.to_string(), event)
+    })
+}
 
+// Listen to all the supported events on the root container. Returns None if the container
+// is already listened to by another root, the listeners are removed when they are dropped
+pub fn listen_to_all_supported_events(container: &JsValue) -> Option<Vec<EventListener>> {
     let element = container
         .clone()
         .dyn_into::<Element>()
         .expect("container is not element");
-    let on_click = EventListener::new(&element.clone(), event_type.clone(), move |event| {
-        dispatch_event(&element, event_type.clone(), event)
+    if is_root_container(&element) {
+        if is_dev() {
+            log!("The container is already listened to by another root");
+        }
+        return None;
+    }
+    Reflect::set(
+        &element,
+        &LISTENING_MARKER.into(),
+        &JsValue::from_bool(true),
+    )
+    .expect("TODO: panic set LISTENING_MARKER");
+
+    if is_dev() {
+        log!("Init events on {:?}", container);
+    }
+
+    Some(
+        all_native_events()
+            .map(|event_type| listen_to_native_event(&element, event_type))
+            .collect(),
+    )
+}
+
+pub fn remove_all_event_listeners(container: &JsValue, listeners: Vec<EventListener>) {
+    drop(listeners);
+    Reflect::delete_property(
+        container.unchecked_ref::<Object>(),
+        &LISTENING_MARKER.into(),
+    )
+    .expect("TODO: panic delete LISTENING_MARKER");
+}
+
+// A portal container is listened to once, however many roots render portals into it, and the
+// listeners are removed when the last of these roots is unmounted
+struct PortalContainerListeners {
+    container: Element,
+    roots: usize,
+    // None if the container is a root container, which listens to the events itself
+    listeners: Option<Vec<EventListener>>,
+}
+
+thread_local! {
+    static PORTAL_CONTAINER_LISTENERS: RefCell<Vec<PortalContainerListeners>> = RefCell::new(vec![]);
+}
+
+pub fn listen_to_portal_container(container: &Element) {
+    PORTAL_CONTAINER_LISTENERS.with(|portal_containers| {
+        let mut portal_containers = portal_containers.borrow_mut();
+        match portal_containers
+            .iter_mut()
+            .find(|portal_container| Object::is(&portal_container.container, container))
+        {
+            Some(portal_container) => portal_container.roots += 1,
+            None => portal_containers.push(PortalContainerListeners {
+                container: container.clone(),
+                roots: 1,
+                listeners: listen_to_all_supported_events(&container.clone().into()),
+            }),
+        }
+    });
+}
+
+pub fn unlisten_to_portal_container(container: &Element) {
+    let listeners = PORTAL_CONTAINER_LISTENERS.with(|portal_containers| {
+        let mut portal_containers = portal_containers.borrow_mut();
+        let index = portal_containers
+            .iter()
+            .position(|portal_container| Object::is(&portal_container.container, container))?;
+        portal_containers[index].roots -= 1;
+        if portal_containers[index].roots > 0 {
+            return None;
+        }
+        portal_containers.remove(index).listeners
     });
-    on_click.forget();
+    if let Some(listeners) = listeners {
+        remove_all_event_listeners(&container.clone().into(), listeners);
+    }
 }
 
 pub fn update_fiber_props(node: &Element, props: &JsValue) {
     // log!("update_fiber_props {:?}", node);
-    let js_value = derive_from_js_value(&node, ELEMENT_EVENT_PROPS_KEY);
-    let element_event_props = if js_value.is_object() {
-        js_value.dyn_into::<Object>().unwrap()
+    // Keep the latest props on the node, the event callbacks are looked up from them
+    // when the event is dispatched, so props removed by an update are dropped too
+    let element_event_props = if props.is_object() {
+        props.clone()
     } else {
-        Object::new()
+        Object::new().into()
     };
-    for event_type in VALID_EVENT_TYPE_LIST {
-        let callback_name_list = get_event_callback_name_from_event_type(event_type);
-        if callback_name_list.is_none() {
-            break;
-        }
-
-        for callback_name in callback_name_list.clone().unwrap() {
-            if props.is_object()
-                && props
-                    .dyn_ref::<Object>()
-                    .unwrap()
-                    .has_own_property(&callback_name.into())
-            {
-                let callback = derive_from_js_value(props, callback_name);
-                Reflect::set(&element_event_props, &callback_name.into(), &callback)
-                    .expect("TODO: panic set callback_name");
-            }
-        }
-    }
     Reflect::set(&node, &ELEMENT_EVENT_PROPS_KEY.into(), &element_event_props)
         .expect("TODO: set ELEMENT_EVENT_PROPS_KEY");
 }
@@ -0,0 +1,282 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::js_sys::{Array, Function, Object, Reflect};
+use web_sys::Event;
+
+use shared::derive_from_js_value;
+
+static PROPAGATION_STOPPED_KEY: &str = "__propagationStopped";
+
+thread_local! {
+    // The methods are shared by all the synthetic events, so they are created only once
+    static SYNTHETIC_EVENT_PROTOTYPE: Object = create_synthetic_event_prototype();
+}
+
+// Properties copied from the native event, see https://developer.mozilla.org/en-US/docs/Web/API/Event
+static EVENT_INTERFACE: [&str; 6] = [
+    "eventPhase",
+    "bubbles",
+    "cancelable",
+    "timeStamp",
+    "defaultPrevented",
+    "isTrusted",
+];
+
+static UI_EVENT_INTERFACE: [&str; 2] = ["view", "detail"];
+
+static MOUSE_EVENT_INTERFACE: [&str; 15] = [
+    "screenX",
+    "screenY",
+    "clientX",
+    "clientY",
+    "pageX",
+    "pageY",
+    "ctrlKey",
+    "shiftKey",
+    "altKey",
+    "metaKey",
+    "button",
+    "buttons",
+    "relatedTarget",
+    "movementX",
+    "movementY",
+];
+
+static DRAG_EVENT_INTERFACE: [&str; 1] = ["dataTransfer"];
+
+static FOCUS_EVENT_INTERFACE: [&str; 1] = ["relatedTarget"];
+
+static KEYBOARD_EVENT_INTERFACE: [&str; 12] = [
+    "key", "code", "location", "ctrlKey", "shiftKey", "altKey", "metaKey", "repeat", "locale",
+    "charCode", "keyCode", "which",
+];
+
+static POINTER_EVENT_INTERFACE: [&str; 10] = [
+    "pointerId",
+    "width",
+    "height",
+    "pressure",
+    "tangentialPressure",
+    "tiltX",
+    "tiltY",
+    "twist",
+    "pointerType",
+    "isPrimary",
+];
+
+static TOUCH_EVENT_INTERFACE: [&str; 7] = [
+    "touches",
+    "targetTouches",
+    "changedTouches",
+    "altKey",
+    "metaKey",
+    "ctrlKey",
+    "shiftKey",
+];
+
+static WHEEL_EVENT_INTERFACE: [&str; 4] = ["deltaX", "deltaY", "deltaZ", "deltaMode"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventInterface {
+    Event,
+    UIEvent,
+    MouseEvent,
+    DragEvent,
+    FocusEvent,
+    KeyboardEvent,
+    PointerEvent,
+    TouchEvent,
+    WheelEvent,
+}
+
+impl EventInterface {
+    pub fn from_event_type(event_type: &str) -> Self {
+        match event_type {
+            "keydown" | "keypress" | "keyup" => EventInterface::KeyboardEvent,
+            "focusin" | "focusout" => EventInterface::FocusEvent,
+            "click" | "auxclick" | "dblclick" | "contextmenu" | "mousedown" | "mousemove"
+            | "mouseout" | "mouseover" | "mouseup" | "mouseenter" | "mouseleave" => {
+                EventInterface::MouseEvent
+            }
+            "drag" | "dragend" | "dragenter" | "dragexit" | "dragleave" | "dragover"
+            | "dragstart" | "drop" => EventInterface::DragEvent,
+            "gotpointercapture" | "lostpointercapture" | "pointercancel" | "pointerdown"
+            | "pointermove" | "pointerout" | "pointerover" | "pointerup" | "pointerenter"
+            | "pointerleave" => EventInterface::PointerEvent,
+            "touchcancel" | "touchend" | "touchmove" | "touchstart" => EventInterface::TouchEvent,
+            "wheel" => EventInterface::WheelEvent,
+            "scroll" => EventInterface::UIEvent,
+            _ => EventInterface::Event,
+        }
+    }
+
+    // Each interface extends the previous ones, e.g. WheelEvent -> MouseEvent -> UIEvent -> Event
+    fn properties(&self) -> Vec<&'static str> {
+        let mut properties = EVENT_INTERFACE.to_vec();
+        if *self == EventInterface::Event {
+            return properties;
+        }
+        properties.extend(UI_EVENT_INTERFACE);
+        match self {
+            EventInterface::MouseEvent => properties.extend(MOUSE_EVENT_INTERFACE),
+            EventInterface::DragEvent => {
+                properties.extend(MOUSE_EVENT_INTERFACE);
+                properties.extend(DRAG_EVENT_INTERFACE);
+            }
+            EventInterface::PointerEvent => {
+                properties.extend(MOUSE_EVENT_INTERFACE);
+                properties.extend(POINTER_EVENT_INTERFACE);
+            }
+            EventInterface::WheelEvent => {
+                properties.extend(MOUSE_EVENT_INTERFACE);
+                properties.extend(WHEEL_EVENT_INTERFACE);
+            }
+            EventInterface::FocusEvent => properties.extend(FOCUS_EVENT_INTERFACE),
+            EventInterface::KeyboardEvent => properties.extend(KEYBOARD_EVENT_INTERFACE),
+            EventInterface::TouchEvent => properties.extend(TOUCH_EVENT_INTERFACE),
+            _ => {}
+        }
+        properties
+    }
+}
+
+fn get_native_event(synthetic_event: &JsValue) -> Option<Event> {
+    derive_from_js_value(synthetic_event, "nativeEvent")
+        .dyn_into::<Event>()
+        .ok()
+}
+
+fn prevent_default(synthetic_event: &JsValue, _args: &Array) -> JsValue {
+    Reflect::set(
+        synthetic_event,
+        &"defaultPrevented".into(),
+        &JsValue::from_bool(true),
+    )
+    .expect("TODO: panic set defaultPrevented");
+    if let Some(native_event) = get_native_event(synthetic_event) {
+        if native_event.cancelable() {
+            native_event.prevent_default();
+        }
+    }
+    JsValue::undefined()
+}
+
+fn is_default_prevented(synthetic_event: &JsValue, _args: &Array) -> JsValue {
+    JsValue::from_bool(derive_from_js_value(synthetic_event, "defaultPrevented").is_truthy())
+}
+
+fn stop_propagation(synthetic_event: &JsValue, _args: &Array) -> JsValue {
+    Reflect::set(
+        synthetic_event,
+        &PROPAGATION_STOPPED_KEY.into(),
+        &JsValue::from_bool(true),
+    )
+    .expect("TODO: panic set __propagationStopped");
+    if let Some(native_event) = get_native_event(synthetic_event) {
+        native_event.stop_propagation();
+    }
+    JsValue::undefined()
+}
+
+fn is_propagation_stopped_method(synthetic_event: &JsValue, _args: &Array) -> JsValue {
+    JsValue::from_bool(derive_from_js_value(synthetic_event, PROPAGATION_STOPPED_KEY).is_truthy())
+}
+
+// Synthetic events are not pooled, persist and isPersistent are kept for compatibility
+fn persist(_synthetic_event: &JsValue, _args: &Array) -> JsValue {
+    JsValue::undefined()
+}
+
+fn is_persistent(_synthetic_event: &JsValue, _args: &Array) -> JsValue {
+    JsValue::from_bool(true)
+}
+
+fn get_modifier_state(synthetic_event: &JsValue, args: &Array) -> JsValue {
+    let native_event = derive_from_js_value(synthetic_event, "nativeEvent");
+    let get_modifier_state = derive_from_js_value(&native_event, "getModifierState");
+    match get_modifier_state.dyn_ref::<Function>() {
+        Some(f) => JsValue::from_bool(
+            f.call1(&native_event, &args.get(0))
+                .map_or(false, |state| state.is_truthy()),
+        ),
+        None => JsValue::from_bool(false),
+    }
+}
+
+// Closures can't see `this`, so a plain JS function passes the event it's called on to the closure
+fn create_method(method: fn(&JsValue, &Array) -> JsValue) -> JsValue {
+    let closure = Closure::wrap(
+        Box::new(move |this: JsValue, args: Array| method(&this, &args))
+            as Box<dyn Fn(JsValue, Array) -> JsValue>,
+    );
+    let shim = Function::new_with_args(
+        "method",
+        "return function (...args) { return method(this, args) }",
+    )
+    .call1(&JsValue::null(), closure.as_ref())
+    .expect("TODO: panic create method");
+    closure.forget();
+    shim
+}
+
+fn create_synthetic_event_prototype() -> Object {
+    let prototype = Object::new();
+    let methods: [(&str, fn(&JsValue, &Array) -> JsValue); 7] = [
+        ("preventDefault", prevent_default),
+        ("isDefaultPrevented", is_default_prevented),
+        ("stopPropagation", stop_propagation),
+        ("isPropagationStopped", is_propagation_stopped_method),
+        ("persist", persist),
+        ("isPersistent", is_persistent),
+        ("getModifierState", get_modifier_state),
+    ];
+    for (name, method) in methods {
+        Reflect::set(&prototype, &name.into(), &create_method(method))
+            .expect("TODO: panic set prototype method");
+    }
+    prototype
+}
+
+/**
+ * Create a synthetic event which wraps the native event, the properties of the
+ * event interface are copied from the native event.
+ */
+pub fn create_synthetic_event(native_event: &Event, event_type: &str) -> JsValue {
+    let synthetic_event = SYNTHETIC_EVENT_PROTOTYPE.with(|prototype| Object::create(prototype));
+    let set = |key: &str, value: &JsValue| {
+        Reflect::set(&synthetic_event, &key.into(), value)
+            .expect("TODO: panic set synthetic_event");
+    };
+
+    set("type", &event_type.into());
+    set("nativeEvent", native_event);
+    set(
+        "target",
+        &native_event
+            .target()
+            .map_or(JsValue::null(), |target| target.into()),
+    );
+    set("currentTarget", &JsValue::null());
+    for property in EventInterface::from_event_type(event_type).properties() {
+        set(property, &derive_from_js_value(native_event, property));
+    }
+    set(PROPAGATION_STOPPED_KEY, &JsValue::from_bool(false));
+    synthetic_event.into()
+}
+
+fn call_method(synthetic_event: &JsValue, name: &str) -> JsValue {
+    derive_from_js_value(synthetic_event, name)
+        .dyn_ref::<Function>()
+        .expect("synthetic event method is not function")
+        .call0(synthetic_event)
+        .expect("TODO: panic call synthetic event method")
+}
+
+pub fn is_propagation_stopped(synthetic_event: &JsValue) -> bool {
+    call_method(synthetic_event, "isPropagationStopped").is_truthy()
+}
+
+pub fn set_current_target(synthetic_event: &JsValue, current_target: &JsValue) {
+    Reflect::set(synthetic_event, &"currentTarget".into(), current_target)
+        .expect("TODO: panic set currentTarget");
+}
@@ -87,7 +87,20 @@ impl HostConfig for ReactNoopHostConfig {
         Rc::new(JsValue::from(obj))
     }
 
-    fn create_instance(&self, _type: String, props: Rc<dyn Any>) -> Rc<dyn Any> {
+    fn get_root_host_context(&self, _container: Rc<dyn Any>) -> JsValue {
+        JsValue::null()
+    }
+
+    fn get_child_host_context(&self, _parent_host_context: &JsValue, _type: &str) -> JsValue {
+        JsValue::null()
+    }
+
+    fn create_instance(
+        &self,
+        _type: String,
+        props: Rc<dyn Any>,
+        _host_context: &JsValue,
+    ) -> Rc<dyn Any> {
         let obj = Object::new();
         Reflect::set(&obj, &"id".into(), &getCounter().into());
         Reflect::set(&obj, &"type".into(), &_type.into());
@@ -115,6 +128,8 @@ impl HostConfig for ReactNoopHostConfig {
         children.push(&c);
     }
 
+    fn finalize_initial_children(&self, _instance: Rc<dyn Any>, _type: String, _props: &JsValue) {}
+
     fn append_child_to_container(&self, child: Rc<dyn Any>, container: Rc<dyn Any>) {
         let container = container.clone().downcast::<JsValue>().unwrap();
         let c = child.clone().downcast::<JsValue>().unwrap();
@@ -214,6 +229,49 @@ impl HostConfig for ReactNoopHostConfig {
         }
     }
 
+    fn prepare_update(
+        &self,
+        _instance: Rc<dyn Any>,
+        _type: String,
+        old_props: &JsValue,
+        new_props: &JsValue,
+    ) -> Option<JsValue> {
+        let keys = Object::keys(new_props.dyn_ref::<Object>().unwrap());
+        let changed = keys.length()
+            != Object::keys(old_props.dyn_ref::<Object>().unwrap()).length()
+            || keys.iter().any(|key| {
+                key != "children"
+                    && !Object::is(
+                        &Reflect::get(old_props, &key).unwrap(),
+                        &Reflect::get(new_props, &key).unwrap(),
+                    )
+            });
+        if changed {
+            Some(JsValue::from_bool(true))
+        } else {
+            None
+        }
+    }
+
+    fn should_set_text_content(&self, _type: &str, _props: &JsValue) -> bool {
+        false
+    }
+
+    fn reset_text_content(&self, _instance: Rc<dyn Any>) {}
+
+    fn get_public_instance(&self, instance: Rc<dyn Any>) -> JsValue {
+        let instance = instance.downcast::<JsValue>().unwrap();
+        (*instance).clone()
+    }
+
+    fn get_portal_container(&self, container_info: &JsValue) -> Rc<dyn Any> {
+        Rc::new(container_info.clone())
+    }
+
+    fn prepare_portal_mount(&self, _container: Rc<dyn Any>) {}
+
+    fn precache_fiber_node(&self, _fiber: Rc<RefCell<FiberNode>>, _instance: Rc<dyn Any>) {}
+
     fn commit_update(&self, fiber: Rc<RefCell<FiberNode>>) {
         match fiber.borrow().tag {
             WorkTag::HostText => {
@@ -221,6 +279,12 @@ impl HostConfig for ReactNoopHostConfig {
                 let instance = FiberNode::derive_state_node(fiber.clone());
                 self.commit_text_update(instance.unwrap(), &text);
             }
+            WorkTag::HostComponent => {
+                let instance = FiberNode::derive_state_node(fiber.clone()).unwrap();
+                let instance = instance.downcast::<JsValue>().unwrap();
+                Reflect::set(&instance, &"props".into(), &fiber.borrow().memoized_props)
+                    .expect("TODO: panic set props");
+            }
             _ => {
                 log!("Unsupported update type")
             }
@@ -3,23 +3,33 @@ use std::rc::Rc;
 
 use wasm_bindgen::{JsCast, JsValue};
 
-use shared::{derive_from_js_value, is_dev, log, shallow_equal};
+use shared::{derive_from_js_value, is_dev, log, shallow_equal, REACT_FORWARD_REF_TYPE};
 use web_sys::js_sys::{Function, Object, Reflect};
 
 use crate::child_fiber::{clone_child_fiblers, mount_child_fibers, reconcile_child_fibers};
 use crate::fiber::{FiberNode, MemoizedState};
-use crate::fiber_context::{prepare_to_read_context, propagate_context_change, push_provider};
+use crate::fiber_class_component::{
+    construct_class_instance, get_class_instance, is_class_component, mount_class_instance,
+    resume_mount_class_instance, update_class_instance,
+};
+use crate::fiber_context::{
+    prepare_to_read_context, propagate_context_change, push_provider, read_context,
+};
 use crate::fiber_flags::Flags;
-use crate::fiber_hooks::{bailout_hook, render_with_hooks};
+use crate::fiber_hooks::{bailout_hook, check_did_render_id_hook, render_with_hooks};
+use crate::fiber_host_context::{push_host_container, push_host_context};
 use crate::fiber_lanes::{include_some_lanes, Lane};
+use crate::fiber_tree_context::{
+    get_forks_at_level, is_forked_child, is_tree_id_enabled, push_materialized_tree_id,
+    push_tree_id,
+};
+use crate::root_state::RootState;
 use crate::suspense_context::push_suspense_handler;
 use crate::update_queue::{process_update_queue, ReturnOfProcessUpdateQueue};
 use crate::work_tags::WorkTag;
 
-static mut DID_RECEIVE_UPDATE: bool = false;
-
-pub fn mark_wip_received_update() {
-    unsafe { DID_RECEIVE_UPDATE = true };
+pub fn mark_wip_received_update(state: &RootState) {
+    state.did_receive_update.set(true);
 }
 
 fn bailout_on_already_finished_work(
@@ -48,12 +58,21 @@ fn check_scheduled_update_or_context(current: Rc<RefCell<FiberNode>>, render_lan
 }
 
 pub fn begin_work(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
     render_lane: Lane,
 ) -> Result<Option<Rc<RefCell<FiberNode>>>, JsValue> {
-    unsafe {
-        DID_RECEIVE_UPDATE = false;
-    };
+    state.did_receive_update.set(false);
+
+    if is_forked_child(state, work_in_progress.clone()) {
+        let index = { work_in_progress.borrow().index };
+        push_tree_id(
+            state,
+            work_in_progress.clone(),
+            get_forks_at_level(state),
+            index,
+        );
+    }
 
     // TODO work with suspense
     let current = { work_in_progress.borrow().alternate.clone() };
@@ -65,17 +84,25 @@ pub fn begin_work(
         let new_props = work_in_progress.borrow().pending_props.clone();
         let new_type = work_in_progress.borrow()._type.clone();
         if !Object::is(&old_props, &new_props) || !Object::is(&old_type, &new_type) {
-            unsafe { DID_RECEIVE_UPDATE = true }
+            state.did_receive_update.set(true);
         } else {
             let has_scheduled_update_or_context =
                 check_scheduled_update_or_context(current.clone(), render_lane.clone());
             // The current fiber lane is not included in render_lane
             // TODO context
+            // The error boundary which has captured an error should be rendered again
+            let did_capture = work_in_progress.borrow().flags.contains(Flags::DidCapture);
             if !has_scheduled_update_or_context
                 && current.borrow().tag != WorkTag::SuspenseComponent
+                && !did_capture
             {
-                unsafe { DID_RECEIVE_UPDATE = false }
-                match work_in_progress.borrow().tag {
+                state.did_receive_update.set(false);
+                let tag = { work_in_progress.borrow().tag.clone() };
+                match tag {
+                    WorkTag::HostRoot | WorkTag::HostPortal => {
+                        push_host_container(state, work_in_progress.clone())
+                    }
+                    WorkTag::HostComponent => push_host_context(state, work_in_progress.clone()),
                     WorkTag::ContextProvider => {
                         let new_value = derive_from_js_value(
                             &work_in_progress.borrow().memoized_props,
@@ -83,7 +110,7 @@ pub fn begin_work(
                         );
                         let context =
                             derive_from_js_value(&work_in_progress.borrow()._type, "_context");
-                        push_provider(&context, new_value);
+                        push_provider(state, &context, new_value);
                     }
                     _ => {}
                 }
@@ -105,24 +132,56 @@ pub fn begin_work(
     return match tag {
         WorkTag::FunctionComponent => {
             let Component = { work_in_progress.borrow()._type.clone() };
-            update_function_component(work_in_progress.clone(), Component, render_lane)
+            update_function_component(state, work_in_progress.clone(), Component, render_lane)
+        }
+        WorkTag::ClassComponent => {
+            let Component = { work_in_progress.borrow()._type.clone() };
+            update_class_component(state, work_in_progress.clone(), Component, render_lane)
+        }
+        WorkTag::HostRoot => {
+            push_host_container(state, work_in_progress.clone());
+            Ok(update_host_root(
+                state,
+                work_in_progress.clone(),
+                render_lane,
+            ))
+        }
+        WorkTag::HostComponent => {
+            push_host_context(state, work_in_progress.clone());
+            Ok(update_host_component(state, work_in_progress.clone()))
         }
-        WorkTag::HostRoot => Ok(update_host_root(work_in_progress.clone(), render_lane)),
-        WorkTag::HostComponent => Ok(update_host_component(work_in_progress.clone())),
         WorkTag::HostText => Ok(None),
+        WorkTag::HostPortal => Ok(update_portal_component(state, work_in_progress.clone())),
         WorkTag::ContextProvider => Ok(update_context_provider(
+            state,
             work_in_progress.clone(),
             render_lane.clone(),
         )),
-        WorkTag::MemoComponent => update_memo_component(work_in_progress.clone(), render_lane),
-        WorkTag::Fragment => Ok(update_fragment(work_in_progress.clone())),
-        WorkTag::SuspenseComponent => Ok(update_suspense_component(work_in_progress.clone())),
-        WorkTag::OffscreenComponent => Ok(update_offscreen_component(work_in_progress.clone())),
-        WorkTag::LazyComponent => update_lazy_component(work_in_progress.clone(), render_lane),
+        WorkTag::ContextConsumer => {
+            update_context_consumer(state, work_in_progress.clone(), render_lane.clone())
+        }
+        WorkTag::MemoComponent => {
+            update_memo_component(state, work_in_progress.clone(), render_lane)
+        }
+        WorkTag::ForwardRef => {
+            let Component = { work_in_progress.borrow()._type.clone() };
+            update_forward_ref(state, work_in_progress.clone(), Component, render_lane)
+        }
+        WorkTag::Fragment => Ok(update_fragment(state, work_in_progress.clone())),
+        WorkTag::SuspenseComponent => {
+            Ok(update_suspense_component(state, work_in_progress.clone()))
+        }
+        WorkTag::OffscreenComponent => {
+            Ok(update_offscreen_component(state, work_in_progress.clone()))
+        }
+        WorkTag::LazyComponent => {
+            update_lazy_component(state, work_in_progress.clone(), render_lane)
+        }
     };
 }
 
 fn update_lazy_component(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
     render_lane: Lane,
 ) -> Result<Option<Rc<RefCell<FiberNode>>>, JsValue> {
@@ -132,8 +191,16 @@ fn update_lazy_component(
     let init = init_jsvalue.dyn_ref::<Function>().unwrap();
     let Component = init.call1(&JsValue::null(), &payload)?;
     work_in_progress.borrow_mut()._type = Component.clone();
+    if is_class_component(&Component) {
+        work_in_progress.borrow_mut().tag = WorkTag::ClassComponent;
+        return update_class_component(state, work_in_progress, Component, render_lane);
+    }
+    if is_forward_ref(&Component) {
+        work_in_progress.borrow_mut().tag = WorkTag::ForwardRef;
+        return update_forward_ref(state, work_in_progress, Component, render_lane);
+    }
     work_in_progress.borrow_mut().tag = WorkTag::FunctionComponent;
-    let child = update_function_component(work_in_progress, Component.clone(), render_lane);
+    let child = update_function_component(state, work_in_progress, Component.clone(), render_lane);
     child
 }
 
@@ -262,6 +329,7 @@ fn update_suspense_primary_children(
 }
 
 fn update_suspense_component(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
 ) -> Option<Rc<RefCell<FiberNode>>> {
     let current = { work_in_progress.borrow().alternate.clone() };
@@ -278,7 +346,7 @@ fn update_suspense_component(
 
     let next_primary_children = derive_from_js_value(&next_props, "children");
     let next_fallback_children = derive_from_js_value(&next_props, "fallback");
-    push_suspense_handler(work_in_progress.clone());
+    push_suspense_handler(state, work_in_progress.clone());
 
     if current.is_none() {
         if show_fallback {
@@ -310,21 +378,26 @@ fn update_suspense_component(
 }
 
 fn update_offscreen_component(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
 ) -> Option<Rc<RefCell<FiberNode>>> {
     let next_props = { work_in_progress.borrow().pending_props.clone() };
     let next_children = derive_from_js_value(&next_props, "children");
-    reconcile_children(work_in_progress.clone(), Some(next_children));
+    reconcile_children(state, work_in_progress.clone(), Some(next_children));
     work_in_progress.borrow().child.clone()
 }
 
-fn update_fragment(work_in_progress: Rc<RefCell<FiberNode>>) -> Option<Rc<RefCell<FiberNode>>> {
+fn update_fragment(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+) -> Option<Rc<RefCell<FiberNode>>> {
     let next_children = { work_in_progress.borrow().pending_props.clone() };
-    reconcile_children(work_in_progress.clone(), Some(next_children));
+    reconcile_children(state, work_in_progress.clone(), Some(next_children));
     work_in_progress.borrow().child.clone()
 }
 
 fn update_memo_component(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
     render_lane: Lane,
 ) -> Result<Option<Rc<RefCell<FiberNode>>>, JsValue> {
@@ -349,7 +422,7 @@ fn update_memo_component(
             }
 
             if props_equal && Object::is(&current.borrow()._ref, &work_in_progress.borrow()._ref) {
-                unsafe { DID_RECEIVE_UPDATE = false };
+                state.did_receive_update.set(false);
                 work_in_progress.borrow_mut().pending_props = prev_props;
                 work_in_progress.borrow_mut().lanes = current.borrow().lanes.clone();
                 return Ok(bailout_on_already_finished_work(
@@ -360,10 +433,54 @@ fn update_memo_component(
         }
     }
     let Component = { derive_from_js_value(&work_in_progress.borrow()._type, "type") };
-    update_function_component(work_in_progress.clone(), Component, render_lane)
+    if is_forward_ref(&Component) {
+        return update_forward_ref(state, work_in_progress.clone(), Component, render_lane);
+    }
+    update_function_component(state, work_in_progress.clone(), Component, render_lane)
+}
+
+fn is_forward_ref(Component: &JsValue) -> bool {
+    Component.is_object() && derive_from_js_value(Component, "$$typeof") == REACT_FORWARD_REF_TYPE
+}
+
+// The ref of the element is passed to the render function instead of being attached
+fn update_forward_ref(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+    Component: JsValue,
+    render_lane: Lane,
+) -> Result<Option<Rc<RefCell<FiberNode>>>, JsValue> {
+    let render = derive_from_js_value(&Component, "render");
+    let _ref = { work_in_progress.borrow()._ref.clone() };
+    prepare_to_read_context(state, work_in_progress.clone(), render_lane.clone());
+    let next_children = render_with_hooks(
+        state,
+        work_in_progress.clone(),
+        render,
+        _ref,
+        render_lane.clone(),
+    )?;
+    let has_id = check_did_render_id_hook(state);
+
+    let current = { work_in_progress.borrow().alternate.clone() };
+    if current.is_some() && !state.did_receive_update.get() {
+        bailout_hook(work_in_progress.clone(), render_lane.clone());
+        return Ok(bailout_on_already_finished_work(
+            work_in_progress,
+            render_lane,
+        ));
+    }
+
+    if has_id && is_tree_id_enabled(state) {
+        push_materialized_tree_id(state, work_in_progress.clone());
+    }
+
+    reconcile_children(state, work_in_progress.clone(), Some(next_children));
+    Ok(work_in_progress.clone().borrow().child.clone())
 }
 
 fn update_context_provider(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
     render_lane: Lane,
 ) -> Option<Rc<RefCell<FiberNode>>> {
@@ -373,7 +490,7 @@ fn update_context_provider(
     let old_props = { work_in_progress.borrow().memoized_props.clone() };
     let new_value = derive_from_js_value(&new_props, "value");
 
-    push_provider(&context, derive_from_js_value(&new_props, "value"));
+    push_provider(state, &context, derive_from_js_value(&new_props, "value"));
 
     if !old_props.is_null() {
         let old_value = derive_from_js_value(&old_props, "value");
@@ -390,21 +507,54 @@ fn update_context_provider(
     }
 
     let next_children = derive_from_js_value(&new_props, "children");
-    reconcile_children(work_in_progress.clone(), Some(next_children));
+    reconcile_children(state, work_in_progress.clone(), Some(next_children));
     work_in_progress.clone().borrow().child.clone()
 }
 
+// <Context.Consumer>{value => ...}</Context.Consumer>
+fn update_context_consumer(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+    render_lane: Lane,
+) -> Result<Option<Rc<RefCell<FiberNode>>>, JsValue> {
+    let consumer_type = { work_in_progress.borrow()._type.clone() };
+    let context = derive_from_js_value(&consumer_type, "_context");
+    let new_props = { work_in_progress.borrow().pending_props.clone() };
+    let render = derive_from_js_value(&new_props, "children");
+    if !render.is_function() {
+        return Err(JsValue::from_str(
+            "A context consumer was rendered with multiple children, or a child that isn't a function.",
+        ));
+    }
+
+    prepare_to_read_context(state, work_in_progress.clone(), render_lane);
+    let new_value = read_context(state, Some(work_in_progress.clone()), context);
+    let next_children = render
+        .dyn_ref::<Function>()
+        .unwrap()
+        .call1(&JsValue::null(), &new_value)?;
+    reconcile_children(state, work_in_progress.clone(), Some(next_children));
+    Ok(work_in_progress.clone().borrow().child.clone())
+}
+
 fn update_function_component(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
     Component: JsValue,
     render_lane: Lane,
 ) -> Result<Option<Rc<RefCell<FiberNode>>>, JsValue> {
-    prepare_to_read_context(work_in_progress.clone(), render_lane.clone());
-    let next_children =
-        render_with_hooks(work_in_progress.clone(), Component, render_lane.clone())?;
+    prepare_to_read_context(state, work_in_progress.clone(), render_lane.clone());
+    let next_children = render_with_hooks(
+        state,
+        work_in_progress.clone(),
+        Component,
+        JsValue::undefined(),
+        render_lane.clone(),
+    )?;
+    let has_id = check_did_render_id_hook(state);
 
     let current = { work_in_progress.borrow().alternate.clone() };
-    if current.is_some() && unsafe { !DID_RECEIVE_UPDATE } {
+    if current.is_some() && !state.did_receive_update.get() {
         bailout_hook(work_in_progress.clone(), render_lane.clone());
         return Ok(bailout_on_already_finished_work(
             work_in_progress,
@@ -412,11 +562,101 @@ fn update_function_component(
         ));
     }
 
-    reconcile_children(work_in_progress.clone(), Some(next_children));
+    if has_id && is_tree_id_enabled(state) {
+        push_materialized_tree_id(state, work_in_progress.clone());
+    }
+
+    reconcile_children(state, work_in_progress.clone(), Some(next_children));
     Ok(work_in_progress.clone().borrow().child.clone())
 }
 
+fn update_class_component(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+    Component: JsValue,
+    render_lane: Lane,
+) -> Result<Option<Rc<RefCell<FiberNode>>>, JsValue> {
+    prepare_to_read_context(state, work_in_progress.clone(), render_lane.clone());
+    let next_props = { work_in_progress.borrow().pending_props.clone() };
+    let current = { work_in_progress.borrow().alternate.clone() };
+
+    let should_update = match current.clone() {
+        Some(current) if work_in_progress.borrow().state_node.is_some() => update_class_instance(
+            state,
+            current,
+            work_in_progress.clone(),
+            &Component,
+            &next_props,
+            render_lane.clone(),
+        )?,
+        _ if work_in_progress.borrow().state_node.is_some() => resume_mount_class_instance(
+            state,
+            work_in_progress.clone(),
+            &Component,
+            &next_props,
+            render_lane.clone(),
+        )?,
+        _ => {
+            construct_class_instance(state, work_in_progress.clone(), &Component, &next_props)?;
+            mount_class_instance(
+                state,
+                work_in_progress.clone(),
+                &Component,
+                &next_props,
+                render_lane.clone(),
+            )?;
+            true
+        }
+    };
+
+    let did_capture = work_in_progress.borrow().flags.contains(Flags::DidCapture);
+    mark_ref(current.clone(), work_in_progress.clone());
+    if !should_update && !did_capture {
+        return Ok(bailout_on_already_finished_work(
+            work_in_progress,
+            render_lane,
+        ));
+    }
+
+    let instance = get_class_instance(work_in_progress.clone()).unwrap();
+    let next_children = if did_capture
+        && !derive_from_js_value(&Component, "getDerivedStateFromError").is_function()
+    {
+        // Only componentDidCatch is defined, render nothing until it sets the state
+        JsValue::null()
+    } else {
+        let render = derive_from_js_value(&instance, "render");
+        render.dyn_ref::<Function>().unwrap().call0(&instance)?
+    };
+
+    if did_capture && current.is_some() {
+        force_unmount_current_and_reconcile(state, work_in_progress.clone(), next_children);
+    } else {
+        reconcile_children(state, work_in_progress.clone(), Some(next_children));
+    }
+    Ok(work_in_progress.clone().borrow().child.clone())
+}
+
+// The children which throw the error shouldn't be reused, so delete all of them first
+fn force_unmount_current_and_reconcile(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+    next_children: JsValue,
+) {
+    let current = { work_in_progress.borrow().alternate.clone().unwrap() };
+    let current_child = { current.borrow().child.clone() };
+    work_in_progress.borrow_mut().child = reconcile_child_fibers(
+        state,
+        work_in_progress.clone(),
+        current_child,
+        Some(JsValue::null()),
+    );
+    work_in_progress.borrow_mut().child =
+        reconcile_child_fibers(state, work_in_progress.clone(), None, Some(next_children));
+}
+
 fn update_host_root(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
     render_lane: Lane,
 ) -> Option<Rc<RefCell<FiberNode>>> {
@@ -450,7 +690,8 @@ fn update_host_root(
             .shared
             .pending = None;
         let ReturnOfProcessUpdateQueue { memoized_state, .. } =
-            process_update_queue(base_state, pending, render_lane.clone(), None);
+            process_update_queue(base_state, pending, render_lane.clone(), None, None)
+                .expect("TODO: panic process the update queue of HostRoot");
         work_in_progress.clone().borrow_mut().memoized_state = memoized_state.clone();
         let current = { work_in_progress.borrow().alternate.clone() };
         if current.is_some() {
@@ -461,6 +702,14 @@ fn update_host_root(
         }
     }
 
+    if work_in_progress.borrow().flags.contains(Flags::DidCapture) {
+        // An error is thrown and there is no error boundary, unmount the whole tree
+        work_in_progress.borrow_mut().memoized_state =
+            Some(MemoizedState::MemoizedJsValue(JsValue::null()));
+        force_unmount_current_and_reconcile(state, work_in_progress.clone(), JsValue::null());
+        return work_in_progress.clone().borrow().child.clone();
+    }
+
     let next_children = work_in_progress_cloned.borrow().memoized_state.clone();
     if next_children.is_none() {
         panic!("update_host_root next_children is none")
@@ -479,7 +728,7 @@ fn update_host_root(
     }
 
     if let MemoizedState::MemoizedJsValue(next_children) = next_children.unwrap() {
-        reconcile_children(work_in_progress.clone(), Some(next_children));
+        reconcile_children(state, work_in_progress.clone(), Some(next_children));
     }
     work_in_progress.clone().borrow().child.clone()
 }
@@ -494,30 +743,67 @@ fn mark_ref(current: Option<Rc<RefCell<FiberNode>>>, work_in_progress: Rc<RefCel
 }
 
 fn update_host_component(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
 ) -> Option<Rc<RefCell<FiberNode>>> {
     let work_in_progress = Rc::clone(&work_in_progress);
 
-    let next_children = {
+    let mut next_children = {
         let ref_fiber_node = work_in_progress.borrow();
         derive_from_js_value(&ref_fiber_node.pending_props, "children")
     };
 
     let alternate = { work_in_progress.borrow().alternate.clone() };
+    let _type = { work_in_progress.borrow()._type.as_string().unwrap() };
+    let next_props = { work_in_progress.borrow().pending_props.clone() };
+    let host_config = &state.host_config;
+    if host_config.should_set_text_content(_type.as_str(), &next_props) {
+        // The text children are handled by the host config, no need to create fibers for them
+        next_children = JsValue::null();
+    } else if let Some(current) = alternate.as_ref() {
+        let prev_props = current.borrow().memoized_props.clone();
+        if host_config.should_set_text_content(_type.as_str(), &prev_props) {
+            // Clear the previous text content before the new children are inserted
+            work_in_progress.borrow_mut().flags |= Flags::ContentReset;
+        }
+    }
     mark_ref(alternate, work_in_progress.clone());
 
     {
-        reconcile_children(work_in_progress.clone(), Some(next_children));
+        reconcile_children(state, work_in_progress.clone(), Some(next_children));
     }
     work_in_progress.clone().borrow().child.clone()
 }
 
-fn reconcile_children(work_in_progress: Rc<RefCell<FiberNode>>, children: Option<JsValue>) {
+fn update_portal_component(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+) -> Option<Rc<RefCell<FiberNode>>> {
+    push_host_container(state, work_in_progress.clone());
+    let next_children = { work_in_progress.borrow().pending_props.clone() };
+    let current = { work_in_progress.borrow().alternate.clone() };
+    if current.is_none() {
+        // The portal is not inserted with its parent like the other fibers, so its
+        // children have to be placed into the portal container by themselves
+        work_in_progress.borrow_mut().child =
+            reconcile_child_fibers(state, work_in_progress.clone(), None, Some(next_children));
+    } else {
+        reconcile_children(state, work_in_progress.clone(), Some(next_children));
+    }
+    work_in_progress.borrow().child.clone()
+}
+
+fn reconcile_children(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+    children: Option<JsValue>,
+) {
     let work_in_progress = Rc::clone(&work_in_progress);
     let current = { work_in_progress.borrow().alternate.clone() };
     if current.is_some() {
         // update
         work_in_progress.borrow_mut().child = reconcile_child_fibers(
+            state,
             work_in_progress.clone(),
             current.clone().unwrap().clone().borrow().child.clone(),
             children,
@@ -525,6 +811,6 @@ fn reconcile_children(work_in_progress: Rc<RefCell<FiberNode>>, children: Option
     } else {
         // mount
         work_in_progress.borrow_mut().child =
-            mount_child_fibers(work_in_progress.clone(), None, children)
+            mount_child_fibers(state, work_in_progress.clone(), None, children)
     }
 }
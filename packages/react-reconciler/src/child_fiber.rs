@@ -5,10 +5,14 @@ use std::rc::Rc;
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::js_sys::{Array, Object, Reflect};
 
-use shared::{derive_from_js_value, log, type_of, REACT_ELEMENT_TYPE, REACT_FRAGMENT_TYPE};
+use shared::{
+    derive_from_js_value, log, type_of, REACT_ELEMENT_TYPE, REACT_FRAGMENT_TYPE, REACT_PORTAL_TYPE,
+};
 
-use crate::fiber::FiberNode;
+use crate::fiber::{FiberNode, StateNode};
 use crate::fiber_flags::Flags;
+use crate::fiber_tree_context::{is_tree_id_enabled, push_tree_fork};
+use crate::root_state::RootState;
 use crate::work_tags::WorkTag;
 use crate::work_tags::WorkTag::HostText;
 use crate::JsValueKey;
@@ -146,6 +150,67 @@ fn reconcile_single_element(
     Rc::new(RefCell::new(fiber))
 }
 
+fn is_same_portal_container(fiber: Rc<RefCell<FiberNode>>, portal: &JsValue) -> bool {
+    if fiber.borrow().tag != WorkTag::HostPortal {
+        return false;
+    }
+    let state_node = { fiber.borrow().state_node.clone() };
+    match state_node.as_deref() {
+        Some(StateNode::HostPortal { container_info, .. }) => Object::is(
+            container_info,
+            &derive_from_js_value(portal, "containerInfo"),
+        ),
+        _ => false,
+    }
+}
+
+fn portal_children(portal: &JsValue) -> JsValue {
+    let children = derive_from_js_value(portal, "children");
+    if children.is_null() || children.is_undefined() {
+        return Array::new().into();
+    }
+    children
+}
+
+fn reconcile_single_portal(
+    state: &RootState,
+    return_fiber: Rc<RefCell<FiberNode>>,
+    current_first_child: Option<Rc<RefCell<FiberNode>>>,
+    portal: &JsValue,
+    should_track_effects: bool,
+) -> Rc<RefCell<FiberNode>> {
+    let key = derive_from_js_value(portal, "key");
+    let mut current = current_first_child;
+    while current.is_some() {
+        let current_rc = current.clone().unwrap();
+        if Object::is(&current_rc.borrow().key, &key) {
+            if is_same_portal_container(current_rc.clone(), portal) {
+                let existing = use_fiber(current_rc.clone(), portal_children(portal));
+                existing.borrow_mut()._return = Some(return_fiber.clone());
+                delete_remaining_children(
+                    return_fiber.clone(),
+                    current_rc.borrow().sibling.clone(),
+                    should_track_effects,
+                );
+                return existing;
+            }
+            delete_remaining_children(return_fiber.clone(), current.clone(), should_track_effects);
+            break;
+        } else {
+            delete_child(
+                return_fiber.clone(),
+                current_rc.clone(),
+                should_track_effects,
+            );
+            current = current_rc.borrow().sibling.clone();
+        }
+    }
+
+    let mut created = FiberNode::create_fiber_from_portal(portal, state.host_config.as_ref());
+    created._return = Some(return_fiber.clone());
+    Rc::new(RefCell::new(created))
+}
+
 fn create_props_with_content(content: JsValue) -> JsValue {
     let props = Object::new();
     Reflect::set(&props, &JsValue::from("content"), &content).expect("props panic");
@@ -211,6 +276,7 @@ fn update_fragment(
 }
 
 fn update_from_map(
+    state: &RootState,
     return_fiber: Rc<RefCell<FiberNode>>,
     existing_children: &mut HashMap<JsValueKey, Rc<RefCell<FiberNode>>>,
     index: u32,
@@ -270,6 +336,20 @@ fn update_from_map(
             existing_children,
         ));
     } else if type_of(element, "object") && !element.is_null() {
+        if derive_from_js_value(element, "$$typeof") == REACT_PORTAL_TYPE {
+            if before.is_some() {
+                let before = (*before.clone().unwrap()).clone();
+                existing_children.remove(&JsValueKey(key_to_use.clone()));
+                if is_same_portal_container(before.clone(), element) {
+                    return Some(use_fiber(before.clone(), portal_children(element)));
+                }
+                delete_child(return_fiber, before, should_track_effects);
+            }
+            return Some(Rc::new(RefCell::new(FiberNode::create_fiber_from_portal(
+                element,
+                state.host_config.as_ref(),
+            ))));
+        }
         if derive_from_js_value(&(*element).clone(), "$$typeof") == REACT_ELEMENT_TYPE {
             if derive_from_js_value(&(*element).clone(), "type") == REACT_FRAGMENT_TYPE {
                 let before = match before {
@@ -313,6 +393,7 @@ fn update_from_map(
 }
 
 fn reconcile_children_array(
+    state: &RootState,
     return_fiber: Rc<RefCell<FiberNode>>,
     current_first_child: Option<Rc<RefCell<FiberNode>>>,
     new_child: &Array,
@@ -342,6 +423,7 @@ fn reconcile_children_array(
         let after = new_child.get(i);
         // log!("after {:?}", after);
         let new_fiber = update_from_map(
+            state,
             return_fiber.clone(),
             &mut existing_children,
             i,
@@ -358,6 +440,9 @@ fn reconcile_children_array(
         {
             new_fiber.borrow_mut().index = i;
             new_fiber.borrow_mut()._return = Some(return_fiber.clone());
+            if is_tree_id_enabled(state) {
+                new_fiber.borrow_mut().flags |= Flags::Forked;
+            }
         }
 
         if last_new_fiber.is_none() {
@@ -389,6 +474,10 @@ fn reconcile_children_array(
     for (_, fiber) in existing_children {
         delete_child(return_fiber.clone(), fiber, should_track_effects);
     }
+
+    if is_tree_id_enabled(state) {
+        push_tree_fork(state, return_fiber.clone(), length);
+    }
     // log!(
     //     "first_new_fiber {:?} {:?}",
     //     first_new_fiber,
@@ -398,6 +487,7 @@ fn reconcile_children_array(
 }
 
 fn _reconcile_child_fibers(
+    state: &RootState,
     return_fiber: Rc<RefCell<FiberNode>>,
     current_first_child: Option<Rc<RefCell<FiberNode>>>,
     new_child: Option<JsValue>,
@@ -418,6 +508,7 @@ fn _reconcile_child_fibers(
             ));
         } else if new_child.is_array() {
             return reconcile_children_array(
+                state,
                 return_fiber,
                 current_first_child,
                 new_child.dyn_ref::<Array>().unwrap(),
@@ -435,6 +526,17 @@ fn _reconcile_child_fibers(
                         ),
                         should_track_effects,
                     ));
+                } else if _typeof == REACT_PORTAL_TYPE {
+                    return Some(place_single_child(
+                        reconcile_single_portal(
+                            state,
+                            return_fiber,
+                            current_first_child,
+                            new_child,
+                            should_track_effects,
+                        ),
+                        should_track_effects,
+                    ));
                 }
             }
         }
@@ -473,17 +575,19 @@ pub fn clone_child_fiblers(wip: Rc<RefCell<FiberNode>>) {
 }
 
 pub fn reconcile_child_fibers(
+    state: &RootState,
     return_fiber: Rc<RefCell<FiberNode>>,
     current_first_child: Option<Rc<RefCell<FiberNode>>>,
     new_child: Option<JsValue>,
 ) -> Option<Rc<RefCell<FiberNode>>> {
-    _reconcile_child_fibers(return_fiber, current_first_child, new_child, true)
+    _reconcile_child_fibers(state, return_fiber, current_first_child, new_child, true)
 }
 
 pub fn mount_child_fibers(
+    state: &RootState,
     return_fiber: Rc<RefCell<FiberNode>>,
     current_first_child: Option<Rc<RefCell<FiberNode>>>,
     new_child: Option<JsValue>,
 ) -> Option<Rc<RefCell<FiberNode>>> {
-    _reconcile_child_fibers(return_fiber, current_first_child, new_child, false)
+    _reconcile_child_fibers(state, return_fiber, current_first_child, new_child, false)
 }
@@ -3,32 +3,45 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use wasm_bindgen::{JsCast, JsValue};
-use web_sys::js_sys::{Function, Reflect};
+use web_sys::js_sys::{Array, Function, Reflect};
 
 use shared::{is_dev, log, type_of};
-use web_sys::Node;
 
-use crate::fiber::{FiberNode, FiberRootNode, StateNode};
+use crate::fiber::{FiberNode, FiberRootNode, MemoizedState, StateNode};
+use crate::fiber_class_component::{
+    call_instance_method, commit_class_callbacks, get_class_instance,
+};
 use crate::fiber_flags::{get_mutation_mask, get_passive_mask, Flags};
-use crate::fiber_hooks::Effect;
+use crate::fiber_hooks::{set_is_running_insertion_effect, Effect};
+use crate::root_state::RootState;
+use crate::work_loop::capture_commit_phase_error;
 use crate::work_tags::WorkTag;
-use crate::work_tags::WorkTag::{FunctionComponent, HostComponent, HostRoot, HostText};
-use crate::HOST_CONFIG;
+use crate::work_tags::WorkTag::{
+    ClassComponent, ForwardRef, FunctionComponent, HostComponent, HostPortal, HostRoot, HostText,
+    MemoComponent,
+};
 
-static mut NEXT_EFFECT: Option<Rc<RefCell<FiberNode>>> = None;
+// The key of the getSnapshotBeforeUpdate result on the instance
+static SNAPSHOT_BEFORE_UPDATE_KEY: &str = "__reactInternalSnapshotBeforeUpdate";
 
 enum Phrase {
+    BeforeMutation,
     Mutation,
     Layout,
 }
 
+// The fibers which render with hooks
+fn is_function_component_like(tag: &WorkTag) -> bool {
+    *tag == FunctionComponent || *tag == ForwardRef || *tag == MemoComponent
+}
+
 fn commit_passive_effect(
     finished_work: Rc<RefCell<FiberNode>>,
     root: Rc<RefCell<FiberRootNode>>,
     _type: &str,
 ) {
     let finished_work_b = finished_work.borrow();
-    if finished_work_b.tag != WorkTag::FunctionComponent
+    if !is_function_component_like(&finished_work_b.tag)
         || (_type == "update"
             && (finished_work_b.flags.clone() & Flags::PassiveEffect == Flags::NoFlags))
     {
@@ -46,27 +59,41 @@ fn commit_passive_effect(
                 .pending_passive_effects
                 .borrow_mut()
                 .unmount
-                .push(update_queue.borrow().last_effect.clone().unwrap());
+                .push((
+                    finished_work.clone(),
+                    update_queue.borrow().last_effect.clone().unwrap(),
+                ));
         } else {
             root.borrow()
                 .pending_passive_effects
                 .borrow_mut()
                 .update
-                .push(update_queue.borrow().last_effect.clone().unwrap());
+                .push((
+                    finished_work.clone(),
+                    update_queue.borrow().last_effect.clone().unwrap(),
+                ));
         }
     }
 }
 
+fn get_last_effect(fiber: Rc<RefCell<FiberNode>>) -> Option<Rc<RefCell<Effect>>> {
+    let update_queue = { fiber.borrow().update_queue.clone() };
+    update_queue.and_then(|update_queue| update_queue.borrow().last_effect.clone())
+}
+
 pub fn commit_hook_effect_list(
     flags: Flags,
+    finished_work: Rc<RefCell<FiberNode>>,
     last_effect: Rc<RefCell<Effect>>,
-    callback: fn(effect: Rc<RefCell<Effect>>),
+    callback: fn(effect: Rc<RefCell<Effect>>) -> Result<(), JsValue>,
 ) {
     let mut effect = last_effect.borrow().next.clone();
     loop {
         let mut effect_rc = effect.clone().unwrap();
         if effect_rc.borrow().tag.clone() & flags.clone() == flags.clone() {
-            callback(effect_rc.clone())
+            if let Err(e) = callback(effect_rc.clone()) {
+                capture_commit_phase_error(finished_work.clone(), e);
+            }
         }
         effect = effect_rc.borrow().next.clone();
         if Rc::ptr_eq(
@@ -77,87 +104,109 @@ pub fn commit_hook_effect_list(
         }
     }
 }
-pub fn commit_hook_effect_list_destroy(flags: Flags, last_effect: Rc<RefCell<Effect>>) {
-    commit_hook_effect_list(flags, last_effect, |effect: Rc<RefCell<Effect>>| {
-        let destroy = { effect.borrow().destroy.clone() };
-        if destroy.is_function() {
-            destroy
-                .dyn_ref::<Function>()
-                .unwrap()
-                .call0(&JsValue::null());
-        }
-        effect.borrow_mut().tag &= !Flags::HookHasEffect;
-    });
+pub fn commit_hook_effect_list_destroy(
+    flags: Flags,
+    finished_work: Rc<RefCell<FiberNode>>,
+    last_effect: Rc<RefCell<Effect>>,
+) {
+    commit_hook_effect_list(
+        flags,
+        finished_work,
+        last_effect,
+        |effect: Rc<RefCell<Effect>>| {
+            let destroy = { effect.borrow().destroy.clone() };
+            effect.borrow_mut().tag &= !Flags::HookHasEffect;
+            if destroy.is_function() {
+                destroy
+                    .dyn_ref::<Function>()
+                    .unwrap()
+                    .call0(&JsValue::null())?;
+            }
+            Ok(())
+        },
+    );
 }
 
-pub fn commit_hook_effect_list_unmount(flags: Flags, last_effect: Rc<RefCell<Effect>>) {
-    commit_hook_effect_list(flags, last_effect, |effect: Rc<RefCell<Effect>>| {
-        let destroy = &effect.borrow().destroy;
-        if destroy.is_function() {
-            destroy
-                .dyn_ref::<Function>()
-                .unwrap()
-                .call0(&JsValue::null());
-        }
-    });
+pub fn commit_hook_effect_list_unmount(
+    flags: Flags,
+    finished_work: Rc<RefCell<FiberNode>>,
+    last_effect: Rc<RefCell<Effect>>,
+) {
+    commit_hook_effect_list(
+        flags,
+        finished_work,
+        last_effect,
+        |effect: Rc<RefCell<Effect>>| {
+            let destroy = { effect.borrow().destroy.clone() };
+            if destroy.is_function() {
+                destroy
+                    .dyn_ref::<Function>()
+                    .unwrap()
+                    .call0(&JsValue::null())?;
+            }
+            Ok(())
+        },
+    );
 }
 
-pub fn commit_hook_effect_list_mount(flags: Flags, last_effect: Rc<RefCell<Effect>>) {
-    commit_hook_effect_list(flags, last_effect, |effect: Rc<RefCell<Effect>>| {
-        let create = { effect.borrow().create.clone() };
-        if create.is_function() {
-            let destroy = create.call0(&JsValue::null()).unwrap();
-            effect.borrow_mut().destroy = destroy;
-        }
-    });
+pub fn commit_hook_effect_list_mount(
+    flags: Flags,
+    finished_work: Rc<RefCell<FiberNode>>,
+    last_effect: Rc<RefCell<Effect>>,
+) {
+    commit_hook_effect_list(
+        flags,
+        finished_work,
+        last_effect,
+        |effect: Rc<RefCell<Effect>>| {
+            let create = { effect.borrow().create.clone() };
+            if create.is_function() {
+                let destroy = create.call0(&JsValue::null())?;
+                effect.borrow_mut().destroy = destroy;
+            }
+            Ok(())
+        },
+    );
 }
 
 pub fn commit_effects(
     phrase: Phrase,
     mask: Flags,
-    callbak: fn(Rc<RefCell<FiberNode>>, Rc<RefCell<FiberRootNode>>) -> (),
-) -> Box<dyn Fn(Rc<RefCell<FiberNode>>, Rc<RefCell<FiberRootNode>>) -> ()> {
+    callbak: fn(&RootState, Rc<RefCell<FiberNode>>, Rc<RefCell<FiberRootNode>>) -> (),
+) -> Box<dyn Fn(&RootState, Rc<RefCell<FiberNode>>, Rc<RefCell<FiberRootNode>>) -> ()> {
     Box::new(
-        move |finished_work: Rc<RefCell<FiberNode>>, root: Rc<RefCell<FiberRootNode>>| -> () {
-            unsafe {
-                NEXT_EFFECT = Some(finished_work);
-                while NEXT_EFFECT.is_some() {
-                    let next_effect = NEXT_EFFECT.clone().unwrap().clone();
-                    let child = next_effect.borrow().child.clone();
-                    if child.is_some()
-                        && next_effect.borrow().subtree_flags.clone() & mask.clone()
-                            != Flags::NoFlags
-                    {
-                        NEXT_EFFECT = child;
-                    } else {
-                        while NEXT_EFFECT.is_some() {
-                            callbak(NEXT_EFFECT.clone().unwrap(), root.clone());
-                            let sibling = NEXT_EFFECT
-                                .clone()
-                                .clone()
-                                .unwrap()
-                                .borrow()
-                                .sibling
-                                .clone();
-                            if sibling.is_some() {
-                                NEXT_EFFECT = sibling;
-                                break;
-                            }
-
-                            let _return = NEXT_EFFECT
-                                .clone()
-                                .unwrap()
-                                .clone()
-                                .borrow()
-                                ._return
-                                .clone();
-
-                            if _return.is_none() {
-                                NEXT_EFFECT = None
-                            } else {
-                                NEXT_EFFECT = _return;
-                            }
+        move |state: &RootState,
+              finished_work: Rc<RefCell<FiberNode>>,
+              root: Rc<RefCell<FiberRootNode>>|
+              -> () {
+            *state.next_effect.borrow_mut() = Some(finished_work);
+            loop {
+                let next_effect = { state.next_effect.borrow().clone() };
+                let next_effect = match next_effect {
+                    Some(next_effect) => next_effect,
+                    None => break,
+                };
+                let child = next_effect.borrow().child.clone();
+                if child.is_some()
+                    && next_effect.borrow().subtree_flags.clone() & mask.clone() != Flags::NoFlags
+                {
+                    *state.next_effect.borrow_mut() = child;
+                } else {
+                    loop {
+                        let fiber = { state.next_effect.borrow().clone() };
+                        let fiber = match fiber {
+                            Some(fiber) => fiber,
+                            None => break,
+                        };
+                        callbak(state, fiber.clone(), root.clone());
+                        let sibling = { fiber.borrow().sibling.clone() };
+                        if sibling.is_some() {
+                            *state.next_effect.borrow_mut() = sibling;
+                            break;
                         }
+
+                        let _return = { fiber.borrow()._return.clone() };
+                        *state.next_effect.borrow_mut() = _return;
                     }
                 }
             }
@@ -165,7 +214,20 @@ pub fn commit_effects(
     )
 }
 
+pub fn commit_before_mutation_effects(
+    state: &RootState,
+    finished_work: Rc<RefCell<FiberNode>>,
+    root: Rc<RefCell<FiberRootNode>>,
+) {
+    commit_effects(
+        Phrase::BeforeMutation,
+        Flags::BeforeMutationMask,
+        commit_before_mutation_effects_on_fiber,
+    )(state, finished_work, root)
+}
+
 pub fn commit_layout_effects(
+    state: &RootState,
     finished_work: Rc<RefCell<FiberNode>>,
     root: Rc<RefCell<FiberRootNode>>,
 ) {
@@ -173,10 +235,11 @@ pub fn commit_layout_effects(
         Phrase::Layout,
         Flags::LayoutMask,
         commit_layout_effects_on_fiber,
-    )(finished_work, root)
+    )(state, finished_work, root)
 }
 
 pub fn commit_mutation_effects(
+    state: &RootState,
     finished_work: Rc<RefCell<FiberNode>>,
     root: Rc<RefCell<FiberRootNode>>,
 ) {
@@ -184,37 +247,127 @@ pub fn commit_mutation_effects(
         Phrase::Mutation,
         get_mutation_mask() | get_passive_mask(),
         commit_mutation_effects_on_fiber,
-    )(finished_work, root)
+    )(state, finished_work, root)
+}
+
+fn get_prev_props_and_state(current: Rc<RefCell<FiberNode>>) -> (JsValue, JsValue) {
+    let current = current.borrow();
+    let prev_state = match current.memoized_state.as_ref() {
+        Some(MemoizedState::MemoizedJsValue(state)) => state.clone(),
+        _ => JsValue::null(),
+    };
+    (current.memoized_props.clone(), prev_state)
+}
+
+fn commit_before_mutation_effects_on_fiber(
+    _state: &RootState,
+    finished_work: Rc<RefCell<FiberNode>>,
+    root: Rc<RefCell<FiberRootNode>>,
+) {
+    let flags = finished_work.borrow().flags.clone();
+    let tag = finished_work.borrow().tag.clone();
+    if flags.contains(Flags::Snapshot) && tag == ClassComponent {
+        let current = { finished_work.borrow().alternate.clone() };
+        if let Some(current) = current {
+            let instance = get_class_instance(finished_work.clone()).unwrap();
+            let (prev_props, prev_state) = get_prev_props_and_state(current);
+            match call_instance_method(
+                &instance,
+                "getSnapshotBeforeUpdate",
+                &Array::of2(&prev_props, &prev_state),
+            ) {
+                Ok(snapshot) => {
+                    Reflect::set(&instance, &SNAPSHOT_BEFORE_UPDATE_KEY.into(), &snapshot)
+                        .expect("TODO: panic set snapshot");
+                }
+                Err(e) => capture_commit_phase_error(finished_work.clone(), e),
+            }
+        }
+        finished_work.borrow_mut().flags -= Flags::Snapshot;
+    }
+}
+
+fn commit_class_layout_lifecycles(finished_work: Rc<RefCell<FiberNode>>) {
+    let instance = get_class_instance(finished_work.clone()).unwrap();
+    let current = { finished_work.borrow().alternate.clone() };
+    let result = match current {
+        None => call_instance_method(&instance, "componentDidMount", &Array::new()),
+        Some(current) => {
+            let (prev_props, prev_state) = get_prev_props_and_state(current);
+            let snapshot = Reflect::get(&instance, &SNAPSHOT_BEFORE_UPDATE_KEY.into())
+                .unwrap_or(JsValue::undefined());
+            call_instance_method(
+                &instance,
+                "componentDidUpdate",
+                &Array::of3(&prev_props, &prev_state, &snapshot),
+            )
+        }
+    };
+    if let Err(e) = result {
+        capture_commit_phase_error(finished_work.clone(), e);
+    }
 }
 
 fn commit_layout_effects_on_fiber(
+    state: &RootState,
     finished_work: Rc<RefCell<FiberNode>>,
     root: Rc<RefCell<FiberRootNode>>,
 ) {
     let flags = finished_work.borrow().flags.clone();
     let tag = finished_work.borrow().tag.clone();
-    if flags & Flags::Ref != Flags::NoFlags && tag == HostComponent {
-        safely_attach_ref(finished_work.clone());
+    if is_function_component_like(&tag) && flags.contains(Flags::Update) {
+        if let Some(last_effect) = get_last_effect(finished_work.clone()) {
+            commit_hook_effect_list_mount(
+                Flags::Layout | Flags::HookHasEffect,
+                finished_work.clone(),
+                last_effect,
+            );
+        }
+        finished_work.borrow_mut().flags -= Flags::Update;
+    }
+    if tag == ClassComponent {
+        if flags.contains(Flags::Update) {
+            commit_class_layout_lifecycles(finished_work.clone());
+            finished_work.borrow_mut().flags -= Flags::Update;
+        }
+        if flags.contains(Flags::Callback) {
+            let instance = get_class_instance(finished_work.clone()).unwrap();
+            commit_class_callbacks(finished_work.clone(), &instance);
+            finished_work.borrow_mut().flags -= Flags::Callback;
+        }
+    }
+    if flags & Flags::Ref != Flags::NoFlags && (tag == HostComponent || tag == ClassComponent) {
+        safely_attach_ref(state, finished_work.clone());
         finished_work.borrow_mut().flags -= Flags::Ref;
     }
 }
 
 fn commit_mutation_effects_on_fiber(
+    state: &RootState,
     finished_work: Rc<RefCell<FiberNode>>,
     root: Rc<RefCell<FiberRootNode>>,
 ) {
     let flags = finished_work.borrow().flags.clone();
     if flags.contains(Flags::Placement) {
-        commit_placement(finished_work.clone());
+        commit_placement(state, finished_work.clone());
         finished_work.borrow_mut().flags -= Flags::Placement;
     }
 
+    // No children have been inserted, reset the text content here
+    if finished_work.borrow().flags.contains(Flags::ContentReset) {
+        let instance = FiberNode::derive_state_node(finished_work.clone());
+        if let Some(instance) = instance {
+            state.host_config.reset_text_content(instance)
+        }
+        finished_work.borrow_mut().flags -= Flags::ContentReset;
+    }
+
     if flags.contains(Flags::ChildDeletion) {
         {
             let deletions = &finished_work.borrow().deletions;
             if !deletions.is_empty() {
                 for child_to_delete in deletions {
-                    commit_deletion(child_to_delete.clone(), root.clone());
+                    commit_deletion(state, child_to_delete.clone(), root.clone());
                 }
             }
         }
@@ -227,104 +380,206 @@ fn commit_mutation_effects_on_fiber(
     //     finished_work,
     //     finished_work.borrow().alternate
     // );
-    if flags.contains(Flags::Update) {
+    let tag = finished_work.borrow().tag.clone();
+    // For class component, Update means componentDidMount/componentDidUpdate in the layout phase
+    if flags.contains(Flags::Update) && (tag == HostComponent || tag == HostText) {
         // commit_update(finished_work.clone());
-        unsafe {
-            HOST_CONFIG
-                .as_ref()
-                .unwrap()
-                .commit_update(finished_work.clone())
-        }
+        state.host_config.commit_update(finished_work.clone());
+        finished_work.borrow_mut().update_payload = None;
         finished_work.borrow_mut().flags -= Flags::Update;
     }
 
+    // useInsertionEffect is fired here, then the destroys of useLayoutEffect,
+    // the creates of useLayoutEffect will be called in the layout phase
+    if flags.contains(Flags::Update) && is_function_component_like(&tag) {
+        if let Some(last_effect) = get_last_effect(finished_work.clone()) {
+            set_is_running_insertion_effect(state, true);
+            commit_hook_effect_list_unmount(
+                Flags::Insertion | Flags::HookHasEffect,
+                finished_work.clone(),
+                last_effect.clone(),
+            );
+            commit_hook_effect_list_mount(
+                Flags::Insertion | Flags::HookHasEffect,
+                finished_work.clone(),
+                last_effect.clone(),
+            );
+            set_is_running_insertion_effect(state, false);
+            commit_hook_effect_list_unmount(
+                Flags::Layout | Flags::HookHasEffect,
+                finished_work.clone(),
+                last_effect,
+            );
+        }
+    }
+
     if flags.clone() & Flags::PassiveEffect != Flags::NoFlags {
         commit_passive_effect(finished_work.clone(), root, "update");
         finished_work.borrow_mut().flags -= Flags::PassiveEffect;
     }
 
-    if flags & Flags::Ref != Flags::NoFlags && finished_work.borrow().tag.clone() == HostComponent {
-        safely_detach_ref(finished_work);
+    if flags & Flags::Ref != Flags::NoFlags && (tag == HostComponent || tag == ClassComponent) {
+        // The previous ref is detached, the new one is attached in the layout phase
+        let current = { finished_work.borrow().alternate.clone() };
+        if let Some(current) = current {
+            safely_detach_ref(current);
+        }
     }
 }
 
 fn safely_detach_ref(current: Rc<RefCell<FiberNode>>) {
     let _ref = current.borrow()._ref.clone();
-    if !_ref.is_null() {
+    let ref_cleanup = { current.borrow_mut().ref_cleanup.take() };
+    if let Some(ref_cleanup) = ref_cleanup {
+        let alternate = { current.borrow().alternate.clone() };
+        if let Some(alternate) = alternate {
+            alternate.borrow_mut().ref_cleanup = None;
+        }
+        if let Err(e) = ref_cleanup.call0(&JsValue::null()) {
+            capture_commit_phase_error(current, e);
+        }
+    } else if !_ref.is_null() {
         if type_of(&_ref, "function") {
-            _ref.dyn_ref::<Function>()
+            if let Err(e) = _ref
+                .dyn_ref::<Function>()
                 .unwrap()
-                .call1(&JsValue::null(), &JsValue::null());
+                .call1(&JsValue::null(), &JsValue::null())
+            {
+                capture_commit_phase_error(current, e);
+            }
         } else {
             Reflect::set(&_ref, &"current".into(), &JsValue::null());
         }
     }
 }
 
-fn safely_attach_ref(fiber: Rc<RefCell<FiberNode>>) {
+fn safely_attach_ref(state: &RootState, fiber: Rc<RefCell<FiberNode>>) {
     let _ref = fiber.borrow()._ref.clone();
     if !_ref.is_null() {
-        let instance = match fiber.borrow().state_node.clone() {
-            Some(s) => match &*s {
-                StateNode::Element(element) => {
-                    let node = (*element).downcast_ref::<Node>().unwrap();
-                    Some(node.clone())
-                }
-                StateNode::FiberRootNode(_) => None,
-            },
-            None => None,
+        let tag = { fiber.borrow().tag.clone() };
+        let instance = if tag == ClassComponent {
+            get_class_instance(fiber.clone())
+        } else {
+            match fiber.borrow().state_node.clone() {
+                Some(s) => match &*s {
+                    StateNode::Element(element) => {
+                        Some(state.host_config.get_public_instance(element.clone()))
+                    }
+                    _ => None,
+                },
+                None => None,
+            }
         };
 
         if instance.is_none() {
-            panic!("instance is none")
+            if is_dev() {
+                log!("The instance of the ref is none {:?}", fiber);
+            }
+            return;
         }
 
         let instance = instance.as_ref().unwrap();
         if type_of(&_ref, "function") {
-            _ref.dyn_ref::<Function>()
+            match _ref
+                .dyn_ref::<Function>()
                 .unwrap()
-                .call1(&JsValue::null(), instance);
+                .call1(&JsValue::null(), instance)
+            {
+                Ok(ref_cleanup) => {
+                    fiber.borrow_mut().ref_cleanup = ref_cleanup.dyn_into::<Function>().ok();
+                }
+                Err(e) => capture_commit_phase_error(fiber.clone(), e),
+            }
         } else {
             Reflect::set(&_ref, &"current".into(), instance);
         }
     }
 }
 
+// Only the top level host nodes of the deleted subtree are removed, the ones inside a portal
+// are removed from the container of the portal, which is recorded along with the node
 fn record_host_children_to_delete(
-    children_to_delete: &mut Vec<Rc<RefCell<FiberNode>>>,
+    children_to_delete: &mut Vec<(Option<Rc<RefCell<FiberNode>>>, Rc<RefCell<FiberNode>>)>,
     unmount_fiber: Rc<RefCell<FiberNode>>,
+    child_to_delete: Rc<RefCell<FiberNode>>,
 ) {
-    let len = children_to_delete.len();
-    if len == 0 {
-        children_to_delete.push(unmount_fiber.clone());
-    } else {
-        let last_one = &children_to_delete[len - 1];
-        let mut node_option = last_one.borrow().sibling.clone();
-        while node_option.is_some() {
-            let node = node_option.unwrap();
-            if Rc::ptr_eq(&unmount_fiber, &node) {
-                children_to_delete.push(unmount_fiber.clone());
-            }
-            node_option = node.borrow().sibling.clone();
+    if Rc::ptr_eq(&unmount_fiber, &child_to_delete) {
+        children_to_delete.push((None, unmount_fiber));
+        return;
+    }
+    let mut parent = { unmount_fiber.borrow()._return.clone() };
+    while let Some(p) = parent {
+        let tag = { p.borrow().tag.clone() };
+        if tag == HostComponent {
+            return;
+        }
+        if tag == HostPortal {
+            children_to_delete.push((Some(p), unmount_fiber));
+            return;
+        }
+        if Rc::ptr_eq(&p, &child_to_delete) {
+            break;
         }
+        parent = p.borrow()._return.clone();
     }
+    children_to_delete.push((None, unmount_fiber));
 }
 
-fn commit_deletion(child_to_delete: Rc<RefCell<FiberNode>>, root: Rc<RefCell<FiberRootNode>>) {
-    let mut root_children_to_delete: Vec<Rc<RefCell<FiberNode>>> = vec![];
+fn commit_deletion(
+    state: &RootState,
+    child_to_delete: Rc<RefCell<FiberNode>>,
+    root: Rc<RefCell<FiberRootNode>>,
+) {
+    let mut root_children_to_delete: Vec<(Option<Rc<RefCell<FiberNode>>>, Rc<RefCell<FiberNode>>)> =
+        vec![];
 
     commit_nested_unmounts(child_to_delete.clone(), |unmount_fiber| {
         match unmount_fiber.borrow().tag {
-            FunctionComponent => {
+            FunctionComponent | ForwardRef | MemoComponent => {
+                if let Some(last_effect) = get_last_effect(unmount_fiber.clone()) {
+                    set_is_running_insertion_effect(state, true);
+                    commit_hook_effect_list_unmount(
+                        Flags::Insertion,
+                        child_to_delete.clone(),
+                        last_effect.clone(),
+                    );
+                    set_is_running_insertion_effect(state, false);
+                    commit_hook_effect_list_unmount(
+                        Flags::Layout,
+                        child_to_delete.clone(),
+                        last_effect,
+                    );
+                }
                 commit_passive_effect(unmount_fiber.clone(), root.clone(), "unmount");
             }
+            ClassComponent => {
+                safely_detach_ref(unmount_fiber.clone());
+                let instance = get_class_instance(unmount_fiber.clone());
+                if let Some(instance) = instance {
+                    if let Err(e) =
+                        call_instance_method(&instance, "componentWillUnmount", &Array::new())
+                    {
+                        // The boundary should be the mounted ancestor of the deleted subtree
+                        capture_commit_phase_error(child_to_delete.clone(), e);
+                    }
+                }
+            }
             HostComponent => {
-                record_host_children_to_delete(&mut root_children_to_delete, unmount_fiber.clone());
+                record_host_children_to_delete(
+                    &mut root_children_to_delete,
+                    unmount_fiber.clone(),
+                    child_to_delete.clone(),
+                );
                 safely_detach_ref(unmount_fiber.clone());
             }
             HostText => {
-                record_host_children_to_delete(&mut root_children_to_delete, unmount_fiber.clone());
+                record_host_children_to_delete(
+                    &mut root_children_to_delete,
+                    unmount_fiber.clone(),
+                    child_to_delete.clone(),
+                );
             }
+            HostPortal => {}
             _ => {
                 if is_dev() {
                     log!("unsupported unmount type {:?}", unmount_fiber);
@@ -335,17 +590,17 @@ fn commit_deletion(child_to_delete: Rc<RefCell<FiberNode>>, root: Rc<RefCell<Fib
 
     if !root_children_to_delete.is_empty() {
         let host_parent = get_host_parent(child_to_delete.clone());
-        if host_parent.is_some() {
-            let host_parent = host_parent.unwrap();
-            for child in root_children_to_delete {
+        for (portal, child) in root_children_to_delete {
+            let parent = match portal {
+                Some(portal) => Some(portal),
+                None => host_parent.clone(),
+            };
+            if let Some(parent) = parent {
                 let node = FiberNode::derive_state_node(child.clone());
-                let host_parent_state_node = FiberNode::derive_state_node(host_parent.clone());
-                unsafe {
-                    HOST_CONFIG
-                        .as_ref()
-                        .unwrap()
-                        .remove_child(node.unwrap(), host_parent_state_node.unwrap())
-                }
+                let parent_state_node = FiberNode::derive_state_node(parent.clone());
+                state
+                    .host_config
+                    .remove_child(node.unwrap(), parent_state_node.unwrap())
             }
         }
     }
@@ -402,16 +657,25 @@ where
     }
 }
 
-fn commit_placement(finished_work: Rc<RefCell<FiberNode>>) {
+fn commit_placement(state: &RootState, finished_work: Rc<RefCell<FiberNode>>) {
     let host_parent = get_host_parent(finished_work.clone());
     if host_parent.is_none() {
         return;
     }
-    let parent_state_node = FiberNode::derive_state_node(host_parent.unwrap());
+    let host_parent = host_parent.unwrap();
+    let parent_state_node = FiberNode::derive_state_node(host_parent.clone());
+    if host_parent.borrow().flags.contains(Flags::ContentReset) {
+        // The parent's text content has to be reset before any children are inserted
+        if let Some(parent_state_node) = parent_state_node.clone() {
+            state.host_config.reset_text_content(parent_state_node)
+        }
+        host_parent.borrow_mut().flags -= Flags::ContentReset;
+    }
     let sibling = get_host_sibling(finished_work.clone());
 
     if parent_state_node.is_some() {
         insert_or_append_placement_node_into_container(
+            state,
             finished_work.clone(),
             parent_state_node.unwrap(),
             sibling,
@@ -423,10 +687,13 @@ fn get_element_from_state_node(state_node: Rc<StateNode>) -> Rc<dyn Any> {
     match &*state_node {
         StateNode::FiberRootNode(root) => root.clone().borrow().container.clone(),
         StateNode::Element(ele) => ele.clone(),
+        StateNode::ClassInstance(instance) => Rc::new(instance.clone()),
+        StateNode::HostPortal { container, .. } => container.clone(),
     }
 }
 
 fn insert_or_append_placement_node_into_container(
+    state: &RootState,
     fiber: Rc<RefCell<FiberNode>>,
     parent: Rc<dyn Any>,
     before: Option<Rc<dyn Any>>,
@@ -438,28 +705,28 @@ fn insert_or_append_placement_node_into_container(
         let state_node = get_element_from_state_node(state_node);
 
         if before.is_some() {
-            unsafe {
-                HOST_CONFIG.as_ref().unwrap().insert_child_to_container(
-                    state_node,
-                    parent,
-                    before.clone().unwrap(),
-                )
-            };
+            state.host_config.insert_child_to_container(
+                state_node,
+                parent,
+                before.clone().unwrap(),
+            );
         } else {
-            unsafe {
-                HOST_CONFIG
-                    .as_ref()
-                    .unwrap()
-                    .append_child_to_container(state_node, parent.clone())
-            };
+            state
+                .host_config
+                .append_child_to_container(state_node, parent.clone());
         }
 
         return;
     }
+    if tag == WorkTag::HostPortal {
+        // The children of the portal are placed into the container of the portal
+        return;
+    }
 
     let child = fiber.borrow().child.clone();
     if child.is_some() {
         insert_or_append_placement_node_into_container(
+            state,
             child.clone().unwrap(),
             parent.clone(),
             before.clone(),
@@ -467,6 +734,7 @@ fn insert_or_append_placement_node_into_container(
         let mut sibling = child.unwrap().clone().borrow().sibling.clone();
         while sibling.is_some() {
             insert_or_append_placement_node_into_container(
+                state,
                 sibling.clone().unwrap(),
                 parent.clone(),
                 before.clone(),
@@ -481,7 +749,10 @@ fn get_host_parent(fiber: Rc<RefCell<FiberNode>>) -> Option<Rc<RefCell<FiberNode
     while parent.is_some() {
         let p = parent.clone().unwrap();
         let parent_tag = p.borrow().tag.clone();
-        if parent_tag == WorkTag::HostComponent || parent_tag == WorkTag::HostRoot {
+        if parent_tag == WorkTag::HostComponent
+            || parent_tag == WorkTag::HostRoot
+            || parent_tag == WorkTag::HostPortal
+        {
             return Some(p);
         }
         parent = p.borrow()._return.clone();
@@ -503,7 +774,7 @@ fn get_host_sibling(fiber: Rc<RefCell<FiberNode>>) -> Option<Rc<dyn Any>> {
             let node_rc = node.clone().unwrap();
             let parent = node_rc.borrow()._return.clone();
             let tag = parent.clone().unwrap().borrow().tag.clone();
-            if parent.is_none() || tag == HostComponent || tag == HostRoot {
+            if parent.is_none() || tag == HostComponent || tag == HostRoot || tag == HostPortal {
                 return None;
             }
             node = parent.clone();
@@ -527,7 +798,8 @@ fn get_host_sibling(fiber: Rc<RefCell<FiberNode>>) -> Option<Rc<dyn Any>> {
             if node_rc.borrow().flags.contains(Flags::Placement) {
                 continue 'find_sibling;
             }
-            if node_rc.borrow().child.is_none() {
+            // The host nodes of a portal are not under the same host parent
+            if node_rc.borrow().child.is_none() || node_rc.borrow().tag == HostPortal {
                 continue 'find_sibling;
             } else {
                 node_rc
@@ -10,237 +10,288 @@ use shared::derive_from_js_value;
 use crate::fiber::{FiberNode, StateNode};
 use crate::fiber_context::pop_provider;
 use crate::fiber_flags::Flags;
+use crate::fiber_host_context::{get_host_context, pop_host_container, pop_host_context};
 use crate::fiber_lanes::{merge_lanes, Lane};
-use crate::suspense_context::pop_suspense_handler;
+use crate::fiber_tree_context::pop_tree_context;
+use crate::root_state::RootState;
 use crate::work_tags::WorkTag;
-use crate::HostConfig;
-
-pub struct CompleteWork {
-    pub host_config: Rc<dyn HostConfig>,
-}
 
 fn mark_ref(fiber: Rc<RefCell<FiberNode>>) {
     fiber.borrow_mut().flags |= Flags::Ref;
 }
 
-impl CompleteWork {
-    pub(crate) fn new(host_config: Rc<dyn HostConfig>) -> Self {
-        Self { host_config }
-    }
-
-    fn append_all_children(&self, parent: Rc<dyn Any>, work_in_progress: Rc<RefCell<FiberNode>>) {
-        let work_in_progress = work_in_progress.clone();
-        let mut node = work_in_progress.borrow().child.clone();
-        while node.is_some() {
-            let node_unwrap = node.clone().unwrap();
+fn append_all_children(
+    state: &RootState,
+    parent: Rc<dyn Any>,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+) {
+    let work_in_progress = work_in_progress.clone();
+    let mut node = work_in_progress.borrow().child.clone();
+    while node.is_some() {
+        let node_unwrap = node.clone().unwrap();
+        let n = node_unwrap.clone();
+        if n.borrow().tag == WorkTag::HostComponent || n.borrow().tag == WorkTag::HostText {
+            state.host_config.append_initial_child(
+                parent.clone(),
+                FiberNode::derive_state_node(node.clone().unwrap()).unwrap(),
+            )
+        } else if n.borrow().tag == WorkTag::HostPortal {
+            // The children of the portal are placed into the portal container
+        } else if n.borrow().child.is_some() {
             let n = node_unwrap.clone();
-            if n.borrow().tag == WorkTag::HostComponent || n.borrow().tag == WorkTag::HostText {
-                self.host_config.append_initial_child(
-                    parent.clone(),
-                    FiberNode::derive_state_node(node.clone().unwrap()).unwrap(),
-                )
-            } else if n.borrow().child.is_some() {
-                let n = node_unwrap.clone();
-                {
-                    let borrowed = n.borrow_mut();
-                    borrowed
-                        .child
-                        .as_ref()
-                        .unwrap()
-                        .clone()
-                        .borrow_mut()
-                        ._return = Some(node_unwrap.clone());
-                }
-
-                node = node_unwrap.clone().borrow().child.clone();
-                continue;
+            {
+                let borrowed = n.borrow_mut();
+                borrowed
+                    .child
+                    .as_ref()
+                    .unwrap()
+                    .clone()
+                    .borrow_mut()
+                    ._return = Some(node_unwrap.clone());
             }
 
-            if Rc::ptr_eq(&node_unwrap, &work_in_progress) {
+            node = node_unwrap.clone().borrow().child.clone();
+            continue;
+        }
+
+        if Rc::ptr_eq(&node_unwrap, &work_in_progress) {
+            return;
+        }
+
+        while node
+            .clone()
+            .unwrap()
+            .clone()
+            .borrow()
+            .sibling
+            .clone()
+            .is_none()
+        {
+            let node_cloned = node.clone().unwrap().clone();
+            if node_cloned.borrow()._return.is_none()
+                || Rc::ptr_eq(
+                    &node_cloned.borrow()._return.as_ref().unwrap(),
+                    &work_in_progress,
+                )
+            {
                 return;
             }
 
-            while node
+            node = node_cloned.borrow()._return.clone();
+        }
+
+        {
+            let node = node.clone().unwrap();
+            let _return = { node.borrow()._return.clone() };
+            node.borrow()
+                .sibling
                 .clone()
                 .unwrap()
                 .clone()
-                .borrow()
-                .sibling
-                .clone()
-                .is_none()
+                .borrow_mut()
+                ._return = _return;
+        }
+
+        node = node.clone().unwrap().borrow().sibling.clone();
+    }
+}
+
+fn bubble_properties(complete_work: Rc<RefCell<FiberNode>>) {
+    let mut subtree_flags = Flags::NoFlags;
+    let mut new_child_lanes = Lane::NoLane;
+    {
+        let mut child = { complete_work.clone().borrow().child.clone() };
+
+        while child.is_some() {
+            let child_rc = child.clone().unwrap().clone();
             {
-                let node_cloned = node.clone().unwrap().clone();
-                if node_cloned.borrow()._return.is_none()
-                    || Rc::ptr_eq(
-                        &node_cloned.borrow()._return.as_ref().unwrap(),
-                        &work_in_progress,
-                    )
-                {
-                    return;
-                }
+                let child_borrowed = child_rc.borrow();
+                subtree_flags |= child_borrowed.subtree_flags.clone();
+                subtree_flags |= child_borrowed.flags.clone();
 
-                node = node_cloned.borrow()._return.clone();
+                new_child_lanes = merge_lanes(
+                    new_child_lanes,
+                    merge_lanes(
+                        child_borrowed.lanes.clone(),
+                        child_borrowed.child_lanes.clone(),
+                    ),
+                )
             }
-
             {
-                let node = node.clone().unwrap();
-                let _return = { node.borrow()._return.clone() };
-                node.borrow()
-                    .sibling
-                    .clone()
-                    .unwrap()
-                    .clone()
-                    .borrow_mut()
-                    ._return = _return;
+                child_rc.borrow_mut()._return = Some(complete_work.clone());
             }
-
-            node = node.clone().unwrap().borrow().sibling.clone();
+            child = child_rc.borrow().sibling.clone();
         }
     }
+    complete_work.clone().borrow_mut().subtree_flags |= subtree_flags.clone();
+    complete_work.clone().borrow_mut().child_lanes |= new_child_lanes.clone();
+}
 
-    fn bubble_properties(&self, complete_work: Rc<RefCell<FiberNode>>) {
-        let mut subtree_flags = Flags::NoFlags;
-        let mut new_child_lanes = Lane::NoLane;
-        {
-            let mut child = { complete_work.clone().borrow().child.clone() };
-
-            while child.is_some() {
-                let child_rc = child.clone().unwrap().clone();
-                {
-                    let child_borrowed = child_rc.borrow();
-                    subtree_flags |= child_borrowed.subtree_flags.clone();
-                    subtree_flags |= child_borrowed.flags.clone();
-
-                    new_child_lanes = merge_lanes(
-                        new_child_lanes,
-                        merge_lanes(
-                            child_borrowed.lanes.clone(),
-                            child_borrowed.child_lanes.clone(),
-                        ),
-                    )
-                }
-                {
-                    child_rc.borrow_mut()._return = Some(complete_work.clone());
-                }
-                child = child_rc.borrow().sibling.clone();
-            }
-        }
-        complete_work.clone().borrow_mut().subtree_flags |= subtree_flags.clone();
-        complete_work.clone().borrow_mut().child_lanes |= new_child_lanes.clone();
+fn mark_update(fiber: Rc<RefCell<FiberNode>>) {
+    fiber.borrow_mut().flags |= Flags::Update;
+}
+
+fn update_host_component(
+    state: &RootState,
+    current: Rc<RefCell<FiberNode>>,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+    new_props: &JsValue,
+) {
+    let old_props = { current.borrow().memoized_props.clone() };
+    if Object::is(&old_props, new_props) {
+        return;
     }
 
-    fn mark_update(fiber: Rc<RefCell<FiberNode>>) {
-        fiber.borrow_mut().flags |= Flags::Update;
+    let instance = FiberNode::derive_state_node(work_in_progress.clone()).unwrap();
+    let _type = { work_in_progress.borrow()._type.as_string().unwrap() };
+    let update_payload = state
+        .host_config
+        .prepare_update(instance, _type, &old_props, new_props);
+    let has_update = update_payload.is_some();
+    work_in_progress.borrow_mut().update_payload = update_payload;
+    if has_update {
+        mark_update(work_in_progress.clone());
     }
+}
 
-    pub fn complete_work(
-        &self,
-        work_in_progress: Rc<RefCell<FiberNode>>,
-    ) -> Option<Rc<RefCell<FiberNode>>> {
-        let work_in_progress_cloned = work_in_progress.clone();
-        let new_props = { work_in_progress_cloned.borrow().pending_props.clone() };
-        let current = { work_in_progress_cloned.borrow().alternate.clone() };
-        let tag = { work_in_progress_cloned.borrow().tag.clone() };
-        match tag {
-            WorkTag::HostComponent => {
-                if current.is_some() && work_in_progress_cloned.borrow().state_node.is_some() {
-                    // todo: compare props to decide if need to update
-                    CompleteWork::mark_update(work_in_progress.clone());
-                    let current = current.unwrap();
-                    if !Object::is(
-                        &current.borrow()._ref,
-                        &work_in_progress_cloned.borrow()._ref,
-                    ) {
-                        mark_ref(work_in_progress.clone());
-                    }
-                } else {
-                    let instance = self.host_config.create_instance(
-                        work_in_progress
-                            .clone()
-                            .borrow()
-                            ._type
-                            .as_ref()
-                            .as_string()
-                            .unwrap(),
-                        Rc::new(new_props),
-                    );
-                    self.append_all_children(instance.clone(), work_in_progress.clone());
-                    work_in_progress.clone().borrow_mut().state_node =
-                        Some(Rc::new(StateNode::Element(instance.clone())));
-                    if !work_in_progress.borrow()._ref.is_null() {
-                        mark_ref(work_in_progress.clone());
-                    }
+pub fn complete_work(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+) -> Option<Rc<RefCell<FiberNode>>> {
+    let work_in_progress_cloned = work_in_progress.clone();
+    let new_props = { work_in_progress_cloned.borrow().pending_props.clone() };
+    let current = { work_in_progress_cloned.borrow().alternate.clone() };
+    let tag = { work_in_progress_cloned.borrow().tag.clone() };
+    pop_tree_context(state, work_in_progress.clone());
+    match tag {
+        WorkTag::HostRoot => {
+            pop_host_container(state);
+            work_in_progress.borrow_mut().flags -= Flags::DidCapture;
+            bubble_properties(work_in_progress.clone());
+            None
+        }
+        WorkTag::HostComponent => {
+            pop_host_context(state);
+            if current.is_some() && work_in_progress_cloned.borrow().state_node.is_some() {
+                let current = current.unwrap();
+                update_host_component(state, current.clone(), work_in_progress.clone(), &new_props);
+                if !Object::is(
+                    &current.borrow()._ref,
+                    &work_in_progress_cloned.borrow()._ref,
+                ) {
+                    mark_ref(work_in_progress.clone());
+                }
+            } else {
+                let _type = work_in_progress
+                    .clone()
+                    .borrow()
+                    ._type
+                    .as_ref()
+                    .as_string()
+                    .unwrap();
+                let instance = state.host_config.create_instance(
+                    _type.clone(),
+                    Rc::new(new_props.clone()),
+                    &get_host_context(state),
+                );
+                append_all_children(state, instance.clone(), work_in_progress.clone());
+                state
+                    .host_config
+                    .finalize_initial_children(instance.clone(), _type, &new_props);
+                state
+                    .host_config
+                    .precache_fiber_node(work_in_progress.clone(), instance.clone());
+                work_in_progress.clone().borrow_mut().state_node =
+                    Some(Rc::new(StateNode::Element(instance.clone())));
+                if !work_in_progress.borrow()._ref.is_null() {
+                    mark_ref(work_in_progress.clone());
                 }
+            }
 
-                self.bubble_properties(work_in_progress.clone());
+            bubble_properties(work_in_progress.clone());
+            // log!(
+            //     "bubble_properties HostComponent {:?}",
+            //     work_in_progress.clone()
+            // );
+            None
+        }
+        WorkTag::HostText => {
+            if current.is_some() && work_in_progress_cloned.borrow().state_node.is_some() {
+                let old_text = derive_from_js_value(
+                    &current.clone().unwrap().clone().borrow().memoized_props,
+                    "content",
+                );
+                let new_text = derive_from_js_value(&new_props, "content");
                 // log!(
-                //     "bubble_properties HostComponent {:?}",
-                //     work_in_progress.clone()
+                //     "complete host {:?} {:?} {:?}",
+                //     work_in_progress_cloned,
+                //     old_text,
+                //     new_text
                 // );
-                None
-            }
-            WorkTag::HostText => {
-                if current.is_some() && work_in_progress_cloned.borrow().state_node.is_some() {
-                    let old_text = derive_from_js_value(
-                        &current.clone().unwrap().clone().borrow().memoized_props,
-                        "content",
-                    );
-                    let new_text = derive_from_js_value(&new_props, "content");
-                    // log!(
-                    //     "complete host {:?} {:?} {:?}",
-                    //     work_in_progress_cloned,
-                    //     old_text,
-                    //     new_text
-                    // );
-                    if !Object::is(&old_text, &new_text) {
-                        CompleteWork::mark_update(work_in_progress.clone());
-                    }
-                } else {
-                    let text_instance = self.host_config.create_text_instance(
-                        &Reflect::get(&new_props, &JsValue::from_str("content")).unwrap(),
-                    );
-                    work_in_progress.clone().borrow_mut().state_node =
-                        Some(Rc::new(StateNode::Element(text_instance.clone())));
+                if !Object::is(&old_text, &new_text) {
+                    mark_update(work_in_progress.clone());
                 }
-
-                self.bubble_properties(work_in_progress.clone());
-                None
-            }
-            WorkTag::ContextProvider => {
-                let _type = { work_in_progress.borrow()._type.clone() };
-                let context = derive_from_js_value(&_type, "_context");
-                pop_provider(&context);
-                self.bubble_properties(work_in_progress.clone());
-                None
+            } else {
+                let text_instance = state.host_config.create_text_instance(
+                    &Reflect::get(&new_props, &JsValue::from_str("content")).unwrap(),
+                );
+                work_in_progress.clone().borrow_mut().state_node =
+                    Some(Rc::new(StateNode::Element(text_instance.clone())));
             }
-            // WorkTag::SuspenseComponent => {
-            //     pop_suspense_handler();
-            //     let offscreen_fiber = work_in_progress.borrow().child.clone().unwrap();
-            //     let is_hidden =
-            //         derive_from_js_value(&offscreen_fiber.borrow().pending_props, "mode")
-            //             .as_string()
-            //             .unwrap()
-            //             == "hidden";
-            //     let current_offscreen_fiber = offscreen_fiber.borrow().alternate.clone();
-            //     if current_offscreen_fiber.is_some() {
-            //         let current_offscreen_fiber = current_offscreen_fiber.unwrap();
-            //         let was_hidden = derive_from_js_value(
-            //             &current_offscreen_fiber.borrow().pending_props,
-            //             "mode",
-            //         )
-            //         .as_string()
-            //         .unwrap()
-            //             == "hidden";
-            //         if is_hidden != was_hidden {
-            //             offscreen_fiber.borrow_mut().flags !=
-            //         }
-            //     }
-            //     None
-            // }
-            _ => {
-                self.bubble_properties(work_in_progress.clone());
-                None
+
+            bubble_properties(work_in_progress.clone());
+            None
+        }
+        WorkTag::ClassComponent => {
+            // The error has been captured, keep the flag until here so that
+            // the error thrown while rerendering will go to the outer boundary
+            work_in_progress.borrow_mut().flags -= Flags::DidCapture;
+            bubble_properties(work_in_progress.clone());
+            None
+        }
+        WorkTag::HostPortal => {
+            pop_host_container(state);
+            if current.is_none() {
+                let container = FiberNode::derive_state_node(work_in_progress.clone()).unwrap();
+                state.host_config.prepare_portal_mount(container);
             }
+            bubble_properties(work_in_progress.clone());
+            None
+        }
+        WorkTag::ContextProvider => {
+            let _type = { work_in_progress.borrow()._type.clone() };
+            let context = derive_from_js_value(&_type, "_context");
+            pop_provider(state, &context);
+            bubble_properties(work_in_progress.clone());
+            None
+        }
+        // WorkTag::SuspenseComponent => {
+        //     pop_suspense_handler();
+        //     let offscreen_fiber = work_in_progress.borrow().child.clone().unwrap();
+        //     let is_hidden =
+        //         derive_from_js_value(&offscreen_fiber.borrow().pending_props, "mode")
+        //             .as_string()
+        //             .unwrap()
+        //             == "hidden";
+        //     let current_offscreen_fiber = offscreen_fiber.borrow().alternate.clone();
+        //     if current_offscreen_fiber.is_some() {
+        //         let current_offscreen_fiber = current_offscreen_fiber.unwrap();
+        //         let was_hidden = derive_from_js_value(
+        //             &current_offscreen_fiber.borrow().pending_props,
+        //             "mode",
+        //         )
+        //         .as_string()
+        //         .unwrap()
+        //             == "hidden";
+        //         if is_hidden != was_hidden {
+        //             offscreen_fiber.borrow_mut().flags !=
+        //         }
+        //     }
+        //     None
+        // }
+        _ => {
+            bubble_properties(work_in_progress.clone());
+            None
         }
     }
 }
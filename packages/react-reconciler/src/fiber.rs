@@ -7,25 +7,33 @@ use std::rc::Rc;
 
 use scheduler::Task;
 use wasm_bindgen::JsValue;
-use web_sys::js_sys::Reflect;
+use web_sys::js_sys::{Array, Function, Reflect};
 
 use shared::{
-    derive_from_js_value, log, type_of, REACT_LAZY_TYPE, REACT_MEMO_TYPE, REACT_PROVIDER_TYPE,
-    REACT_SUSPENSE_TYPE,
+    derive_from_js_value, log, type_of, REACT_CONSUMER_TYPE, REACT_FORWARD_REF_TYPE,
+    REACT_LAZY_TYPE, REACT_MEMO_TYPE, REACT_PROVIDER_TYPE, REACT_SUSPENSE_TYPE,
 };
 
+use crate::fiber_class_component::is_class_component;
 use crate::fiber_context::ContextItem;
 use crate::fiber_flags::Flags;
 use crate::fiber_hooks::{Effect, Hook};
 use crate::fiber_lanes::{get_highest_priority, merge_lanes, Lane};
+use crate::root_state::RootState;
 use crate::update_queue::{Update, UpdateQueue};
 use crate::work_tags::WorkTag;
-use crate::JsValueKey;
+use crate::{HostConfig, JsValueKey};
 
 #[derive(Debug)]
 pub enum StateNode {
     FiberRootNode(Rc<RefCell<FiberRootNode>>),
     Element(Rc<dyn Any>),
+    ClassInstance(JsValue),
+    // container_info is what passed to createPortal, container is converted by the HostConfig
+    HostPortal {
+        container_info: JsValue,
+        container: Rc<dyn Any>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -37,8 +45,9 @@ pub enum MemoizedState {
 
 #[derive(Debug, Clone)]
 pub struct PendingPassiveEffects {
-    pub unmount: Vec<Rc<RefCell<Effect>>>,
-    pub update: Vec<Rc<RefCell<Effect>>>,
+    // The fiber is kept to find the error boundary when the effect throws
+    pub unmount: Vec<(Rc<RefCell<FiberNode>>, Rc<RefCell<Effect>>)>,
+    pub update: Vec<(Rc<RefCell<FiberNode>>, Rc<RefCell<Effect>>)>,
 }
 
 impl MemoizedState {
@@ -71,10 +80,14 @@ pub struct FiberNode {
     pub alternate: Option<Rc<RefCell<FiberNode>>>,
     pub _type: JsValue,
     pub _ref: JsValue,
+    // The cleanup returned by the callback ref, called instead of the ref with null
+    pub ref_cleanup: Option<Function>,
     pub flags: Flags,
     pub subtree_flags: Flags,
     pub memoized_props: JsValue,
     pub memoized_state: Option<MemoizedState>,
+    // The result of HostConfig::prepare_update, consumed by HostConfig::commit_update
+    pub update_payload: Option<JsValue>,
     pub deletions: Vec<Rc<RefCell<FiberNode>>>,
     pub dependencies: Option<Rc<RefCell<FiberDependencies>>>,
 }
@@ -139,12 +152,14 @@ impl FiberNode {
             _type: JsValue::null(),
             memoized_props: JsValue::null(),
             memoized_state: None,
+            update_payload: None,
             flags: Flags::NoFlags,
             subtree_flags: Flags::NoFlags,
             deletions: vec![],
             lanes: Lane::NoLane,
             child_lanes: Lane::NoLane,
             _ref,
+            ref_cleanup: None,
             dependencies: None,
         }
     }
@@ -158,6 +173,24 @@ impl FiberNode {
         )
     }
 
+    pub fn create_fiber_from_portal(portal: &JsValue, host_config: &dyn HostConfig) -> FiberNode {
+        let children = derive_from_js_value(portal, "children");
+        let pending_props = if children.is_null() || children.is_undefined() {
+            Array::new().into()
+        } else {
+            children
+        };
+        let key = derive_from_js_value(portal, "key");
+        let mut fiber = FiberNode::new(WorkTag::HostPortal, pending_props, key, JsValue::null());
+        let container_info = derive_from_js_value(portal, "containerInfo");
+        let container = host_config.get_portal_container(&container_info);
+        fiber.state_node = Some(Rc::new(StateNode::HostPortal {
+            container_info,
+            container,
+        }));
+        fiber
+    }
+
     pub fn create_fiber_from_fragment(elements: JsValue, key: JsValue) -> FiberNode {
         FiberNode::new(WorkTag::Fragment, elements, key, JsValue::null())
     }
@@ -178,13 +211,19 @@ impl FiberNode {
             let _typeof = derive_from_js_value(&_type, "$$typeof");
             if _typeof == REACT_PROVIDER_TYPE {
                 fiber_tag = WorkTag::ContextProvider;
+            } else if _typeof == REACT_CONSUMER_TYPE {
+                fiber_tag = WorkTag::ContextConsumer;
             } else if _typeof == REACT_MEMO_TYPE {
                 fiber_tag = WorkTag::MemoComponent;
+            } else if _typeof == REACT_FORWARD_REF_TYPE {
+                fiber_tag = WorkTag::ForwardRef;
             } else if _typeof == REACT_LAZY_TYPE {
                 fiber_tag = WorkTag::LazyComponent;
             } else {
                 log!("Unsupported type {:?}", _type);
             }
+        } else if is_class_component(&_type) {
+            fiber_tag = WorkTag::ClassComponent
         } else if !type_of(&_type, "function") {
             log!("Unsupported type {:?}", _type);
         }
@@ -238,6 +277,7 @@ impl FiberNode {
                     }))),
                     None => None,
                 };
+                wip.ref_cleanup = c.ref_cleanup.clone();
                 wip.alternate = Some(current);
                 wip
             };
@@ -256,6 +296,7 @@ impl FiberNode {
                 wip.flags = Flags::NoFlags;
                 wip.subtree_flags = Flags::NoFlags;
                 wip.deletions = vec![];
+                wip.update_payload = None;
                 wip._type = c._type.clone();
 
                 wip.update_queue = c.update_queue.clone();
@@ -273,6 +314,7 @@ impl FiberNode {
                     None => None,
                 };
                 wip._ref = c._ref.clone();
+                wip.ref_cleanup = c.ref_cleanup.clone();
             }
             w.clone()
         };
@@ -287,6 +329,8 @@ impl FiberNode {
         Some(match &*state_node.unwrap().clone() {
             StateNode::FiberRootNode(root) => root.clone().borrow().container.clone(),
             StateNode::Element(ele) => ele.clone(),
+            StateNode::ClassInstance(instance) => Rc::new(instance.clone()),
+            StateNode::HostPortal { container, .. } => container.clone(),
         })
     }
 }
@@ -303,10 +347,20 @@ pub struct FiberRootNode {
     pub callback_priority: Lane,
     pub pending_passive_effects: Rc<RefCell<PendingPassiveEffects>>,
     pub ping_cache: Option<HashMap<JsValueKey, Rc<RefCell<HashSet<Lane>>>>>,
+    // The identifierPrefix option of createRoot, prepended to the ids generated by useId
+    pub identifier_prefix: String,
+    // The renderer of the root and the state of its render and commit
+    pub(crate) state: Rc<RootState>,
+    // The passive effects have been scheduled to flush, but have not been flushed yet
+    pub does_have_passive_effects: bool,
 }
 
 impl FiberRootNode {
-    pub fn new(container: Rc<dyn Any>, host_root_fiber: Rc<RefCell<FiberNode>>) -> Self {
+    pub fn new(
+        container: Rc<dyn Any>,
+        host_root_fiber: Rc<RefCell<FiberNode>>,
+        host_config: Rc<dyn HostConfig>,
+    ) -> Self {
         Self {
             container,
             current: host_root_fiber,
@@ -322,6 +376,9 @@ impl FiberRootNode {
             pinged_lanes: Lane::NoLane,
             suspended_lanes: Lane::NoLane,
             ping_cache: None,
+            identifier_prefix: String::new(),
+            state: RootState::new(host_config),
+            does_have_passive_effects: false,
         }
     }
 
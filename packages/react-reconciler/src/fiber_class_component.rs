@@ -0,0 +1,605 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::js_sys::{Array, Function, Object, Reflect};
+
+use shared::{derive_from_js_value, is_dev, log, shallow_equal, type_of};
+
+use crate::fiber::{FiberNode, MemoizedState, StateNode};
+use crate::fiber_context::read_context;
+use crate::fiber_flags::Flags;
+use crate::fiber_lanes::{is_subset_of_lanes, merge_lanes, request_update_lane, Lane};
+use crate::root_state::RootState;
+use crate::update_queue::{create_update, create_update_queue, enqueue_update, Update, UpdateTag};
+use crate::work_loop::{capture_commit_phase_error, schedule_update_on_fiber};
+
+pub fn is_class_component(component: &JsValue) -> bool {
+    if !type_of(component, "function") {
+        return false;
+    }
+    let prototype = derive_from_js_value(component, "prototype");
+    !prototype.is_undefined() && derive_from_js_value(&prototype, "isReactComponent").is_truthy()
+}
+
+pub fn get_class_instance(fiber: Rc<RefCell<FiberNode>>) -> Option<JsValue> {
+    let state_node = fiber.borrow().state_node.clone();
+    match state_node {
+        Some(state_node) => match &*state_node {
+            StateNode::ClassInstance(instance) => Some(instance.clone()),
+            _ => None,
+        },
+        None => None,
+    }
+}
+
+pub fn call_instance_method(
+    instance: &JsValue,
+    method: &str,
+    args: &Array,
+) -> Result<JsValue, JsValue> {
+    let f = derive_from_js_value(instance, method);
+    if !f.is_function() {
+        return Ok(JsValue::undefined());
+    }
+    f.dyn_ref::<Function>().unwrap().apply(instance, args)
+}
+
+fn get_memoized_state(fiber: Rc<RefCell<FiberNode>>) -> JsValue {
+    match fiber.borrow().memoized_state.as_ref() {
+        Some(MemoizedState::MemoizedJsValue(state)) => state.clone(),
+        _ => JsValue::null(),
+    }
+}
+
+fn set_memoized_state(fiber: Rc<RefCell<FiberNode>>, state: JsValue) {
+    fiber.borrow_mut().memoized_state = Some(MemoizedState::MemoizedJsValue(state));
+}
+
+fn set_instance_property(instance: &JsValue, key: &str, value: &JsValue) {
+    Reflect::set(instance, &key.into(), value).expect("TODO: panic set instance property");
+}
+
+pub fn enqueue_class_update(fiber: Rc<RefCell<FiberNode>>, update: Update, lane: Lane) {
+    let update_queue = { fiber.borrow().update_queue.clone() };
+    if update_queue.is_none() {
+        // The fiber has been unmounted
+        return;
+    }
+    let update_queue = update_queue.unwrap();
+    enqueue_update(update_queue.clone(), update, fiber.clone(), lane.clone());
+
+    // The work in progress has its own copy of the queue, but the pending updates are shared
+    let alternate = { fiber.borrow().alternate.clone() };
+    if let Some(alternate) = alternate {
+        let alternate_queue = { alternate.borrow().update_queue.clone() };
+        if let Some(alternate_queue) = alternate_queue {
+            if !Rc::ptr_eq(&alternate_queue, &update_queue) {
+                let pending = update_queue.borrow().shared.pending.clone();
+                alternate_queue.borrow_mut().shared.pending = pending;
+            }
+        }
+    }
+    schedule_update_on_fiber(fiber, lane);
+}
+
+fn to_callback(callback: &JsValue) -> Option<Function> {
+    callback.dyn_ref::<Function>().cloned()
+}
+
+// this.updater of the instance, the fiber is captured like the dispatch of useState
+fn create_class_updater(fiber: Rc<RefCell<FiberNode>>) -> JsValue {
+    let updater = Object::new();
+
+    let fiber_cloned = fiber.clone();
+    let enqueue_set_state = Closure::wrap(Box::new(move |payload: JsValue, callback: JsValue| {
+        let lane = request_update_lane();
+        let mut update = create_update(payload, lane.clone());
+        update.callback = to_callback(&callback);
+        enqueue_class_update(fiber_cloned.clone(), update, lane);
+    }) as Box<dyn Fn(JsValue, JsValue)>);
+    let enqueue_set_state_fn = enqueue_set_state
+        .as_ref()
+        .unchecked_ref::<Function>()
+        .clone();
+    enqueue_set_state.forget();
+
+    let enqueue_force_update = Closure::wrap(Box::new(move |callback: JsValue| {
+        let lane = request_update_lane();
+        let mut update = create_update(JsValue::undefined(), lane.clone());
+        update.tag = UpdateTag::ForceUpdate;
+        update.callback = to_callback(&callback);
+        enqueue_class_update(fiber.clone(), update, lane);
+    }) as Box<dyn Fn(JsValue)>);
+    let enqueue_force_update_fn = enqueue_force_update
+        .as_ref()
+        .unchecked_ref::<Function>()
+        .clone();
+    enqueue_force_update.forget();
+
+    Reflect::set(&updater, &"enqueueSetState".into(), &enqueue_set_state_fn)
+        .expect("TODO: panic set enqueueSetState");
+    Reflect::set(
+        &updater,
+        &"enqueueForceUpdate".into(),
+        &enqueue_force_update_fn,
+    )
+    .expect("TODO: panic set enqueueForceUpdate");
+    updater.into()
+}
+
+fn read_class_context(state: &RootState, wip: Rc<RefCell<FiberNode>>, ctor: &JsValue) -> JsValue {
+    let context_type = derive_from_js_value(ctor, "contextType");
+    if type_of(&context_type, "object") && !context_type.is_null() {
+        return read_context(state, Some(wip), context_type);
+    }
+    JsValue::undefined()
+}
+
+// Merge the partial state into the previous state
+fn assign_state(prev_state: &JsValue, partial_state: &JsValue) -> JsValue {
+    if partial_state.is_null() || partial_state.is_undefined() {
+        return prev_state.clone();
+    }
+    Object::assign2(
+        &Object::new(),
+        prev_state.unchecked_ref::<Object>(),
+        partial_state.unchecked_ref::<Object>(),
+    )
+    .into()
+}
+
+fn get_state_from_update(
+    wip: Rc<RefCell<FiberNode>>,
+    update: Rc<RefCell<Update>>,
+    prev_state: &JsValue,
+    props: &JsValue,
+    instance: &JsValue,
+) -> Result<JsValue, JsValue> {
+    let tag = { update.borrow().tag.clone() };
+    match tag {
+        UpdateTag::UpdateState => {
+            let payload = update
+                .borrow()
+                .action
+                .clone()
+                .unwrap_or(JsValue::undefined());
+            let partial_state = match payload.dyn_ref::<Function>() {
+                Some(f) => f.call2(instance, prev_state, props)?,
+                None => payload.clone(),
+            };
+            Ok(assign_state(prev_state, &partial_state))
+        }
+        UpdateTag::ForceUpdate => {
+            let queue = { wip.borrow().update_queue.clone().unwrap() };
+            queue.borrow_mut().has_force_update = true;
+            Ok(prev_state.clone())
+        }
+        UpdateTag::CaptureUpdate => {
+            let flags = { wip.borrow().flags.clone() };
+            wip.borrow_mut().flags = (flags - Flags::ShouldCapture) | Flags::DidCapture;
+            let get_derived_state_from_error =
+                derive_from_js_value(&wip.borrow()._type, "getDerivedStateFromError");
+            match get_derived_state_from_error.dyn_ref::<Function>() {
+                Some(f) => {
+                    let error = update
+                        .borrow()
+                        .action
+                        .clone()
+                        .unwrap_or(JsValue::undefined());
+                    let partial_state = f.call1(&JsValue::null(), &error)?;
+                    Ok(assign_state(prev_state, &partial_state))
+                }
+                None => Ok(prev_state.clone()),
+            }
+        }
+    }
+}
+
+fn clone_update(
+    update: Rc<RefCell<Update>>,
+    lane: Lane,
+    keep_callback: bool,
+) -> Rc<RefCell<Update>> {
+    let update = update.borrow();
+    let mut clone = create_update(update.action.clone().unwrap_or(JsValue::undefined()), lane);
+    clone.tag = update.tag.clone();
+    if keep_callback {
+        clone.callback = update.callback.clone();
+    }
+    Rc::new(RefCell::new(clone))
+}
+
+// The work in progress gets its own queue, so base_state and base_queue of current are
+// kept if the render is interrupted
+fn clone_update_queue(current: Rc<RefCell<FiberNode>>, wip: Rc<RefCell<FiberNode>>) {
+    let current_queue = { current.borrow().update_queue.clone() };
+    let wip_queue = { wip.borrow().update_queue.clone() };
+    if let (Some(current_queue), Some(wip_queue)) = (current_queue, wip_queue) {
+        if Rc::ptr_eq(&current_queue, &wip_queue) {
+            let mut queue = current_queue.borrow().clone();
+            queue.callbacks = vec![];
+            wip.borrow_mut().update_queue = Some(Rc::new(RefCell::new(queue)));
+        }
+    }
+}
+
+fn clone_update_ring(last: Option<Rc<RefCell<Update>>>) -> Option<Rc<RefCell<Update>>> {
+    let last = last?;
+    let first = last.borrow().next.clone().unwrap();
+    let mut update = first.clone();
+    let mut new_first: Option<Rc<RefCell<Update>>> = None;
+    let mut new_last: Option<Rc<RefCell<Update>>> = None;
+    loop {
+        let lane = { update.borrow().lane.clone() };
+        let clone = clone_update(update.clone(), lane, true);
+        match new_last.clone() {
+            None => new_first = Some(clone.clone()),
+            Some(new_last) => new_last.borrow_mut().next = Some(clone.clone()),
+        }
+        new_last = Some(clone);
+        let next = { update.borrow().next.clone().unwrap() };
+        if Rc::ptr_eq(&next, &first) {
+            break;
+        }
+        update = next;
+    }
+    new_last.clone().unwrap().borrow_mut().next = new_first;
+    new_last
+}
+
+// The update created by an error boundary in the render phase only belongs to the work
+// in progress, it's dropped if the render is restarted
+pub fn enqueue_captured_update(wip: Rc<RefCell<FiberNode>>, update: Update) {
+    let current = { wip.borrow().alternate.clone() };
+    if let Some(current) = current {
+        clone_update_queue(current, wip.clone());
+    }
+    let queue = { wip.borrow().update_queue.clone().unwrap() };
+    // Copy the base updates, they might be shared with current
+    let base_queue = clone_update_ring(queue.borrow().base_queue.clone());
+    let update = Rc::new(RefCell::new(update));
+    match base_queue {
+        None => update.borrow_mut().next = Some(update.clone()),
+        Some(base_queue) => {
+            update.borrow_mut().next = base_queue.borrow().next.clone();
+            base_queue.borrow_mut().next = Some(update.clone());
+        }
+    }
+    queue.borrow_mut().base_queue = Some(update);
+}
+
+fn process_class_update_queue(
+    wip: Rc<RefCell<FiberNode>>,
+    props: &JsValue,
+    instance: &JsValue,
+    render_lane: Lane,
+) -> Result<(), JsValue> {
+    let queue = { wip.borrow().update_queue.clone().unwrap() };
+    queue.borrow_mut().has_force_update = false;
+    let mut base_queue = queue.borrow().base_queue.clone();
+    let mut pending = queue.borrow().shared.pending.clone();
+
+    if pending.is_some() {
+        let current_queue = match wip.borrow().alternate.clone() {
+            Some(current) => current.borrow().update_queue.clone(),
+            None => None,
+        };
+        let current_queue =
+            current_queue.filter(|current_queue| !Rc::ptr_eq(current_queue, &queue));
+        // The base updates are shared with current unless an error has been captured
+        let shares_base_queue = match current_queue.as_ref() {
+            Some(current_queue) => match (
+                current_queue.borrow().base_queue.as_ref(),
+                base_queue.as_ref(),
+            ) {
+                (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            },
+            None => false,
+        };
+        if current_queue.is_some() && !shares_base_queue {
+            // Keep the pending updates of current untouched
+            pending = clone_update_ring(pending);
+        }
+
+        if base_queue.is_some() {
+            // Same as update_state, pending is appended to the base queue
+            let base_queue = base_queue.clone().unwrap();
+            let pending = pending.clone().unwrap();
+            let base_first = base_queue.borrow().next.clone();
+            let pending_first = pending.borrow().next.clone();
+            base_queue.borrow_mut().next = pending_first;
+            pending.borrow_mut().next = base_first;
+        }
+        base_queue = pending;
+        queue.borrow_mut().shared.pending = None;
+        queue.borrow_mut().base_queue = base_queue.clone();
+
+        if shares_base_queue {
+            // Same as update_state, current keeps all the updates in case the render is interrupted
+            let current_queue = current_queue.unwrap();
+            current_queue.borrow_mut().shared.pending = None;
+            current_queue.borrow_mut().base_queue = base_queue.clone();
+        }
+    }
+
+    if base_queue.is_none() {
+        return Ok(());
+    }
+
+    let base_state = queue.borrow().base_state.clone().unwrap_or(JsValue::null());
+    let mut new_state = base_state.clone();
+    let mut new_base_state = base_state;
+    let mut new_base_queue_first: Option<Rc<RefCell<Update>>> = None;
+    let mut new_base_queue_last: Option<Rc<RefCell<Update>>> = None;
+
+    let first = base_queue.unwrap().borrow().next.clone().unwrap();
+    let mut update = first.clone();
+    loop {
+        let update_lane = { update.borrow().lane.clone() };
+        if !is_subset_of_lanes(render_lane.clone(), update_lane.clone()) {
+            // Insufficient priority, the update and the following ones are kept
+            let clone = clone_update(update.clone(), update_lane.clone(), true);
+            if new_base_queue_last.is_none() {
+                new_base_queue_first = Some(clone.clone());
+                new_base_state = new_state.clone();
+            } else {
+                new_base_queue_last.clone().unwrap().borrow_mut().next = Some(clone.clone());
+            }
+            new_base_queue_last = Some(clone);
+            let lanes = { wip.borrow().lanes.clone() };
+            wip.borrow_mut().lanes = merge_lanes(lanes, update_lane);
+        } else {
+            if new_base_queue_last.is_some() {
+                // It has to be processed again after the skipped ones, but never skipped
+                let clone = clone_update(update.clone(), Lane::NoLane, false);
+                new_base_queue_last.clone().unwrap().borrow_mut().next = Some(clone.clone());
+                new_base_queue_last = Some(clone);
+            }
+
+            new_state =
+                get_state_from_update(wip.clone(), update.clone(), &new_state, props, instance)?;
+            let callback = { update.borrow().callback.clone() };
+            if let Some(callback) = callback {
+                queue.borrow_mut().callbacks.push(callback);
+                wip.borrow_mut().flags |= Flags::Callback;
+            }
+        }
+
+        let next = { update.borrow().next.clone().unwrap() };
+        if Rc::ptr_eq(&next, &first) {
+            break;
+        }
+        update = next;
+    }
+
+    if new_base_queue_last.is_none() {
+        new_base_state = new_state.clone();
+    } else {
+        new_base_queue_last.clone().unwrap().borrow_mut().next = new_base_queue_first;
+    }
+
+    queue.borrow_mut().base_state = Some(new_base_state);
+    queue.borrow_mut().base_queue = new_base_queue_last;
+    set_memoized_state(wip, new_state);
+    Ok(())
+}
+
+fn apply_derived_state_from_props(
+    wip: Rc<RefCell<FiberNode>>,
+    ctor: &JsValue,
+    next_props: &JsValue,
+) -> Result<(), JsValue> {
+    let get_derived_state_from_props = derive_from_js_value(ctor, "getDerivedStateFromProps");
+    if !get_derived_state_from_props.is_function() {
+        return Ok(());
+    }
+    let prev_state = get_memoized_state(wip.clone());
+    let partial_state = get_derived_state_from_props
+        .dyn_ref::<Function>()
+        .unwrap()
+        .call2(&JsValue::null(), next_props, &prev_state)?;
+    if is_dev() && partial_state.is_undefined() {
+        log!("getDerivedStateFromProps(): A valid state object (or null) must be returned");
+    }
+    let memoized_state = assign_state(&prev_state, &partial_state);
+    set_memoized_state(wip.clone(), memoized_state.clone());
+
+    // If there's no pending update, the derived state is also the base state
+    if wip.borrow().lanes == Lane::NoLane {
+        let queue = { wip.borrow().update_queue.clone().unwrap() };
+        queue.borrow_mut().base_state = Some(memoized_state);
+    }
+    Ok(())
+}
+
+pub fn construct_class_instance(
+    state: &RootState,
+    wip: Rc<RefCell<FiberNode>>,
+    ctor: &JsValue,
+    props: &JsValue,
+) -> Result<JsValue, JsValue> {
+    let context = read_class_context(state, wip.clone(), ctor);
+    let instance = Reflect::construct(
+        ctor.unchecked_ref::<Function>(),
+        &Array::of2(props, &context),
+    )?;
+
+    let instance_state = derive_from_js_value(&instance, "state");
+    set_memoized_state(
+        wip.clone(),
+        if instance_state.is_undefined() {
+            JsValue::null()
+        } else {
+            instance_state
+        },
+    );
+    set_instance_property(&instance, "updater", &create_class_updater(wip.clone()));
+    wip.borrow_mut().state_node = Some(Rc::new(StateNode::ClassInstance(instance.clone())));
+    Ok(instance)
+}
+
+pub fn mount_class_instance(
+    state: &RootState,
+    wip: Rc<RefCell<FiberNode>>,
+    ctor: &JsValue,
+    new_props: &JsValue,
+    render_lane: Lane,
+) -> Result<(), JsValue> {
+    let instance = get_class_instance(wip.clone()).unwrap();
+    let memoized_state = get_memoized_state(wip.clone());
+    set_instance_property(&instance, "props", new_props);
+    set_instance_property(&instance, "state", &memoized_state);
+    set_instance_property(
+        &instance,
+        "context",
+        &read_class_context(state, wip.clone(), ctor),
+    );
+
+    let queue = create_update_queue();
+    queue.borrow_mut().base_state = Some(memoized_state);
+    wip.borrow_mut().update_queue = Some(queue);
+
+    process_class_update_queue(wip.clone(), new_props, &instance, render_lane)?;
+    apply_derived_state_from_props(wip.clone(), ctor, new_props)?;
+    set_instance_property(&instance, "state", &get_memoized_state(wip.clone()));
+
+    if derive_from_js_value(&instance, "componentDidMount").is_function() {
+        wip.borrow_mut().flags |= Flags::Update;
+    }
+    Ok(())
+}
+
+// The instance is created but not committed, e.g. the boundary is rendered again
+// after an error has been captured
+pub fn resume_mount_class_instance(
+    state: &RootState,
+    wip: Rc<RefCell<FiberNode>>,
+    ctor: &JsValue,
+    new_props: &JsValue,
+    render_lane: Lane,
+) -> Result<bool, JsValue> {
+    let instance = get_class_instance(wip.clone()).unwrap();
+    set_instance_property(&instance, "props", new_props);
+    set_instance_property(&instance, "state", &get_memoized_state(wip.clone()));
+    set_instance_property(
+        &instance,
+        "context",
+        &read_class_context(state, wip.clone(), ctor),
+    );
+
+    process_class_update_queue(wip.clone(), new_props, &instance, render_lane)?;
+    apply_derived_state_from_props(wip.clone(), ctor, new_props)?;
+    set_instance_property(&instance, "state", &get_memoized_state(wip.clone()));
+
+    if derive_from_js_value(&instance, "componentDidMount").is_function() {
+        wip.borrow_mut().flags |= Flags::Update;
+    }
+    Ok(true)
+}
+
+fn check_should_component_update(
+    instance: &JsValue,
+    ctor: &JsValue,
+    old_props: &JsValue,
+    new_props: &JsValue,
+    old_state: &JsValue,
+    new_state: &JsValue,
+    next_context: &JsValue,
+) -> Result<bool, JsValue> {
+    if derive_from_js_value(instance, "shouldComponentUpdate").is_function() {
+        let should_update = call_instance_method(
+            instance,
+            "shouldComponentUpdate",
+            &Array::of3(new_props, new_state, next_context),
+        )?;
+        if is_dev() && should_update.is_undefined() {
+            log!("shouldComponentUpdate(): Returned undefined instead of a boolean value");
+        }
+        return Ok(should_update.is_truthy());
+    }
+
+    let prototype = derive_from_js_value(ctor, "prototype");
+    if derive_from_js_value(&prototype, "isPureReactComponent").is_truthy() {
+        return Ok(!shallow_equal(old_props, new_props) || !shallow_equal(old_state, new_state));
+    }
+    Ok(true)
+}
+
+// Return whether the instance should be re-rendered
+pub fn update_class_instance(
+    state: &RootState,
+    current: Rc<RefCell<FiberNode>>,
+    wip: Rc<RefCell<FiberNode>>,
+    ctor: &JsValue,
+    new_props: &JsValue,
+    render_lane: Lane,
+) -> Result<bool, JsValue> {
+    let instance = get_class_instance(wip.clone()).unwrap();
+    clone_update_queue(current.clone(), wip.clone());
+
+    let old_props = { wip.borrow().memoized_props.clone() };
+    set_instance_property(&instance, "props", &old_props);
+    let old_context = derive_from_js_value(&instance, "context");
+    let next_context = read_class_context(state, wip.clone(), ctor);
+
+    let old_state = get_memoized_state(wip.clone());
+    set_instance_property(&instance, "state", &old_state);
+    process_class_update_queue(wip.clone(), new_props, &instance, render_lane)?;
+    let mut new_state = get_memoized_state(wip.clone());
+    let has_force_update = { wip.borrow().update_queue.clone().unwrap() }
+        .borrow()
+        .has_force_update;
+
+    if Object::is(&old_props, new_props)
+        && Object::is(&old_state, &new_state)
+        && Object::is(&old_context, &next_context)
+        && !has_force_update
+    {
+        return Ok(false);
+    }
+
+    apply_derived_state_from_props(wip.clone(), ctor, new_props)?;
+    new_state = get_memoized_state(wip.clone());
+
+    let should_update = has_force_update
+        || check_should_component_update(
+            &instance,
+            ctor,
+            &old_props,
+            new_props,
+            &old_state,
+            &new_state,
+            &next_context,
+        )?;
+
+    if should_update {
+        if derive_from_js_value(&instance, "componentDidUpdate").is_function() {
+            wip.borrow_mut().flags |= Flags::Update;
+        }
+        if derive_from_js_value(&instance, "getSnapshotBeforeUpdate").is_function() {
+            wip.borrow_mut().flags |= Flags::Snapshot;
+        }
+    }
+
+    // Even if it's not re-rendered, the instance should point to the latest props and state
+    set_instance_property(&instance, "props", new_props);
+    set_instance_property(&instance, "state", &new_state);
+    set_instance_property(&instance, "context", &next_context);
+    Ok(should_update)
+}
+
+pub fn commit_class_callbacks(finished_work: Rc<RefCell<FiberNode>>, instance: &JsValue) {
+    let queue = { finished_work.borrow().update_queue.clone() };
+    if let Some(queue) = queue {
+        let callbacks = std::mem::take(&mut queue.borrow_mut().callbacks);
+        for callback in callbacks {
+            if let Err(e) = callback.call0(instance) {
+                capture_commit_phase_error(finished_work.clone(), e);
+            }
+        }
+    }
+}
@@ -2,19 +2,16 @@ use std::{cell::RefCell, rc::Rc};
 
 use shared::{derive_from_js_value, log};
 use wasm_bindgen::JsValue;
-use web_sys::js_sys::{Object, Reflect};
+use web_sys::js_sys::Object;
 
 use crate::{
     begin_work::mark_wip_received_update,
     fiber::{FiberDependencies, FiberNode},
     fiber_lanes::{include_some_lanes, is_subset_of_lanes, merge_lanes, Lane},
+    root_state::RootState,
     work_tags::WorkTag,
 };
 
-static mut PREV_CONTEXT_VALUE: JsValue = JsValue::null();
-static mut PREV_CONTEXT_VALUE_STACK: Vec<JsValue> = vec![];
-static mut LAST_CONTEXT_DEP: Option<Rc<RefCell<ContextItem>>> = None;
-
 #[derive(Clone, Debug)]
 pub struct ContextItem {
     context: JsValue,
@@ -22,28 +19,40 @@ pub struct ContextItem {
     next: Option<Rc<RefCell<ContextItem>>>,
 }
 
-pub fn push_provider(context: &JsValue, new_value: JsValue) {
-    unsafe {
-        PREV_CONTEXT_VALUE_STACK.push(PREV_CONTEXT_VALUE.clone());
-        PREV_CONTEXT_VALUE = Reflect::get(context, &"_currentValue".into()).unwrap();
-        Reflect::set(context, &"_currentValue".into(), &new_value);
-    }
+pub fn push_provider(state: &RootState, context: &JsValue, new_value: JsValue) {
+    state
+        .context_value_stack
+        .borrow_mut()
+        .push((context.clone(), new_value));
 }
 
-pub fn pop_provider(context: &JsValue) {
-    unsafe {
-        Reflect::set(context, &"_currentValue".into(), &PREV_CONTEXT_VALUE);
-        let top = PREV_CONTEXT_VALUE_STACK.pop();
-        if top.is_none() {
-            PREV_CONTEXT_VALUE = JsValue::null();
-        } else {
-            PREV_CONTEXT_VALUE = top.unwrap();
-        }
+pub fn pop_provider(state: &RootState, context: &JsValue) {
+    let top = state.context_value_stack.borrow_mut().pop();
+    match top {
+        Some((top_context, _)) if Object::is(&top_context, context) => {}
+        _ => log!("Unexpected pop of context provider {:?}", context),
     }
 }
 
-pub fn prepare_to_read_context(wip: Rc<RefCell<FiberNode>>, render_lane: Lane) {
-    unsafe { LAST_CONTEXT_DEP = None };
+// The interrupted render leaves its providers on the stack
+pub fn reset_context_stack(state: &RootState) {
+    state.context_value_stack.borrow_mut().clear();
+}
+
+// The value of the closest provider, or the default value of the context
+fn get_context_value(state: &RootState, context: &JsValue) -> JsValue {
+    let value = state
+        .context_value_stack
+        .borrow()
+        .iter()
+        .rev()
+        .find(|(c, _)| Object::is(c, context))
+        .map(|(_, value)| value.clone());
+    value.unwrap_or_else(|| derive_from_js_value(context, "_currentValue"))
+}
+
+pub fn prepare_to_read_context(state: &RootState, wip: Rc<RefCell<FiberNode>>, render_lane: Lane) {
+    *state.last_context_dep.borrow_mut() = None;
 
     let deps = { wip.borrow().dependencies.clone() };
 
@@ -51,19 +60,23 @@ pub fn prepare_to_read_context(wip: Rc<RefCell<FiberNode>>, render_lane: Lane) {
         let deps = deps.unwrap();
         if deps.borrow().first_context.is_some() {
             if include_some_lanes(deps.borrow().lanes.clone(), render_lane) {
-                mark_wip_received_update()
+                mark_wip_received_update(state)
             }
             deps.borrow_mut().first_context = None;
         }
     }
 }
 
-pub fn read_context(consumer: Option<Rc<RefCell<FiberNode>>>, context: JsValue) -> JsValue {
+pub fn read_context(
+    state: &RootState,
+    consumer: Option<Rc<RefCell<FiberNode>>>,
+    context: JsValue,
+) -> JsValue {
     if consumer.is_none() {
         panic!("Can only call useContext in Function Component");
     }
     let consumer = consumer.unwrap();
-    let value = derive_from_js_value(&context, "_currentValue");
+    let value = get_context_value(state, &context);
 
     let context_item = Rc::new(RefCell::new(ContextItem {
         context,
@@ -71,18 +84,17 @@ pub fn read_context(consumer: Option<Rc<RefCell<FiberNode>>>, context: JsValue)
         memoized_state: value.clone(),
     }));
 
-    if unsafe { LAST_CONTEXT_DEP.is_none() } {
-        unsafe { LAST_CONTEXT_DEP = Some(context_item.clone()) };
+    let last_context_dep = { state.last_context_dep.borrow().clone() };
+    if last_context_dep.is_none() {
+        *state.last_context_dep.borrow_mut() = Some(context_item.clone());
         consumer.borrow_mut().dependencies = Some(Rc::new(RefCell::new(FiberDependencies {
             first_context: Some(context_item),
             lanes: Lane::NoLane,
         })));
     } else {
         let next = Some(context_item.clone());
-        unsafe {
-            LAST_CONTEXT_DEP.clone().unwrap().borrow_mut().next = next.clone();
-            LAST_CONTEXT_DEP = next;
-        }
+        last_context_dep.unwrap().borrow_mut().next = next.clone();
+        *state.last_context_dep.borrow_mut() = next;
     }
     value
 }
@@ -11,12 +11,22 @@ bitflags! {
         const Ref           = 0b00010000;
         const Visibility    = 0b00100000;
         const DidCapture    = 0b01000000;
+        const ContentReset  = 0b10000000;
+        const Snapshot      = 0b100000000;
+        const Callback      = 0b1000000000;
         const ShouldCapture = 0b1000000000000;
+        // The fiber is one of the children array, used to generate the tree id of useId
+        const Forked        = 0b10000000000000;
+        // The fiber reads an external store by useSyncExternalStore in a concurrent render
+        const StoreConsistency = 0b100000000000000;
 
-        const LayoutMask    = 0b00010000; // Ref
+        const LayoutMask    = 0b1000010010; // Update | Ref | Callback
+        const BeforeMutationMask = 0b100000000; // Snapshot
         // HookEffectTags
         const HookHasEffect = 0b0001;
         const Passive = 0b0010; // useEffect
+        const Layout = 0b0100; // useLayoutEffect
+        const Insertion = 0b1000; // useInsertionEffect
     }
 }
 
@@ -27,7 +37,7 @@ impl PartialEq for Flags {
 }
 
 pub fn get_mutation_mask() -> Flags {
-    Flags::Placement | Flags::Update | Flags::ChildDeletion
+    Flags::Placement | Flags::Update | Flags::ChildDeletion | Flags::ContentReset
 }
 
 pub fn get_passive_mask() -> Flags {
@@ -1,18 +1,23 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
 
 use react::current_batch_config::REACT_CURRENT_BATCH_CONFIG;
 use wasm_bindgen::prelude::{wasm_bindgen, Closure};
 use wasm_bindgen::{JsCast, JsValue};
-use web_sys::js_sys::{Array, Function, Object, Reflect};
+use web_sys::js_sys::{Array, Error, Function, Object, Reflect};
 
 use shared::{derive_from_js_value, is_dev, log, type_of, REACT_CONTEXT_TYPE};
 
 use crate::begin_work::mark_wip_received_update;
-use crate::fiber::{FiberNode, MemoizedState};
+use crate::fiber::{FiberNode, MemoizedState, StateNode};
 use crate::fiber_context::read_context as read_context_origin;
 use crate::fiber_flags::Flags;
-use crate::fiber_lanes::{merge_lanes, remove_lanes, request_update_lane, Lane};
+use crate::fiber_lanes::{
+    include_some_lanes, includes_only_non_urgent_lanes, merge_lanes, remove_lanes,
+    request_update_lane, Lane,
+};
+use crate::fiber_tree_context::{get_tree_id, is_tree_id_enabled, to_base_32};
+use crate::root_state::RootState;
 use crate::thenable::track_used_thenable;
 use crate::update_queue::{
     create_update, create_update_queue, enqueue_update, process_update_queue,
@@ -25,10 +30,80 @@ extern "C" {
     fn updateDispatcher(args: &JsValue);
 }
 
-static mut CURRENTLY_RENDERING_FIBER: Option<Rc<RefCell<FiberNode>>> = None;
-static mut WORK_IN_PROGRESS_HOOK: Option<Rc<RefCell<Hook>>> = None;
-static mut CURRENT_HOOK: Option<Rc<RefCell<Hook>>> = None;
-static mut RENDER_LANE: Lane = Lane::NoLane;
+thread_local! {
+    // The ids of the roots which are not rendered from the tree use this counter, it's shared
+    // by all the roots so the ids are unique in the page
+    static GLOBAL_CLIENT_ID_COUNTER: Cell<u32> = Cell::new(0);
+}
+
+// The hooks state of the component being rendered in a root
+pub struct HooksState {
+    root_state: Weak<RootState>,
+    currently_rendering_fiber: RefCell<Option<Rc<RefCell<FiberNode>>>>,
+    work_in_progress_hook: RefCell<Option<Rc<RefCell<Hook>>>>,
+    current_hook: RefCell<Option<Rc<RefCell<Hook>>>>,
+    render_lane: RefCell<Lane>,
+    // Updates are not allowed in useInsertionEffect since the DOM is in an inconsistent state
+    is_running_insertion_effect: Cell<bool>,
+    // The number of useId called in the component, used to distinguish the ids in the same fiber
+    local_id_counter: Cell<u32>,
+    // The dispatchers are created once for the root, their hooks work on the state of the root
+    mount_dispatcher: RefCell<Option<JsValue>>,
+    update_dispatcher: RefCell<Option<JsValue>>,
+}
+
+impl HooksState {
+    pub fn new(root_state: Weak<RootState>) -> Self {
+        HooksState {
+            root_state,
+            currently_rendering_fiber: RefCell::new(None),
+            work_in_progress_hook: RefCell::new(None),
+            current_hook: RefCell::new(None),
+            render_lane: RefCell::new(Lane::NoLane),
+            is_running_insertion_effect: Cell::new(false),
+            local_id_counter: Cell::new(0),
+            mount_dispatcher: RefCell::new(None),
+            update_dispatcher: RefCell::new(None),
+        }
+    }
+
+    fn dispatcher(&self, is_update: bool) -> JsValue {
+        let cached = if is_update {
+            &self.update_dispatcher
+        } else {
+            &self.mount_dispatcher
+        };
+        if let Some(dispatcher) = cached.borrow().as_ref() {
+            return dispatcher.clone();
+        }
+        let dispatcher = create_dispatcher(&self.root_state, is_update);
+        *cached.borrow_mut() = Some(dispatcher.clone());
+        dispatcher
+    }
+}
+
+fn upgrade(root_state: &Weak<RootState>) -> Rc<RootState> {
+    root_state
+        .upgrade()
+        .expect("The root of the hooks has been dropped")
+}
+
+fn currently_rendering_fiber(state: &RootState) -> Rc<RefCell<FiberNode>> {
+    state
+        .hooks
+        .currently_rendering_fiber
+        .borrow()
+        .clone()
+        .expect("currently rendering fiber is empty")
+}
+
+fn render_lane(state: &RootState) -> Lane {
+    state.hooks.render_lane.borrow().clone()
+}
+
+pub fn set_is_running_insertion_effect(state: &RootState, is_running: bool) {
+    state.hooks.is_running_insertion_effect.set(is_running);
+}
 
 #[derive(Debug, Clone)]
 pub struct Effect {
@@ -92,92 +167,172 @@ pub fn bailout_hook(wip: Rc<RefCell<FiberNode>>, render_lane: Lane) {
     let update_queue = { current.borrow().update_queue.clone() };
     let lanes = { current.borrow().lanes.clone() };
     wip.borrow_mut().update_queue = update_queue;
-    wip.borrow_mut().flags -= Flags::PassiveEffect;
+    wip.borrow_mut().flags -= Flags::PassiveEffect | Flags::Update;
     current.borrow_mut().lanes = remove_lanes(lanes, render_lane);
 }
 
-fn update_hooks_to_dispatcher(is_update: bool) {
+// Wraps the hook into a function of the dispatcher, which calls it with the state of the root
+macro_rules! hook_function {
+    ($root_state:expr, $hook:expr, |$($arg:ident: $ty:ty),*| -> $ret:ty) => {{
+        let root_state = $root_state.clone();
+        let hook = $hook;
+        let closure = Closure::wrap(Box::new(move |$($arg: $ty),*| {
+            hook(&upgrade(&root_state) $(, $arg)*)
+        }) as Box<dyn Fn($($ty),*) -> $ret>);
+        let function = closure.as_ref().unchecked_ref::<Function>().clone();
+        closure.forget();
+        function
+    }};
+}
+
+fn create_dispatcher(root_state: &Weak<RootState>, is_update: bool) -> JsValue {
     let object = Object::new();
 
     // use_state
-    let use_state_closure =
-        Closure::wrap(Box::new(if is_update { update_state } else { mount_state })
-            as Box<dyn Fn(&JsValue) -> Result<Vec<JsValue>, JsValue>>);
-    let use_state = use_state_closure
-        .as_ref()
-        .unchecked_ref::<Function>()
-        .clone();
-    use_state_closure.forget();
+    let use_state = hook_function!(
+        root_state,
+        if is_update { update_state } else { mount_state },
+        |initial_state: &JsValue| -> Result<Vec<JsValue>, JsValue>
+    );
+
+    // use_reducer
+    let use_reducer = hook_function!(
+        root_state,
+        if is_update {
+            update_reducer
+        } else {
+            mount_reducer
+        },
+        |reducer: Function, initial_arg: JsValue, init: JsValue| -> Result<Vec<JsValue>, JsValue>
+    );
 
     // use_effect
-    let use_effect_closure = Closure::wrap(Box::new(if is_update {
-        update_effect
-    } else {
-        mount_effect
-    }) as Box<dyn Fn(Function, JsValue)>);
-    let use_effect = use_effect_closure
-        .as_ref()
-        .unchecked_ref::<Function>()
-        .clone();
-    use_effect_closure.forget();
+    let use_effect = hook_function!(
+        root_state,
+        if is_update {
+            update_effect
+        } else {
+            mount_effect
+        },
+        |create: Function, deps: JsValue| -> ()
+    );
+
+    // use_layout_effect
+    let use_layout_effect = hook_function!(
+        root_state,
+        if is_update {
+            update_layout_effect
+        } else {
+            mount_layout_effect
+        },
+        |create: Function, deps: JsValue| -> ()
+    );
+
+    // use_insertion_effect
+    let use_insertion_effect = hook_function!(
+        root_state,
+        if is_update {
+            update_insertion_effect
+        } else {
+            mount_insertion_effect
+        },
+        |create: Function, deps: JsValue| -> ()
+    );
 
     // use_ref
-    let use_ref_closure = Closure::wrap(Box::new(if is_update { update_ref } else { mount_ref })
-        as Box<dyn Fn(&JsValue) -> JsValue>);
-    let use_ref = use_ref_closure.as_ref().unchecked_ref::<Function>().clone();
-    use_ref_closure.forget();
+    let use_ref = hook_function!(
+        root_state,
+        if is_update { update_ref } else { mount_ref },
+        |initial_value: &JsValue| -> JsValue
+    );
 
     // use_memo
-    let use_memo_closure =
-        Closure::wrap(Box::new(if is_update { update_memo } else { mount_memo })
-            as Box<dyn Fn(Function, JsValue) -> Result<JsValue, JsValue>>);
-    let use_memo = use_memo_closure
-        .as_ref()
-        .unchecked_ref::<Function>()
-        .clone();
-    use_memo_closure.forget();
+    let use_memo = hook_function!(
+        root_state,
+        if is_update { update_memo } else { mount_memo },
+        |create: Function, deps: JsValue| -> Result<JsValue, JsValue>
+    );
 
     // use_callback
-    let use_callback_closure = Closure::wrap(Box::new(if is_update {
-        update_callback
-    } else {
-        mount_callback
-    }) as Box<dyn Fn(Function, JsValue) -> JsValue>);
-    let use_callback = use_callback_closure
-        .as_ref()
-        .unchecked_ref::<Function>()
-        .clone();
-    use_callback_closure.forget();
+    let use_callback = hook_function!(
+        root_state,
+        if is_update {
+            update_callback
+        } else {
+            mount_callback
+        },
+        |callback: Function, deps: JsValue| -> JsValue
+    );
 
     // use_context
-    let use_context_closure =
-        Closure::wrap(Box::new(read_context) as Box<dyn Fn(JsValue) -> JsValue>);
-    let use_context = use_context_closure
-        .as_ref()
-        .unchecked_ref::<Function>()
-        .clone();
-    use_context_closure.forget();
+    let use_context = hook_function!(root_state, read_context, |context: JsValue| -> JsValue);
 
     // use_transition
-    let use_transition_closure = Closure::wrap(Box::new(if is_update {
-        update_transition
-    } else {
-        mount_transition
-    }) as Box<dyn Fn() -> Vec<JsValue>>);
-    let use_transition = use_transition_closure
-        .as_ref()
-        .unchecked_ref::<Function>()
-        .clone();
-    use_transition_closure.forget();
+    let use_transition = hook_function!(
+        root_state,
+        if is_update {
+            update_transition
+        } else {
+            mount_transition
+        },
+        | | -> Vec<JsValue>
+    );
+
+    // use_imperative_handle
+    let use_imperative_handle = hook_function!(
+        root_state,
+        if is_update {
+            update_imperative_handle
+        } else {
+            mount_imperative_handle
+        },
+        |_ref: JsValue, create: Function, deps: JsValue| -> ()
+    );
+
+    // use_sync_external_store
+    let use_sync_external_store = hook_function!(
+        root_state,
+        if is_update {
+            update_sync_external_store
+        } else {
+            mount_sync_external_store
+        },
+        |subscribe: Function, get_snapshot: Function, get_server_snapshot: JsValue| -> Result<JsValue, JsValue>
+    );
+
+    // use_deferred_value
+    let use_deferred_value = hook_function!(
+        root_state,
+        if is_update {
+            update_deferred_value
+        } else {
+            mount_deferred_value
+        },
+        |value: JsValue, initial_value: JsValue| -> JsValue
+    );
+
+    // use_id
+    let use_id = hook_function!(
+        root_state,
+        if is_update { update_id } else { mount_id },
+        | | -> JsValue
+    );
 
     // use
-    let use_closure =
-        Closure::wrap(Box::new(_use) as Box<dyn Fn(JsValue) -> Result<JsValue, JsValue>>);
-    let use_fn = use_closure.as_ref().unchecked_ref::<Function>().clone();
-    use_closure.forget();
+    let use_fn = hook_function!(root_state, _use, |usable: JsValue| -> Result<JsValue, JsValue>);
 
     Reflect::set(&object, &"use_state".into(), &use_state).expect("TODO: panic set use_state");
+    Reflect::set(&object, &"use_reducer".into(), &use_reducer)
+        .expect("TODO: panic set use_reducer");
     Reflect::set(&object, &"use_effect".into(), &use_effect).expect("TODO: panic set use_effect");
+    Reflect::set(&object, &"use_layout_effect".into(), &use_layout_effect)
+        .expect("TODO: panic set use_layout_effect");
+    Reflect::set(
+        &object,
+        &"use_insertion_effect".into(),
+        &use_insertion_effect,
+    )
+    .expect("TODO: panic set use_insertion_effect");
     Reflect::set(&object, &"use_ref".into(), &use_ref).expect("TODO: panic set use_ref");
     Reflect::set(&object, &"use_memo".into(), &use_memo).expect("TODO: panic set use_memo");
     Reflect::set(&object, &"use_callback".into(), &use_callback)
@@ -186,33 +341,49 @@ fn update_hooks_to_dispatcher(is_update: bool) {
         .expect("TODO: panic set use_context");
     Reflect::set(&object, &"use_transition".into(), &use_transition)
         .expect("TODO: panic set use_transition");
+    Reflect::set(&object, &"use_id".into(), &use_id).expect("TODO: panic set use_id");
+    Reflect::set(
+        &object,
+        &"use_sync_external_store".into(),
+        &use_sync_external_store,
+    )
+    .expect("TODO: panic set use_sync_external_store");
+    Reflect::set(
+        &object,
+        &"use_imperative_handle".into(),
+        &use_imperative_handle,
+    )
+    .expect("TODO: panic set use_imperative_handle");
+    Reflect::set(&object, &"use_deferred_value".into(), &use_deferred_value)
+        .expect("TODO: panic set use_deferred_value");
     Reflect::set(&object, &"use".into(), &use_fn).expect("TODO: panic set use");
 
-    updateDispatcher(&object.into());
+    object.into()
 }
 
 pub fn render_with_hooks(
+    state: &RootState,
     work_in_progress: Rc<RefCell<FiberNode>>,
     Component: JsValue,
+    second_arg: JsValue,
     lane: Lane,
 ) -> Result<JsValue, JsValue> {
-    unsafe {
-        CURRENTLY_RENDERING_FIBER = Some(work_in_progress.clone());
-        RENDER_LANE = lane;
+    {
+        let hooks = &state.hooks;
+        *hooks.currently_rendering_fiber.borrow_mut() = Some(work_in_progress.clone());
+        *hooks.render_lane.borrow_mut() = lane;
+        hooks.local_id_counter.set(0);
     }
 
     let work_in_progress_cloned = work_in_progress.clone();
     {
         work_in_progress_cloned.borrow_mut().memoized_state = None;
         work_in_progress_cloned.borrow_mut().update_queue = None;
+        work_in_progress_cloned.borrow_mut().flags -= Flags::StoreConsistency;
     }
 
     let current = work_in_progress_cloned.borrow().alternate.clone();
-    if current.is_some() {
-        update_hooks_to_dispatcher(true);
-    } else {
-        update_hooks_to_dispatcher(false);
-    }
+    updateDispatcher(&state.hooks.dispatcher(current.is_some()));
 
     let props;
     {
@@ -221,132 +392,115 @@ pub fn render_with_hooks(
     }
 
     let component = JsValue::dyn_ref::<Function>(&Component).unwrap();
-    let children = component.call1(&JsValue::null(), &props);
+    // The second argument is the ref for the render function of forwardRef
+    let children = component.call2(&JsValue::null(), &props, &second_arg);
 
-    unsafe {
-        CURRENTLY_RENDERING_FIBER = None;
-        WORK_IN_PROGRESS_HOOK = None;
-        CURRENT_HOOK = None;
-        RENDER_LANE = Lane::NoLane;
+    {
+        let hooks = &state.hooks;
+        *hooks.currently_rendering_fiber.borrow_mut() = None;
+        *hooks.work_in_progress_hook.borrow_mut() = None;
+        *hooks.current_hook.borrow_mut() = None;
+        *hooks.render_lane.borrow_mut() = Lane::NoLane;
     }
 
     children
 }
 
-fn mount_work_in_progress_hook() -> Option<Rc<RefCell<Hook>>> {
+// Whether the tree id is used by useId in the last rendered component
+pub fn check_did_render_id_hook(state: &RootState) -> bool {
+    let did_render_id_hook = state.hooks.local_id_counter.get() != 0;
+    state.hooks.local_id_counter.set(0);
+    did_render_id_hook
+}
+
+fn mount_work_in_progress_hook(state: &RootState) -> Option<Rc<RefCell<Hook>>> {
+    let hooks = &state.hooks;
     let hook = Rc::new(RefCell::new(Hook::new(None, None, None, None, None)));
-    unsafe {
-        if WORK_IN_PROGRESS_HOOK.is_none() {
-            if CURRENTLY_RENDERING_FIBER.is_none() {
-                log!("WORK_IN_PROGRESS_HOOK and CURRENTLY_RENDERING_FIBER is empty")
-            } else {
-                CURRENTLY_RENDERING_FIBER
-                    .as_ref()
-                    .unwrap()
-                    .clone()
-                    .borrow_mut()
-                    .memoized_state = Some(MemoizedState::Hook(hook.clone()));
-                WORK_IN_PROGRESS_HOOK = Some(hook.clone());
+    let work_in_progress_hook = { hooks.work_in_progress_hook.borrow().clone() };
+    match work_in_progress_hook {
+        None => {
+            let fiber = { hooks.currently_rendering_fiber.borrow().clone() };
+            match fiber {
+                None => log!("WORK_IN_PROGRESS_HOOK and CURRENTLY_RENDERING_FIBER is empty"),
+                Some(fiber) => {
+                    fiber.borrow_mut().memoized_state = Some(MemoizedState::Hook(hook.clone()));
+                    *hooks.work_in_progress_hook.borrow_mut() = Some(hook);
+                }
             }
-        } else {
-            WORK_IN_PROGRESS_HOOK
-                .as_ref()
-                .unwrap()
-                .clone()
-                .borrow_mut()
-                .next = Some(hook.clone());
-            WORK_IN_PROGRESS_HOOK = Some(hook.clone());
         }
-        WORK_IN_PROGRESS_HOOK.clone()
+        Some(work_in_progress_hook) => {
+            work_in_progress_hook.borrow_mut().next = Some(hook.clone());
+            *hooks.work_in_progress_hook.borrow_mut() = Some(hook);
+        }
     }
+    hooks.work_in_progress_hook.borrow().clone()
 }
 
-fn update_work_in_progress_hook() -> Option<Rc<RefCell<Hook>>> {
+fn update_work_in_progress_hook(state: &RootState) -> Option<Rc<RefCell<Hook>>> {
     // case1: Update triggered by interaction, the wip_hook is none, use hook in current_hook to clone wip_hook
     // case2: Update triggered in render process, the wip_hook exists
-    let mut next_current_hook: Option<Rc<RefCell<Hook>>> = None;
-    let mut next_work_in_progress_hook: Option<Rc<RefCell<Hook>>> = None;
-
-    unsafe {
-        next_current_hook = match &CURRENT_HOOK {
-            None => {
-                let current = CURRENTLY_RENDERING_FIBER
-                    .as_ref()
-                    .unwrap()
-                    .clone()
-                    .borrow()
-                    .alternate
-                    .clone();
-
-                match current {
-                    None => None,
-                    Some(current) => match current.clone().borrow().memoized_state.clone() {
-                        Some(MemoizedState::Hook(memoized_state)) => Some(memoized_state.clone()),
-                        _ => None,
-                    },
-                }
-            }
-            Some(current_hook) => current_hook.clone().borrow().next.clone(),
-        };
-
-        next_work_in_progress_hook = match &WORK_IN_PROGRESS_HOOK {
-            None => match CURRENTLY_RENDERING_FIBER.clone() {
-                Some(current) => match current.clone().borrow().memoized_state.clone() {
-                    Some(MemoizedState::Hook(memoized_state)) => Some(memoized_state.clone()),
+    let hooks = &state.hooks;
+    let fiber = currently_rendering_fiber(state);
+
+    let current_hook = { hooks.current_hook.borrow().clone() };
+    let next_current_hook = match current_hook {
+        None => {
+            let current = { fiber.borrow().alternate.clone() };
+            match current {
+                None => None,
+                Some(current) => match current.borrow().memoized_state.clone() {
+                    Some(MemoizedState::Hook(memoized_state)) => Some(memoized_state),
                     _ => None,
                 },
-                _ => None,
-            },
-            Some(work_in_progress_hook) => work_in_progress_hook.clone().borrow().next.clone(),
-        };
-
-        if next_work_in_progress_hook.is_some() {
-            WORK_IN_PROGRESS_HOOK = next_work_in_progress_hook.clone();
-            CURRENT_HOOK = next_current_hook.clone();
-        } else {
-            if next_current_hook.is_none() {
-                log!(
-                    "{:?} hooks is more than last",
-                    CURRENTLY_RENDERING_FIBER
-                        .as_ref()
-                        .unwrap()
-                        .clone()
-                        .borrow()
-                        ._type
-                );
             }
+        }
+        Some(current_hook) => current_hook.borrow().next.clone(),
+    };
 
-            CURRENT_HOOK = next_current_hook;
-            let cloned = CURRENT_HOOK.clone().unwrap().clone();
+    let work_in_progress_hook = { hooks.work_in_progress_hook.borrow().clone() };
+    let next_work_in_progress_hook = match &work_in_progress_hook {
+        None => match fiber.borrow().memoized_state.clone() {
+            Some(MemoizedState::Hook(memoized_state)) => Some(memoized_state),
+            _ => None,
+        },
+        Some(work_in_progress_hook) => work_in_progress_hook.borrow().next.clone(),
+    };
+
+    if next_work_in_progress_hook.is_some() {
+        *hooks.work_in_progress_hook.borrow_mut() = next_work_in_progress_hook;
+        *hooks.current_hook.borrow_mut() = next_current_hook;
+    } else {
+        if next_current_hook.is_none() {
+            log!("{:?} hooks is more than last", fiber.borrow()._type);
+        }
+
+        let new_hook = {
+            let cloned = next_current_hook.clone().unwrap();
             let current_hook = cloned.borrow();
-            let new_hook = Rc::new(RefCell::new(Hook::new(
+            Rc::new(RefCell::new(Hook::new(
                 current_hook.memoized_state.clone(),
                 current_hook.update_queue.clone(),
                 current_hook.base_queue.clone(),
                 current_hook.base_state.clone(),
                 None,
-            )));
-
-            if WORK_IN_PROGRESS_HOOK.is_none() {
-                WORK_IN_PROGRESS_HOOK = Some(new_hook.clone());
-                CURRENTLY_RENDERING_FIBER
-                    .as_ref()
-                    .unwrap()
-                    .clone()
-                    .borrow_mut()
-                    .memoized_state = Some(MemoizedState::Hook(new_hook.clone()));
-            } else {
-                let wip_hook = WORK_IN_PROGRESS_HOOK.clone().unwrap();
+            )))
+        };
+        *hooks.current_hook.borrow_mut() = next_current_hook;
+
+        match work_in_progress_hook {
+            None => {
+                fiber.borrow_mut().memoized_state = Some(MemoizedState::Hook(new_hook.clone()));
+            }
+            Some(wip_hook) => {
                 wip_hook.borrow_mut().next = Some(new_hook.clone());
-                WORK_IN_PROGRESS_HOOK = Some(new_hook.clone());
             }
         }
-        WORK_IN_PROGRESS_HOOK.clone()
+        *hooks.work_in_progress_hook.borrow_mut() = Some(new_hook);
     }
+    hooks.work_in_progress_hook.borrow().clone()
 }
 
-fn mount_state(initial_state: &JsValue) -> Result<Vec<JsValue>, JsValue> {
-    let hook = mount_work_in_progress_hook();
+fn mount_state(state: &RootState, initial_state: &JsValue) -> Result<Vec<JsValue>, JsValue> {
     let memoized_state: JsValue;
 
     if initial_state.is_function() {
@@ -357,34 +511,73 @@ fn mount_state(initial_state: &JsValue) -> Result<Vec<JsValue>, JsValue> {
     } else {
         memoized_state = initial_state.clone();
     }
+    mount_state_with_reducer(state, memoized_state, None)
+}
+
+fn mount_reducer(
+    state: &RootState,
+    reducer: Function,
+    initial_arg: JsValue,
+    init: JsValue,
+) -> Result<Vec<JsValue>, JsValue> {
+    let memoized_state = if init.is_function() {
+        init.dyn_ref::<Function>()
+            .unwrap()
+            .call1(&JsValue::null(), &initial_arg)?
+    } else {
+        initial_arg
+    };
+    mount_state_with_reducer(state, memoized_state, Some(reducer))
+}
+
+// useState is useReducer with basic_state_reducer, which is represented by None
+fn mount_state_with_reducer(
+    state: &RootState,
+    memoized_state: JsValue,
+    reducer: Option<Function>,
+) -> Result<Vec<JsValue>, JsValue> {
+    let hook = mount_work_in_progress_hook(state);
     hook.as_ref().unwrap().clone().borrow_mut().memoized_state =
         Some(MemoizedState::MemoizedJsValue(memoized_state.clone()));
     hook.as_ref().unwrap().clone().borrow_mut().base_state =
         Some(MemoizedState::MemoizedJsValue(memoized_state.clone()));
 
-    unsafe {
-        if CURRENTLY_RENDERING_FIBER.is_none() {
-            log!("mount_state, currentlyRenderingFiber is empty");
-        }
-    }
     let queue = create_update_queue();
     hook.as_ref().unwrap().clone().borrow_mut().update_queue = Some(queue.clone());
     let q_rc = Rc::new(queue.clone());
     let q_rc_cloned = q_rc.clone();
-    let fiber = unsafe { CURRENTLY_RENDERING_FIBER.clone().unwrap() };
+    let fiber = currently_rendering_fiber(state);
+    let root_state = state.hooks.root_state.clone();
     let closure = Closure::wrap(Box::new(move |action: &JsValue| {
-        dispatch_set_state(fiber.clone(), (*q_rc_cloned).clone(), action)
-    }) as Box<dyn Fn(&JsValue)>);
+        dispatch_set_state(&root_state, fiber.clone(), (*q_rc_cloned).clone(), action)
+    }) as Box<dyn Fn(&JsValue) -> Result<(), JsValue>>);
     let function: Function = closure.as_ref().unchecked_ref::<Function>().clone();
     closure.forget();
 
     queue.clone().borrow_mut().dispatch = Some(function.clone());
     queue.clone().borrow_mut().last_rendered_state = Some(memoized_state.clone());
+    queue.clone().borrow_mut().last_rendered_reducer = reducer;
     Ok(vec![memoized_state, function.into()])
 }
 
-fn update_state(_: &JsValue) -> Result<Vec<JsValue>, JsValue> {
-    let hook = update_work_in_progress_hook();
+fn update_state(state: &RootState, _: &JsValue) -> Result<Vec<JsValue>, JsValue> {
+    update_state_with_reducer(state, None)
+}
+
+fn update_reducer(
+    state: &RootState,
+    reducer: Function,
+    _initial_arg: JsValue,
+    _init: JsValue,
+) -> Result<Vec<JsValue>, JsValue> {
+    update_state_with_reducer(state, Some(reducer))
+}
+
+fn update_state_with_reducer(
+    state: &RootState,
+    reducer: Option<Function>,
+) -> Result<Vec<JsValue>, JsValue> {
+    let hook = update_work_in_progress_hook(state);
 
     if hook.is_none() {
         panic!("update_state hook is none")
@@ -394,7 +587,11 @@ fn update_state(_: &JsValue) -> Result<Vec<JsValue>, JsValue> {
     let queue = hook_cloned.borrow().update_queue.clone();
     let base_state = hook_cloned.borrow().base_state.clone();
 
-    let mut base_queue = unsafe { CURRENT_HOOK.clone().unwrap().borrow().base_queue.clone() };
+    // The eager state of the following dispatches is computed by the latest reducer
+    queue.clone().unwrap().borrow_mut().last_rendered_reducer = reducer.clone();
+
+    let current_hook = { state.hooks.current_hook.borrow().clone().unwrap() };
+    let mut base_queue = current_hook.borrow().base_queue.clone();
     let pending = queue.clone().unwrap().borrow().shared.pending.clone();
 
     if pending.is_some() {
@@ -417,7 +614,7 @@ fn update_state(_: &JsValue) -> Result<Vec<JsValue>, JsValue> {
         }
         // pending保存在current中，因为commit阶段不完成，current不会变为wip
         // 所以可以保证多次render阶段（只要不进入commit）都能从current恢复pending
-        unsafe { CURRENT_HOOK.clone().unwrap().borrow_mut().base_queue = pending.clone() };
+        current_hook.borrow_mut().base_queue = pending.clone();
         base_queue = pending;
         queue.clone().unwrap().borrow_mut().shared.pending = None;
     }
@@ -432,14 +629,15 @@ fn update_state(_: &JsValue) -> Result<Vec<JsValue>, JsValue> {
         } = process_update_queue(
             base_state.clone(),
             base_queue,
-            unsafe { RENDER_LANE.clone() },
-            Some(|update: Rc<RefCell<Update>>| {
+            render_lane(state),
+            Some(&|update: Rc<RefCell<Update>>| {
                 let skipped_lane = update.borrow().lane.clone();
-                let fiber = unsafe { CURRENTLY_RENDERING_FIBER.clone().unwrap().clone() };
+                let fiber = currently_rendering_fiber(state);
                 let lanes = { fiber.borrow().lanes.clone() };
                 fiber.borrow_mut().lanes = merge_lanes(lanes, skipped_lane);
             }),
-        );
+            reducer,
+        )?;
 
         if !(memoized_state.is_none() && pre_state.is_none()) {
             let memoized_state = memoized_state.clone().unwrap();
@@ -447,7 +645,7 @@ fn update_state(_: &JsValue) -> Result<Vec<JsValue>, JsValue> {
             if let MemoizedState::MemoizedJsValue(ms_value) = memoized_state {
                 if let MemoizedState::MemoizedJsValue(ps_value) = pre_state {
                     if !Object::is(&ms_value, &ps_value) {
-                        mark_wip_received_update();
+                        mark_wip_received_update(state);
                     }
                 }
             }
@@ -490,10 +688,15 @@ pub fn basic_state_reducer(state: &JsValue, action: &JsValue) -> Result<JsValue,
 }
 
 fn dispatch_set_state(
+    root_state: &Weak<RootState>,
     fiber: Rc<RefCell<FiberNode>>,
     update_queue: Rc<RefCell<UpdateQueue>>,
     action: &JsValue,
-) {
+) -> Result<(), JsValue> {
+    if upgrade(root_state).hooks.is_running_insertion_effect.get() {
+        return Err(Error::new("useInsertionEffect must not schedule updates.").into());
+    }
+
     let lane = request_update_lane();
     let mut update = create_update(action.clone(), lane.clone());
     let current = { fiber.borrow().alternate.clone() };
@@ -506,7 +709,12 @@ fn dispatch_set_state(
             panic!("current state is none")
         }
         let current_state = current_state.unwrap();
-        let eager_state = basic_state_reducer(&current_state, &action);
+        let reducer = { update_queue.borrow().last_rendered_reducer.clone() };
+        let eager_state = match reducer.as_ref() {
+            Some(reducer) => reducer.call2(&JsValue::null(), &current_state, &action),
+            None => basic_state_reducer(&current_state, &action),
+        };
+        update.eager_reducer = reducer;
         // if not ok, the update will be handled in render phase, means the error will be handled in render phase
         if eager_state.is_ok() {
             let eager_state = eager_state.unwrap();
@@ -517,16 +725,18 @@ fn dispatch_set_state(
                 if is_dev() {
                     log!("Hit eager state")
                 }
-                return;
+                return Ok(());
             }
         }
     }
 
     enqueue_update(update_queue.clone(), update, fiber.clone(), lane.clone());
     schedule_update_on_fiber(fiber.clone(), lane);
+    Ok(())
 }
 
 fn push_effect(
+    state: &RootState,
     hook_flags: Flags,
     create: Function,
     destroy: JsValue,
@@ -535,7 +745,7 @@ fn push_effect(
     let mut effect = Rc::new(RefCell::new(Effect::new(
         hook_flags, create, destroy, deps, None,
     )));
-    let fiber = unsafe { CURRENTLY_RENDERING_FIBER.clone().unwrap() };
+    let fiber = currently_rendering_fiber(state);
     let update_queue = { fiber.borrow().update_queue.clone() };
     if update_queue.is_none() {
         let update_queue = create_update_queue();
@@ -559,8 +769,110 @@ fn push_effect(
     return effect;
 }
 
-fn mount_effect(create: Function, deps: JsValue) {
-    let hook = mount_work_in_progress_hook();
+fn mount_effect(state: &RootState, create: Function, deps: JsValue) {
+    mount_effect_impl(state, Flags::PassiveEffect, Flags::Passive, create, deps);
+}
+
+fn update_effect(state: &RootState, create: Function, deps: JsValue) {
+    update_effect_impl(state, Flags::PassiveEffect, Flags::Passive, create, deps);
+}
+
+// The destroy of the previous effect is called in the mutation phase,
+// and the create is called in the layout phase
+fn mount_layout_effect(state: &RootState, create: Function, deps: JsValue) {
+    mount_effect_impl(state, Flags::Update, Flags::Layout, create, deps);
+}
+
+fn update_layout_effect(state: &RootState, create: Function, deps: JsValue) {
+    update_effect_impl(state, Flags::Update, Flags::Layout, create, deps);
+}
+
+// The handle is attached to the ref in the layout phase like the refs of host components
+fn mount_imperative_handle(state: &RootState, _ref: JsValue, create: Function, deps: JsValue) {
+    let effect_deps = imperative_handle_deps(&_ref, deps);
+    mount_effect_impl(
+        state,
+        Flags::Update,
+        Flags::Layout,
+        create_imperative_handle_effect(_ref, create),
+        effect_deps,
+    );
+}
+
+fn update_imperative_handle(state: &RootState, _ref: JsValue, create: Function, deps: JsValue) {
+    let effect_deps = imperative_handle_deps(&_ref, deps);
+    update_effect_impl(
+        state,
+        Flags::Update,
+        Flags::Layout,
+        create_imperative_handle_effect(_ref, create),
+        effect_deps,
+    );
+}
+
+fn imperative_handle_deps(_ref: &JsValue, deps: JsValue) -> JsValue {
+    if deps.is_null() || deps.is_undefined() {
+        return JsValue::null();
+    }
+    deps.dyn_ref::<Array>()
+        .unwrap()
+        .concat(&Array::of1(_ref))
+        .into()
+}
+
+fn create_imperative_handle_effect(_ref: JsValue, create: Function) -> Function {
+    let closure = Closure::wrap(Box::new(move || -> Result<JsValue, JsValue> {
+        if _ref.is_null() || _ref.is_undefined() {
+            return Ok(JsValue::undefined());
+        }
+        let inst = create.call0(&JsValue::null())?;
+        let _ref = _ref.clone();
+        if let Some(ref_fn) = _ref.dyn_ref::<Function>() {
+            let ref_cleanup = ref_fn.call1(&JsValue::null(), &inst)?;
+            let ref_fn = ref_fn.clone();
+            let destroy = Closure::wrap(Box::new(move || -> Result<JsValue, JsValue> {
+                match ref_cleanup.dyn_ref::<Function>() {
+                    Some(ref_cleanup) => ref_cleanup.call0(&JsValue::null()),
+                    None => ref_fn.call1(&JsValue::null(), &JsValue::null()),
+                }
+            })
+                as Box<dyn Fn() -> Result<JsValue, JsValue>>);
+            let destroy_fn = destroy.as_ref().unchecked_ref::<Function>().clone();
+            destroy.forget();
+            return Ok(destroy_fn.into());
+        }
+        Reflect::set(&_ref, &"current".into(), &inst).expect("TODO: panic set current");
+        let destroy = Closure::wrap(Box::new(move || {
+            Reflect::set(&_ref, &"current".into(), &JsValue::null())
+                .expect("TODO: panic set current");
+        }) as Box<dyn Fn()>);
+        let destroy_fn = destroy.as_ref().unchecked_ref::<Function>().clone();
+        destroy.forget();
+        Ok(destroy_fn.into())
+    }) as Box<dyn Fn() -> Result<JsValue, JsValue>>);
+    let function = closure.as_ref().unchecked_ref::<Function>().clone();
+    closure.forget();
+    function
+}
+
+// Fired in the mutation phase before the destroys of useLayoutEffect,
+// so the styles can be injected before the layout effects read the DOM
+fn mount_insertion_effect(state: &RootState, create: Function, deps: JsValue) {
+    mount_effect_impl(state, Flags::Update, Flags::Insertion, create, deps);
+}
+
+fn update_insertion_effect(state: &RootState, create: Function, deps: JsValue) {
+    update_effect_impl(state, Flags::Update, Flags::Insertion, create, deps);
+}
+
+fn mount_effect_impl(
+    state: &RootState,
+    fiber_flags: Flags,
+    hook_flags: Flags,
+    create: Function,
+    deps: JsValue,
+) {
+    let hook = mount_work_in_progress_hook(state);
     let next_deps = if deps.is_undefined() {
         JsValue::null()
     } else {
@@ -569,19 +881,26 @@ fn mount_effect(create: Function, deps: JsValue) {
 
     // 注意区分PassiveEffect与Passive，PassiveEffect是针对fiber.flags
     // Passive是effect类型，代表useEffect。类似的，Layout代表useLayoutEffect
-    let currently_rendering_fiber = unsafe { CURRENTLY_RENDERING_FIBER.clone().unwrap() };
-    currently_rendering_fiber.borrow_mut().flags |= Flags::PassiveEffect;
+    let currently_rendering_fiber = currently_rendering_fiber(state);
+    currently_rendering_fiber.borrow_mut().flags |= fiber_flags;
     hook.as_ref().unwrap().clone().borrow_mut().memoized_state =
         Some(MemoizedState::Effect(push_effect(
-            Flags::Passive | Flags::HookHasEffect,
+            state,
+            hook_flags | Flags::HookHasEffect,
             create,
             JsValue::null(),
             next_deps,
         )));
 }
 
-fn update_effect(create: Function, deps: JsValue) {
-    let hook = update_work_in_progress_hook();
+fn update_effect_impl(
+    state: &RootState,
+    fiber_flags: Flags,
+    hook_flags: Flags,
+    create: Function,
+    deps: JsValue,
+) {
+    let hook = update_work_in_progress_hook(state);
     let next_deps = if deps.is_undefined() {
         JsValue::null()
     } else {
@@ -589,45 +908,37 @@ fn update_effect(create: Function, deps: JsValue) {
     };
 
     let mut destroy = JsValue::null();
-    unsafe {
-        if CURRENT_HOOK.is_some() {
-            let current_hook = CURRENT_HOOK.clone().unwrap();
-            let prev_effect = current_hook.borrow().memoized_state.clone();
-            if let MemoizedState::Effect(prev_effect) = prev_effect.unwrap() {
-                destroy = prev_effect.borrow().destroy.clone();
-                if !next_deps.is_null() {
-                    let prev_deps = prev_effect.borrow().deps.clone();
-
-                    if are_hook_inputs_equal(&prev_deps, &next_deps) {
-                        hook.as_ref().unwrap().borrow_mut().memoized_state =
-                            Some(MemoizedState::Effect(push_effect(
-                                Flags::Passive,
-                                create,
-                                destroy,
-                                next_deps,
-                            )));
-                        return;
-                    }
+    let current_hook = { state.hooks.current_hook.borrow().clone() };
+    if let Some(current_hook) = current_hook {
+        let prev_effect = current_hook.borrow().memoized_state.clone();
+        if let MemoizedState::Effect(prev_effect) = prev_effect.unwrap() {
+            destroy = prev_effect.borrow().destroy.clone();
+            if !next_deps.is_null() {
+                let prev_deps = prev_effect.borrow().deps.clone();
+
+                if are_hook_inputs_equal(&prev_deps, &next_deps) {
+                    hook.as_ref().unwrap().borrow_mut().memoized_state =
+                        Some(MemoizedState::Effect(push_effect(
+                            state, hook_flags, create, destroy, next_deps,
+                        )));
+                    return;
                 }
-            } else {
-                panic!("memoized_state is not Effect")
             }
+        } else {
+            panic!("memoized_state is not Effect")
         }
+    }
 
-        CURRENTLY_RENDERING_FIBER
-            .as_ref()
-            .unwrap()
-            .borrow_mut()
-            .flags |= Flags::PassiveEffect;
+    currently_rendering_fiber(state).borrow_mut().flags |= fiber_flags;
 
-        hook.as_ref().unwrap().clone().borrow_mut().memoized_state =
-            Some(MemoizedState::Effect(push_effect(
-                Flags::Passive | Flags::HookHasEffect,
-                create,
-                destroy.clone(),
-                next_deps,
-            )));
-    }
+    hook.as_ref().unwrap().clone().borrow_mut().memoized_state =
+        Some(MemoizedState::Effect(push_effect(
+            state,
+            hook_flags | Flags::HookHasEffect,
+            create,
+            destroy.clone(),
+            next_deps,
+        )));
 }
 
 fn are_hook_inputs_equal(next_deps: &JsValue, pre_deps: &JsValue) -> bool {
@@ -649,8 +960,8 @@ fn are_hook_inputs_equal(next_deps: &JsValue, pre_deps: &JsValue) -> bool {
     return true;
 }
 
-fn mount_ref(initial_value: &JsValue) -> JsValue {
-    let hook = mount_work_in_progress_hook();
+fn mount_ref(state: &RootState, initial_value: &JsValue) -> JsValue {
+    let hook = mount_work_in_progress_hook(state);
     let ref_obj: Object = Object::new();
     Reflect::set(&ref_obj, &"current".into(), initial_value);
     hook.as_ref().unwrap().borrow_mut().memoized_state =
@@ -658,16 +969,311 @@ fn mount_ref(initial_value: &JsValue) -> JsValue {
     ref_obj.into()
 }
 
-fn update_ref(initial_value: &JsValue) -> JsValue {
-    let hook = update_work_in_progress_hook();
+fn update_ref(state: &RootState, initial_value: &JsValue) -> JsValue {
+    let hook = update_work_in_progress_hook(state);
     match hook.unwrap().borrow_mut().memoized_state.clone() {
         Some(MemoizedState::MemoizedJsValue(value)) => value,
         _ => panic!("ref is none"),
     }
 }
 
-fn mount_memo(create: Function, deps: JsValue) -> Result<JsValue, JsValue> {
-    let hook = mount_work_in_progress_hook();
+fn get_identifier_prefix(fiber: Rc<RefCell<FiberNode>>) -> String {
+    let mut node = Some(fiber);
+    while let Some(fiber) = node {
+        if let Some(state_node) = fiber.borrow().state_node.clone() {
+            if let StateNode::FiberRootNode(root) = &*state_node {
+                return root.borrow().identifier_prefix.clone();
+            }
+        }
+        node = fiber.borrow()._return.clone();
+    }
+    String::new()
+}
+
+fn mount_id(state: &RootState) -> JsValue {
+    let hook = mount_work_in_progress_hook(state);
+    let fiber = currently_rendering_fiber(state);
+    let identifier_prefix = get_identifier_prefix(fiber);
+
+    let id = if is_tree_id_enabled(state) {
+        let mut id = format!(":{}R{}", identifier_prefix, get_tree_id(state));
+        let local_id = state.hooks.local_id_counter.get();
+        state.hooks.local_id_counter.set(local_id + 1);
+        if local_id > 0 {
+            id = format!("{}H{}", id, to_base_32(local_id));
+        }
+        id + ":"
+    } else {
+        let global_client_id = GLOBAL_CLIENT_ID_COUNTER.with(|counter| {
+            let global_client_id = counter.get();
+            counter.set(global_client_id + 1);
+            global_client_id
+        });
+        format!(":{}r{}:", identifier_prefix, to_base_32(global_client_id))
+    };
+
+    let id: JsValue = id.into();
+    hook.as_ref().unwrap().borrow_mut().memoized_state =
+        Some(MemoizedState::MemoizedJsValue(id.clone()));
+    id
+}
+
+fn update_id(state: &RootState) -> JsValue {
+    let hook = update_work_in_progress_hook(state);
+    match hook.unwrap().borrow().memoized_state.clone() {
+        Some(MemoizedState::MemoizedJsValue(id)) => id,
+        _ => panic!("id is none"),
+    }
+}
+
+// The snapshot may be changed between the render and the subscription, or by the other
+// components during a concurrent render. getServerSnapshot is ignored without hydration
+fn mount_sync_external_store(
+    state: &RootState,
+    subscribe: Function,
+    get_snapshot: Function,
+    _get_server_snapshot: JsValue,
+) -> Result<JsValue, JsValue> {
+    let hook = mount_work_in_progress_hook(state);
+    let fiber = currently_rendering_fiber(state);
+    let next_snapshot = get_snapshot.call0(&JsValue::null())?;
+
+    if render_lane(state) != Lane::SyncLane {
+        push_store_consistency_check(fiber.clone(), get_snapshot.clone(), next_snapshot.clone());
+    }
+
+    let inst = Object::new();
+    Reflect::set(&inst, &"value".into(), &next_snapshot).expect("TODO: panic set value");
+    Reflect::set(&inst, &"getSnapshot".into(), &get_snapshot).expect("TODO: panic set getSnapshot");
+    let inst: JsValue = inst.into();
+    {
+        let mut hook = hook.as_ref().unwrap().borrow_mut();
+        hook.memoized_state = Some(MemoizedState::MemoizedJsValue(next_snapshot.clone()));
+        // The instance read by the subscription
+        hook.base_state = Some(MemoizedState::MemoizedJsValue(inst.clone()));
+    }
+
+    let subscribe_array: JsValue = Array::of1(&subscribe).into();
+    mount_effect(
+        state,
+        create_subscribe_to_store(fiber.clone(), inst.clone(), subscribe),
+        subscribe_array,
+    );
+
+    fiber.borrow_mut().flags |= Flags::PassiveEffect;
+    push_effect(
+        state,
+        Flags::Passive | Flags::HookHasEffect,
+        create_update_store_instance(fiber, inst, next_snapshot.clone(), get_snapshot),
+        JsValue::null(),
+        JsValue::null(),
+    );
+    Ok(next_snapshot)
+}
+
+fn update_sync_external_store(
+    state: &RootState,
+    subscribe: Function,
+    get_snapshot: Function,
+    _get_server_snapshot: JsValue,
+) -> Result<JsValue, JsValue> {
+    let hook = update_work_in_progress_hook(state).unwrap();
+    let fiber = currently_rendering_fiber(state);
+    let next_snapshot = get_snapshot.call0(&JsValue::null())?;
+
+    let prev_snapshot = hook
+        .borrow()
+        .memoized_state
+        .clone()
+        .unwrap()
+        .js_value()
+        .unwrap();
+    let snapshot_changed = !Object::is(&prev_snapshot, &next_snapshot);
+    if snapshot_changed {
+        hook.borrow_mut().memoized_state =
+            Some(MemoizedState::MemoizedJsValue(next_snapshot.clone()));
+        mark_wip_received_update(state);
+    }
+    let inst = hook
+        .borrow()
+        .base_state
+        .clone()
+        .unwrap()
+        .js_value()
+        .unwrap();
+
+    let subscribe_array: JsValue = Array::of1(&subscribe).into();
+    update_effect(
+        state,
+        create_subscribe_to_store(fiber.clone(), inst.clone(), subscribe),
+        subscribe_array,
+    );
+
+    let prev_get_snapshot = derive_from_js_value(&inst, "getSnapshot");
+    if !Object::is(&prev_get_snapshot, &get_snapshot) || snapshot_changed {
+        fiber.borrow_mut().flags |= Flags::PassiveEffect;
+        push_effect(
+            state,
+            Flags::Passive | Flags::HookHasEffect,
+            create_update_store_instance(
+                fiber.clone(),
+                inst,
+                next_snapshot.clone(),
+                get_snapshot.clone(),
+            ),
+            JsValue::null(),
+            JsValue::null(),
+        );
+    }
+
+    if render_lane(state) != Lane::SyncLane {
+        push_store_consistency_check(fiber, get_snapshot, next_snapshot.clone());
+    }
+    Ok(next_snapshot)
+}
+
+fn push_store_consistency_check(
+    fiber: Rc<RefCell<FiberNode>>,
+    get_snapshot: Function,
+    rendered_snapshot: JsValue,
+) {
+    fiber.borrow_mut().flags |= Flags::StoreConsistency;
+    let update_queue = { fiber.borrow().update_queue.clone() };
+    let update_queue = match update_queue {
+        Some(update_queue) => update_queue,
+        None => {
+            let update_queue = create_update_queue();
+            fiber.borrow_mut().update_queue = Some(update_queue.clone());
+            update_queue
+        }
+    };
+    update_queue
+        .borrow_mut()
+        .stores
+        .push((get_snapshot, rendered_snapshot));
+}
+
+fn create_subscribe_to_store(
+    fiber: Rc<RefCell<FiberNode>>,
+    inst: JsValue,
+    subscribe: Function,
+) -> Function {
+    let closure = Closure::wrap(Box::new(move || -> Result<JsValue, JsValue> {
+        let fiber = fiber.clone();
+        let inst = inst.clone();
+        let handle_store_change = Closure::wrap(Box::new(move || {
+            if check_if_snapshot_changed(&inst) {
+                force_store_rerender(fiber.clone());
+            }
+        }) as Box<dyn Fn()>);
+        let handle_store_change_fn = handle_store_change
+            .as_ref()
+            .unchecked_ref::<Function>()
+            .clone();
+        handle_store_change.forget();
+        // The unsubscribe is the destroy of the effect
+        subscribe.call1(&JsValue::null(), &handle_store_change_fn)
+    }) as Box<dyn Fn() -> Result<JsValue, JsValue>>);
+    let function = closure.as_ref().unchecked_ref::<Function>().clone();
+    closure.forget();
+    function
+}
+
+fn create_update_store_instance(
+    fiber: Rc<RefCell<FiberNode>>,
+    inst: JsValue,
+    next_snapshot: JsValue,
+    get_snapshot: Function,
+) -> Function {
+    let closure = Closure::wrap(Box::new(move || {
+        Reflect::set(&inst, &"value".into(), &next_snapshot).expect("TODO: panic set value");
+        Reflect::set(&inst, &"getSnapshot".into(), &get_snapshot)
+            .expect("TODO: panic set getSnapshot");
+        // The store may be changed between the render and the commit
+        if check_if_snapshot_changed(&inst) {
+            force_store_rerender(fiber.clone());
+        }
+    }) as Box<dyn Fn()>);
+    let function = closure.as_ref().unchecked_ref::<Function>().clone();
+    closure.forget();
+    function
+}
+
+fn check_if_snapshot_changed(inst: &JsValue) -> bool {
+    let latest_get_snapshot = derive_from_js_value(inst, "getSnapshot");
+    let prev_value = derive_from_js_value(inst, "value");
+    match latest_get_snapshot
+        .dyn_ref::<Function>()
+        .unwrap()
+        .call0(&JsValue::null())
+    {
+        Ok(next_value) => !Object::is(&prev_value, &next_value),
+        // Rerender to throw the error in the render phase
+        Err(_) => true,
+    }
+}
+
+// The updates from the external store can't be time sliced, or the UI may be inconsistent
+fn force_store_rerender(fiber: Rc<RefCell<FiberNode>>) {
+    let lanes = { fiber.borrow().lanes.clone() };
+    fiber.borrow_mut().lanes = merge_lanes(lanes, Lane::SyncLane);
+    let alternate = { fiber.borrow().alternate.clone() };
+    if let Some(alternate) = alternate {
+        let lanes = { alternate.borrow().lanes.clone() };
+        alternate.borrow_mut().lanes = merge_lanes(lanes, Lane::SyncLane);
+    }
+    schedule_update_on_fiber(fiber, Lane::SyncLane);
+}
+
+fn mount_deferred_value(state: &RootState, value: JsValue, initial_value: JsValue) -> JsValue {
+    let hook = mount_work_in_progress_hook(state).unwrap();
+    let is_deferred_render = include_some_lanes(render_lane(state), Lane::TransitionLane);
+    if !initial_value.is_undefined() && !is_deferred_render {
+        // Render the initial value first, the value is rendered in the deferred render
+        hook.borrow_mut().memoized_state =
+            Some(MemoizedState::MemoizedJsValue(initial_value.clone()));
+        schedule_deferred_render(state);
+        return initial_value;
+    }
+    hook.borrow_mut().memoized_state = Some(MemoizedState::MemoizedJsValue(value.clone()));
+    value
+}
+
+fn update_deferred_value(state: &RootState, value: JsValue, _initial_value: JsValue) -> JsValue {
+    let hook = update_work_in_progress_hook(state).unwrap();
+    let prev_value = hook
+        .borrow()
+        .memoized_state
+        .clone()
+        .unwrap()
+        .js_value()
+        .unwrap();
+    if Object::is(&value, &prev_value) {
+        return value;
+    }
+
+    if !includes_only_non_urgent_lanes(render_lane(state)) {
+        // Keep the previous value in the urgent render, and render the new value later
+        schedule_deferred_render(state);
+        return prev_value;
+    }
+
+    hook.borrow_mut().memoized_state = Some(MemoizedState::MemoizedJsValue(value.clone()));
+    mark_wip_received_update(state);
+    value
+}
+
+// The fiber keeps the TransitionLane after the commit, so it doesn't bail out in the
+// deferred render
+fn schedule_deferred_render(state: &RootState) {
+    let fiber = currently_rendering_fiber(state);
+    let lanes = { fiber.borrow().lanes.clone() };
+    fiber.borrow_mut().lanes = merge_lanes(lanes, Lane::TransitionLane);
+    schedule_update_on_fiber(fiber, Lane::TransitionLane);
+}
+
+fn mount_memo(state: &RootState, create: Function, deps: JsValue) -> Result<JsValue, JsValue> {
+    let hook = mount_work_in_progress_hook(state);
     let next_deps = if deps.is_undefined() {
         JsValue::null()
     } else {
@@ -682,8 +1288,8 @@ fn mount_memo(create: Function, deps: JsValue) -> Result<JsValue, JsValue> {
     Ok(next_value)
 }
 
-fn update_memo(create: Function, deps: JsValue) -> Result<JsValue, JsValue> {
-    let hook = update_work_in_progress_hook();
+fn update_memo(state: &RootState, create: Function, deps: JsValue) -> Result<JsValue, JsValue> {
+    let hook = update_work_in_progress_hook(state);
     let next_deps = if deps.is_undefined() {
         JsValue::null()
     } else {
@@ -717,8 +1323,8 @@ fn update_memo(create: Function, deps: JsValue) -> Result<JsValue, JsValue> {
     panic!("update_memo, memoized_state is not JsValue");
 }
 
-fn mount_callback(callback: Function, deps: JsValue) -> JsValue {
-    let hook = mount_work_in_progress_hook();
+fn mount_callback(state: &RootState, callback: Function, deps: JsValue) -> JsValue {
+    let hook = mount_work_in_progress_hook(state);
     let next_deps = if deps.is_undefined() {
         JsValue::null()
     } else {
@@ -732,8 +1338,8 @@ fn mount_callback(callback: Function, deps: JsValue) -> JsValue {
     callback.into()
 }
 
-fn update_callback(callback: Function, deps: JsValue) -> JsValue {
-    let hook = update_work_in_progress_hook();
+fn update_callback(state: &RootState, callback: Function, deps: JsValue) -> JsValue {
+    let hook = update_work_in_progress_hook(state);
     let next_deps = if deps.is_undefined() {
         JsValue::null()
     } else {
@@ -765,35 +1371,34 @@ fn update_callback(callback: Function, deps: JsValue) -> JsValue {
     panic!("update_callback, memoized_state is not JsValue");
 }
 
-fn read_context(context: JsValue) -> JsValue {
-    let consumer = unsafe { CURRENTLY_RENDERING_FIBER.clone() };
-    read_context_origin(consumer, context)
+fn read_context(state: &RootState, context: JsValue) -> JsValue {
+    let consumer = { state.hooks.currently_rendering_fiber.borrow().clone() };
+    read_context_origin(state, consumer, context)
 }
 
-fn _use(usable: JsValue) -> Result<JsValue, JsValue> {
+fn _use(state: &RootState, usable: JsValue) -> Result<JsValue, JsValue> {
     if !usable.is_null() && type_of(&usable, "object") {
         if derive_from_js_value(&usable, "then").is_function() {
-            return track_used_thenable(usable);
+            return track_used_thenable(state, usable);
         } else if derive_from_js_value(&usable, "$$typeof") == REACT_CONTEXT_TYPE {
-            return Ok(read_context(usable));
+            return Ok(read_context(state, usable));
         }
     }
     Err(JsValue::from_str("Not supported use arguments"))
 }
 
-pub fn reset_hooks_on_unwind(wip: Rc<RefCell<FiberNode>>) {
-    unsafe {
-        CURRENTLY_RENDERING_FIBER = None;
-        CURRENT_HOOK = None;
-        WORK_IN_PROGRESS_HOOK = None;
-    }
+pub fn reset_hooks_on_unwind(state: &RootState, wip: Rc<RefCell<FiberNode>>) {
+    let hooks = &state.hooks;
+    *hooks.currently_rendering_fiber.borrow_mut() = None;
+    *hooks.current_hook.borrow_mut() = None;
+    *hooks.work_in_progress_hook.borrow_mut() = None;
 }
 
-fn mount_transition() -> Vec<JsValue> {
-    let result = mount_state(&JsValue::from(false)).unwrap();
+fn mount_transition(state: &RootState) -> Vec<JsValue> {
+    let result = mount_state(state, &JsValue::from(false)).unwrap();
     let is_pending = result[0].as_bool().unwrap();
     let set_pending = result[1].clone().dyn_into::<Function>().unwrap();
-    let hook = mount_work_in_progress_hook();
+    let hook = mount_work_in_progress_hook(state);
     let set_pending_cloned = set_pending.clone();
     let closure = Closure::wrap(Box::new(move |callback: Function| {
         start_transition(set_pending_cloned.clone(), callback);
@@ -805,10 +1410,10 @@ fn mount_transition() -> Vec<JsValue> {
     vec![JsValue::from_bool(is_pending), start.into()]
 }
 
-fn update_transition() -> Vec<JsValue> {
-    let result = update_state(&JsValue::undefined()).unwrap();
+fn update_transition(state: &RootState) -> Vec<JsValue> {
+    let result = update_state(state, &JsValue::undefined()).unwrap();
     let is_pending = result[0].as_bool().unwrap();
-    let hook = update_work_in_progress_hook();
+    let hook = update_work_in_progress_hook(state);
     if let MemoizedState::MemoizedJsValue(start) = hook
         .as_ref()
         .unwrap()
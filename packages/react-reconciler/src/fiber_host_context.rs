@@ -0,0 +1,48 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::JsValue;
+
+use crate::fiber::{FiberNode, StateNode};
+use crate::root_state::RootState;
+
+pub fn get_host_context(state: &RootState) -> JsValue {
+    state
+        .host_context_stack
+        .borrow()
+        .last()
+        .cloned()
+        .unwrap_or(JsValue::null())
+}
+
+pub fn push_host_container(state: &RootState, work_in_progress: Rc<RefCell<FiberNode>>) {
+    let state_node = { work_in_progress.borrow().state_node.clone() };
+    let container = match state_node.as_deref() {
+        Some(StateNode::FiberRootNode(root)) => root.borrow().container.clone(),
+        Some(StateNode::HostPortal { container, .. }) => container.clone(),
+        _ => panic!("HostRoot should have FiberRootNode"),
+    };
+    let host_context = state.host_config.get_root_host_context(container);
+    state.host_context_stack.borrow_mut().push(host_context);
+}
+
+pub fn pop_host_container(state: &RootState) {
+    state.host_context_stack.borrow_mut().pop();
+}
+
+pub fn push_host_context(state: &RootState, work_in_progress: Rc<RefCell<FiberNode>>) {
+    let _type = { work_in_progress.borrow()._type.as_string().unwrap() };
+    let parent_host_context = get_host_context(state);
+    let host_context = state
+        .host_config
+        .get_child_host_context(&parent_host_context, _type.as_str());
+    state.host_context_stack.borrow_mut().push(host_context);
+}
+
+pub fn pop_host_context(state: &RootState) {
+    state.host_context_stack.borrow_mut().pop();
+}
+
+pub fn reset_host_context(state: &RootState) {
+    state.host_context_stack.borrow_mut().clear();
+}
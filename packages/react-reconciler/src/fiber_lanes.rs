@@ -82,6 +82,11 @@ pub fn include_some_lanes(set: Lane, subset: Lane) -> bool {
     return (set & subset) != Lane::NoLane;
 }
 
+pub fn includes_only_non_urgent_lanes(lanes: Lane) -> bool {
+    let urgent_lanes = Lane::SyncLane | Lane::InputContinuousLane | Lane::DefaultLane;
+    (lanes & urgent_lanes) == Lane::NoLane
+}
+
 pub fn remove_lanes(set: Lane, subset: Lane) -> Lane {
     return set - subset;
 }
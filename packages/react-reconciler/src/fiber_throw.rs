@@ -4,19 +4,121 @@ use std::{
     rc::Rc,
 };
 
-use shared::{derive_from_js_value, type_of};
+use shared::{derive_from_js_value, is_dev, log, type_of};
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
-use web_sys::js_sys::Function;
+use web_sys::js_sys::{Array, Function, Object, Reflect};
 
 use crate::{
     fiber::{FiberNode, FiberRootNode},
+    fiber_class_component::{call_instance_method, enqueue_captured_update, get_class_instance},
     fiber_flags::Flags,
     fiber_lanes::Lane,
+    root_state::RootState,
     suspense_context::get_suspense_handler,
+    update_queue::{create_update, Update, UpdateTag},
     work_loop::{ensure_root_is_scheduled, mark_update_lane_from_fiber_to_root},
+    work_tags::WorkTag,
     JsValueKey,
 };
 
+pub fn reset_root_uncaught_errors(state: &RootState) {
+    state.root_uncaught_errors.borrow_mut().clear();
+}
+
+pub fn take_root_uncaught_errors(state: &RootState) -> Vec<JsValue> {
+    std::mem::take(&mut *state.root_uncaught_errors.borrow_mut())
+}
+
+pub fn report_uncaught_error(error: &JsValue) {
+    web_sys::console::error_2(&"Uncaught error:".into(), error);
+}
+
+fn get_component_name(fiber: Rc<RefCell<FiberNode>>) -> Option<String> {
+    let _type = { fiber.borrow()._type.clone() };
+    if let Some(name) = _type.as_string() {
+        return Some(name);
+    }
+    if !type_of(&_type, "function") {
+        return None;
+    }
+    let display_name = derive_from_js_value(&_type, "displayName");
+    if display_name.is_string() {
+        return display_name.as_string();
+    }
+    derive_from_js_value(&_type, "name")
+        .as_string()
+        .filter(|name| !name.is_empty())
+}
+
+pub fn get_component_stack(fiber: Rc<RefCell<FiberNode>>) -> String {
+    let mut stack = String::new();
+    let mut node = Some(fiber);
+    while let Some(fiber) = node {
+        if let Some(name) = get_component_name(fiber.clone()) {
+            stack.push_str(&format!("\n    in {}", name));
+        }
+        node = fiber.borrow()._return.clone();
+    }
+    stack
+}
+
+pub fn is_error_boundary(fiber: Rc<RefCell<FiberNode>>) -> bool {
+    if fiber.borrow().tag != WorkTag::ClassComponent {
+        return false;
+    }
+    let ctor = { fiber.borrow()._type.clone() };
+    if derive_from_js_value(&ctor, "getDerivedStateFromError").is_function() {
+        return true;
+    }
+    match get_class_instance(fiber) {
+        Some(instance) => derive_from_js_value(&instance, "componentDidCatch").is_function(),
+        None => false,
+    }
+}
+
+// getDerivedStateFromError is called when the update is processed,
+// componentDidCatch is called as the callback of the update in the layout phase
+pub fn create_class_error_update(
+    fiber: Rc<RefCell<FiberNode>>,
+    error: JsValue,
+    component_stack: String,
+    lane: Lane,
+) -> Update {
+    let mut update = create_update(error.clone(), lane);
+    update.tag = UpdateTag::CaptureUpdate;
+
+    let ctor = { fiber.borrow()._type.clone() };
+    if is_dev() && !derive_from_js_value(&ctor, "getDerivedStateFromError").is_function() {
+        log!(
+            "The above error occurred in the {} component{}",
+            get_component_name(fiber.clone()).unwrap_or("Anonymous".to_string()),
+            component_stack
+        );
+    }
+
+    let instance = get_class_instance(fiber);
+    if let Some(instance) = instance {
+        if derive_from_js_value(&instance, "componentDidCatch").is_function() {
+            let callback = Closure::once_into_js(move || -> Result<JsValue, JsValue> {
+                let error_info = Object::new();
+                Reflect::set(
+                    &error_info,
+                    &"componentStack".into(),
+                    &component_stack.into(),
+                )
+                .expect("TODO: panic set componentStack");
+                call_instance_method(
+                    &instance,
+                    "componentDidCatch",
+                    &Array::of2(&error, &error_info),
+                )
+            });
+            update.callback = Some(callback.unchecked_into::<Function>());
+        }
+    }
+    update
+}
+
 fn attach_ping_listener(
     root: Rc<RefCell<FiberRootNode>>,
     source_fiber: Rc<RefCell<FiberNode>>,
@@ -74,6 +176,7 @@ fn attach_ping_listener(
 }
 
 pub fn throw_exception(
+    state: &RootState,
     root: Rc<RefCell<FiberRootNode>>,
     source_fiber: Rc<RefCell<FiberNode>>,
     value: JsValue,
@@ -83,12 +186,33 @@ pub fn throw_exception(
         && type_of(&value, "object")
         && derive_from_js_value(&value, "then").is_function()
     {
-        let suspense_boundary = get_suspense_handler();
+        let suspense_boundary = get_suspense_handler(state);
         if suspense_boundary.is_some() {
             let suspense_boundary = suspense_boundary.unwrap();
             suspense_boundary.borrow_mut().flags |= Flags::ShouldCapture;
         }
 
         attach_ping_listener(root, source_fiber, value, lane)
+    } else {
+        // It's an error, find the nearest error boundary which hasn't captured an error
+        let component_stack = get_component_stack(source_fiber.clone());
+        let mut node = source_fiber.borrow()._return.clone();
+        while let Some(fiber) = node {
+            let tag = { fiber.borrow().tag.clone() };
+            if tag == WorkTag::HostRoot {
+                // No error boundary, the whole tree will be unmounted
+                fiber.borrow_mut().flags |= Flags::ShouldCapture;
+                state.root_uncaught_errors.borrow_mut().push(value);
+                return;
+            }
+            if is_error_boundary(fiber.clone()) && !fiber.borrow().flags.contains(Flags::DidCapture)
+            {
+                fiber.borrow_mut().flags |= Flags::ShouldCapture;
+                let update = create_class_error_update(fiber.clone(), value, component_stack, lane);
+                enqueue_captured_update(fiber, update);
+                return;
+            }
+            node = fiber.borrow()._return.clone();
+        }
     }
 }
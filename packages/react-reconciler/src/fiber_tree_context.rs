@@ -0,0 +1,197 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::fiber::FiberNode;
+use crate::fiber_flags::Flags;
+use crate::root_state::RootState;
+
+// The id of a fiber is derived from its position in the tree, each level appends the
+// index of the child (plus one) as a slot to the id of its parent. The leading bit marks
+// the length of the id, the bits which exceed 30 are moved to the overflow string.
+//
+//   00101        00010001011010101
+//   ╰─┬─╯        ╰───────┬───────╯
+// Fork 5 of 20       Parent id
+
+pub struct TreeContext {
+    // Only the fibers mounted in the first render of the root get the tree ids, so the ids
+    // match the markup rendered on the server with the same tree
+    is_tree_id_enabled: bool,
+
+    fork_stack: Vec<(u32, Option<Rc<RefCell<FiberNode>>>)>,
+    tree_fork_provider: Option<Rc<RefCell<FiberNode>>>,
+    tree_fork_count: u32,
+
+    id_stack: Vec<(u32, String, Option<Rc<RefCell<FiberNode>>>)>,
+    tree_context_provider: Option<Rc<RefCell<FiberNode>>>,
+    tree_context_id: u32,
+    tree_context_overflow: String,
+}
+
+impl TreeContext {
+    pub fn new() -> Self {
+        TreeContext {
+            is_tree_id_enabled: false,
+            fork_stack: vec![],
+            tree_fork_provider: None,
+            tree_fork_count: 0,
+            id_stack: vec![],
+            tree_context_provider: None,
+            tree_context_id: 1,
+            tree_context_overflow: String::new(),
+        }
+    }
+}
+
+pub fn reset_tree_context(state: &RootState, is_tree_id_enabled: bool) {
+    let mut tree_context = TreeContext::new();
+    tree_context.is_tree_id_enabled = is_tree_id_enabled;
+    *state.tree_context.borrow_mut() = tree_context;
+}
+
+pub fn is_tree_id_enabled(state: &RootState) -> bool {
+    state.tree_context.borrow().is_tree_id_enabled
+}
+
+fn get_bit_length(number: u32) -> u32 {
+    32 - number.leading_zeros()
+}
+
+pub fn to_base_32(mut number: u32) -> String {
+    let digits = b"0123456789abcdefghijklmnopqrstuv";
+    if number == 0 {
+        return "0".to_string();
+    }
+    let mut result = vec![];
+    while number > 0 {
+        result.push(digits[(number % 32) as usize]);
+        number /= 32;
+    }
+    result.reverse();
+    String::from_utf8(result).unwrap()
+}
+
+fn is_same_fiber(a: &Option<Rc<RefCell<FiberNode>>>, b: &Rc<RefCell<FiberNode>>) -> bool {
+    match a {
+        Some(a) => Rc::ptr_eq(a, b),
+        None => false,
+    }
+}
+
+pub fn is_forked_child(state: &RootState, work_in_progress: Rc<RefCell<FiberNode>>) -> bool {
+    is_tree_id_enabled(state) && work_in_progress.borrow().flags.contains(Flags::Forked)
+}
+
+pub fn get_forks_at_level(state: &RootState) -> u32 {
+    state.tree_context.borrow().tree_fork_count
+}
+
+// Called when the children array of return_fiber is reconciled
+pub fn push_tree_fork(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+    total_children: u32,
+) {
+    let mut tree_context = state.tree_context.borrow_mut();
+    let fork = (
+        tree_context.tree_fork_count,
+        tree_context.tree_fork_provider.clone(),
+    );
+    tree_context.fork_stack.push(fork);
+    tree_context.tree_fork_provider = Some(work_in_progress);
+    tree_context.tree_fork_count = total_children;
+}
+
+pub fn push_tree_id(
+    state: &RootState,
+    work_in_progress: Rc<RefCell<FiberNode>>,
+    total_children: u32,
+    index: u32,
+) {
+    let mut tree_context = state.tree_context.borrow_mut();
+    let id = (
+        tree_context.tree_context_id,
+        tree_context.tree_context_overflow.clone(),
+        tree_context.tree_context_provider.clone(),
+    );
+    tree_context.id_stack.push(id);
+    tree_context.tree_context_provider = Some(work_in_progress);
+
+    let base_id_with_leading_bit = tree_context.tree_context_id;
+    let base_overflow = tree_context.tree_context_overflow.clone();
+    let base_length = get_bit_length(base_id_with_leading_bit) - 1;
+    let base_id = base_id_with_leading_bit & !(1 << base_length);
+
+    // 0 is reserved for the leading bit
+    let slot = index + 1;
+    let length = get_bit_length(total_children) + base_length;
+
+    if length > 30 {
+        // Move the bits which are multiple of 5 to the overflow string, the rest of
+        // the bits are kept in the id
+        let number_of_overflow_bits = base_length - base_length % 5;
+        let new_overflow_bits = (1 << number_of_overflow_bits) - 1;
+        let new_overflow = to_base_32(base_id & new_overflow_bits);
+        let rest_of_base_id = base_id >> number_of_overflow_bits;
+        let rest_of_base_length = base_length - number_of_overflow_bits;
+        let rest_of_length = get_bit_length(total_children) + rest_of_base_length;
+        let rest_of_new_bits = slot << rest_of_base_length;
+        let id = rest_of_new_bits | rest_of_base_id;
+        tree_context.tree_context_id = (1 << rest_of_length) | id;
+        tree_context.tree_context_overflow = new_overflow + &base_overflow;
+    } else {
+        let new_bits = slot << base_length;
+        let id = new_bits | base_id;
+        tree_context.tree_context_id = (1 << length) | id;
+        tree_context.tree_context_overflow = base_overflow;
+    }
+}
+
+// The component which calls useId is treated as having a single child, so the ids of its
+// children are different from its own
+pub fn push_materialized_tree_id(state: &RootState, work_in_progress: Rc<RefCell<FiberNode>>) {
+    push_tree_fork(state, work_in_progress.clone(), 1);
+    push_tree_id(state, work_in_progress, 1, 0);
+}
+
+pub fn pop_tree_context(state: &RootState, work_in_progress: Rc<RefCell<FiberNode>>) {
+    let mut tree_context = state.tree_context.borrow_mut();
+    while is_same_fiber(&tree_context.tree_fork_provider, &work_in_progress) {
+        let (count, provider) = tree_context.fork_stack.pop().unwrap();
+        tree_context.tree_fork_count = count;
+        tree_context.tree_fork_provider = provider;
+    }
+    while is_same_fiber(&tree_context.tree_context_provider, &work_in_progress) {
+        let (id, overflow, provider) = tree_context.id_stack.pop().unwrap();
+        tree_context.tree_context_id = id;
+        tree_context.tree_context_overflow = overflow;
+        tree_context.tree_context_provider = provider;
+    }
+}
+
+pub fn get_tree_id(state: &RootState) -> String {
+    let tree_context = state.tree_context.borrow();
+    let id_with_leading_bit = tree_context.tree_context_id;
+    let id = id_with_leading_bit & !(1 << (get_bit_length(id_with_leading_bit) - 1));
+    to_base_32(id) + &tree_context.tree_context_overflow
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_base_32() {
+        assert_eq!(to_base_32(0), "0");
+        assert_eq!(to_base_32(31), "v");
+        assert_eq!(to_base_32(32), "10");
+        assert_eq!(to_base_32(1025), "101");
+    }
+
+    #[test]
+    fn test_get_bit_length() {
+        assert_eq!(get_bit_length(1), 1);
+        assert_eq!(get_bit_length(2), 2);
+        assert_eq!(get_bit_length(5), 3);
+    }
+}
@@ -6,16 +6,36 @@ use crate::{
     fiber::FiberNode,
     fiber_context::pop_provider,
     fiber_flags::Flags,
+    fiber_host_context::{pop_host_container, pop_host_context},
+    fiber_tree_context::pop_tree_context,
+    root_state::RootState,
     suspense_context::pop_suspense_handler,
-    work_tags::WorkTag::{ContextProvider, SuspenseComponent},
+    work_tags::WorkTag::{
+        ClassComponent, ContextProvider, HostComponent, HostPortal, HostRoot, SuspenseComponent,
+    },
 };
 
-pub fn unwind_work(wip: Rc<RefCell<FiberNode>>) -> Option<Rc<RefCell<FiberNode>>> {
+fn should_capture(wip: Rc<RefCell<FiberNode>>) -> bool {
+    let flags = wip.borrow().flags.clone();
+    (flags.clone() & Flags::ShouldCapture) != Flags::NoFlags
+        && (flags.clone() & Flags::DidCapture) == Flags::NoFlags
+}
+
+fn mark_did_capture(wip: Rc<RefCell<FiberNode>>) {
+    let flags = wip.borrow().flags.clone();
+    wip.borrow_mut().flags = (flags - Flags::ShouldCapture) | Flags::DidCapture;
+}
+
+pub fn unwind_work(
+    state: &RootState,
+    wip: Rc<RefCell<FiberNode>>,
+) -> Option<Rc<RefCell<FiberNode>>> {
     let flags = wip.borrow().flags.clone();
     let tag = wip.borrow().tag.clone();
+    pop_tree_context(state, wip.clone());
     match tag {
         SuspenseComponent => {
-            pop_suspense_handler();
+            pop_suspense_handler(state);
             if (flags.clone() & Flags::ShouldCapture) != Flags::NoFlags
                 && (flags.clone() & Flags::DidCapture) == Flags::NoFlags
             {
@@ -24,9 +44,32 @@ pub fn unwind_work(wip: Rc<RefCell<FiberNode>>) -> Option<Rc<RefCell<FiberNode>>
             }
             None
         }
+        ClassComponent => {
+            if should_capture(wip.clone()) {
+                mark_did_capture(wip.clone());
+                return Some(wip.clone());
+            }
+            None
+        }
+        HostRoot => {
+            pop_host_container(state);
+            if should_capture(wip.clone()) {
+                mark_did_capture(wip.clone());
+                return Some(wip.clone());
+            }
+            None
+        }
+        HostComponent => {
+            pop_host_context(state);
+            None
+        }
+        HostPortal => {
+            pop_host_container(state);
+            None
+        }
         ContextProvider => {
             let context = derive_from_js_value(&wip.borrow()._type, "_context");
-            pop_provider(&context);
+            pop_provider(state, &context);
             None
         }
         _ => None,
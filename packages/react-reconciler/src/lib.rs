@@ -5,7 +5,6 @@ use std::rc::Rc;
 use wasm_bindgen::JsValue;
 use web_sys::js_sys::Object;
 
-use crate::complete_work::CompleteWork;
 use crate::fiber::{FiberNode, FiberRootNode, StateNode};
 // use crate::fiber_hooks::{WORK_LOOP as Fiber_HOOKS};
 use crate::fiber_lanes::Lane;
@@ -18,12 +17,16 @@ mod child_fiber;
 mod commit_work;
 mod complete_work;
 pub mod fiber;
+mod fiber_class_component;
 mod fiber_context;
 mod fiber_flags;
 mod fiber_hooks;
+mod fiber_host_context;
 pub mod fiber_lanes;
 mod fiber_throw;
+mod fiber_tree_context;
 mod fiber_unwind_work;
+mod root_state;
 mod suspense_context;
 mod sync_task_queue;
 mod thenable;
@@ -31,16 +34,33 @@ mod update_queue;
 mod work_loop;
 pub mod work_tags;
 
-pub static mut HOST_CONFIG: Option<Rc<dyn HostConfig>> = None;
-static mut COMPLETE_WORK: Option<CompleteWork> = None;
-
 pub trait HostConfig {
     fn create_text_instance(&self, content: &JsValue) -> Rc<dyn Any>;
-    fn create_instance(&self, _type: String, props: Rc<dyn Any>) -> Rc<dyn Any>;
+    // The host context is what get_root_host_context/get_child_host_context returned for the
+    // parent, e.g. the namespace of the parent element in react-dom
+    fn get_root_host_context(&self, container: Rc<dyn Any>) -> JsValue;
+    fn get_child_host_context(&self, parent_host_context: &JsValue, _type: &str) -> JsValue;
+    fn create_instance(
+        &self,
+        _type: String,
+        props: Rc<dyn Any>,
+        host_context: &JsValue,
+    ) -> Rc<dyn Any>;
     fn append_initial_child(&self, parent: Rc<dyn Any>, child: Rc<dyn Any>);
+    // Called after all the children of the instance are appended
+    fn finalize_initial_children(&self, instance: Rc<dyn Any>, _type: String, props: &JsValue);
     fn append_child_to_container(&self, child: Rc<dyn Any>, parent: Rc<dyn Any>);
     fn remove_child(&self, child: Rc<dyn Any>, container: Rc<dyn Any>);
     // fn commit_text_update(&self, text_instance: Rc<dyn Any>, content: &JsValue);
+    // Diff old_props against new_props, return None if nothing need to be updated
+    fn prepare_update(
+        &self,
+        instance: Rc<dyn Any>,
+        _type: String,
+        old_props: &JsValue,
+        new_props: &JsValue,
+    ) -> Option<JsValue>;
+    // For HostComponent, the changes to apply are in fiber.update_payload
     fn commit_update(&self, fiber: Rc<RefCell<FiberNode>>);
     fn insert_child_to_container(
         &self,
@@ -49,6 +69,18 @@ pub trait HostConfig {
         before: Rc<dyn Any>,
     );
     fn schedule_microtask(&self, callback: Box<dyn FnMut()>);
+    // Whether the children of the instance are set as its text content directly,
+    // no HostText fiber will be created for them
+    fn should_set_text_content(&self, _type: &str, props: &JsValue) -> bool;
+    fn reset_text_content(&self, instance: Rc<dyn Any>);
+    // What the ref of the HostComponent receives, e.g. the DOM node in react-dom
+    fn get_public_instance(&self, instance: Rc<dyn Any>) -> JsValue;
+    // Convert the container passed to createPortal to the one used by the methods above
+    fn get_portal_container(&self, container_info: &JsValue) -> Rc<dyn Any>;
+    // Called when the HostPortal is mounted, e.g. react-dom listens to the events on it
+    fn prepare_portal_mount(&self, container: Rc<dyn Any>);
+    // Link the instance to its fiber, so the renderer can walk the fiber tree from it
+    fn precache_fiber_node(&self, fiber: Rc<RefCell<FiberNode>>, instance: Rc<dyn Any>);
 }
 
 pub struct Reconciler {
@@ -70,6 +102,7 @@ impl Reconciler {
         let root = Rc::new(RefCell::new(FiberRootNode::new(
             container.clone(),
             host_root_fiber.clone(),
+            self.host_config.clone(),
         )));
         let r1 = root.clone();
         host_root_fiber.borrow_mut().state_node = Some(Rc::new(StateNode::FiberRootNode(r1)));
@@ -87,11 +120,7 @@ impl Reconciler {
             host_root_fiber.clone(),
             root_render_priority.clone(),
         );
-        unsafe {
-            HOST_CONFIG = Some(self.host_config.clone());
-            COMPLETE_WORK = Some(CompleteWork::new(self.host_config.clone()));
-            schedule_update_on_fiber(host_root_fiber, root_render_priority);
-        }
+        schedule_update_on_fiber(host_root_fiber, root_render_priority);
         element.clone()
     }
 }
@@ -0,0 +1,58 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::JsValue;
+
+use crate::fiber::FiberNode;
+use crate::fiber_context::ContextItem;
+use crate::fiber_hooks::HooksState;
+use crate::fiber_tree_context::TreeContext;
+use crate::work_loop::WorkLoopState;
+use crate::HostConfig;
+
+// The state of the render and the commit of a root, every root owns one and passes it down
+// the work loop, so the roots (even the ones of different renderers) make progress on their
+// own, an interrupted render is resumed after the other roots have rendered in the middle
+pub struct RootState {
+    pub host_config: Rc<dyn HostConfig>,
+    pub work_loop: WorkLoopState,
+    pub hooks: HooksState,
+    // Whether the fiber in begin_work has received an update, or it can bail out
+    pub did_receive_update: Cell<bool>,
+    // The host context of the fibers being worked on, e.g. the namespace in react-dom
+    pub host_context_stack: RefCell<Vec<JsValue>>,
+    // The values of the providers on the path being rendered, the innermost one is the last.
+    // They are kept by the reconciler instead of being written to the context object, so the
+    // renderers and the roots which share the context never see the values of each other
+    pub context_value_stack: RefCell<Vec<(JsValue, JsValue)>>,
+    pub last_context_dep: RefCell<Option<Rc<RefCell<ContextItem>>>>,
+    pub suspense_handler_stack: RefCell<Vec<Rc<RefCell<FiberNode>>>>,
+    pub suspended_thenable: RefCell<Option<JsValue>>,
+    pub tree_context: RefCell<TreeContext>,
+    // The errors captured by HostRoot in the current render, reported after the commit
+    pub root_uncaught_errors: RefCell<Vec<JsValue>>,
+    pub sync_queue: RefCell<Vec<Box<dyn FnMut()>>>,
+    pub is_flushing_sync_queue: Cell<bool>,
+    pub next_effect: RefCell<Option<Rc<RefCell<FiberNode>>>>,
+}
+
+impl RootState {
+    pub fn new(host_config: Rc<dyn HostConfig>) -> Rc<Self> {
+        Rc::new_cyclic(|root_state| RootState {
+            host_config,
+            work_loop: WorkLoopState::new(),
+            hooks: HooksState::new(root_state.clone()),
+            did_receive_update: Cell::new(false),
+            host_context_stack: RefCell::new(vec![]),
+            context_value_stack: RefCell::new(vec![]),
+            last_context_dep: RefCell::new(None),
+            suspense_handler_stack: RefCell::new(vec![]),
+            suspended_thenable: RefCell::new(None),
+            tree_context: RefCell::new(TreeContext::new()),
+            root_uncaught_errors: RefCell::new(vec![]),
+            sync_queue: RefCell::new(vec![]),
+            is_flushing_sync_queue: Cell::new(false),
+            next_effect: RefCell::new(None),
+        })
+    }
+}
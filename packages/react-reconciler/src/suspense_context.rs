@@ -1,22 +1,21 @@
 use std::{cell::RefCell, rc::Rc};
 
 use crate::fiber::FiberNode;
+use crate::root_state::RootState;
 
-static mut SUSPENSE_HANDLER_STACK: Vec<Rc<RefCell<FiberNode>>> = vec![];
+pub fn get_suspense_handler(state: &RootState) -> Option<Rc<RefCell<FiberNode>>> {
+    state.suspense_handler_stack.borrow().last().cloned()
+}
 
-pub fn get_suspense_handler() -> Option<Rc<RefCell<FiberNode>>> {
-    unsafe {
-        if SUSPENSE_HANDLER_STACK.len() <= 0 {
-            return None;
-        }
-        return Some(SUSPENSE_HANDLER_STACK[SUSPENSE_HANDLER_STACK.len() - 1].clone());
-    }
+// The interrupted render leaves its handlers on the stack
+pub fn reset_suspense_handler_stack(state: &RootState) {
+    state.suspense_handler_stack.borrow_mut().clear();
 }
 
-pub fn push_suspense_handler(handler: Rc<RefCell<FiberNode>>) {
-    unsafe { SUSPENSE_HANDLER_STACK.push(handler) }
+pub fn push_suspense_handler(state: &RootState, handler: Rc<RefCell<FiberNode>>) {
+    state.suspense_handler_stack.borrow_mut().push(handler)
 }
 
-pub fn pop_suspense_handler() -> Option<Rc<RefCell<FiberNode>>> {
-    unsafe { SUSPENSE_HANDLER_STACK.pop() }
+pub fn pop_suspense_handler(state: &RootState) -> Option<Rc<RefCell<FiberNode>>> {
+    state.suspense_handler_stack.borrow_mut().pop()
 }
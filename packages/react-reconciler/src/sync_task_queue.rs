@@ -1,19 +1,22 @@
-static mut SYNC_QUEUE: Vec<Box<dyn FnMut()>> = vec![];
-static mut IS_FLUSHING_SYNC_QUEUE: bool = false;
+use crate::root_state::RootState;
 
-pub fn schedule_sync_callback(callback: Box<dyn FnMut()>) {
-    unsafe { SYNC_QUEUE.push(callback) }
+pub fn schedule_sync_callback(state: &RootState, callback: Box<dyn FnMut()>) {
+    state.sync_queue.borrow_mut().push(callback)
 }
 
-pub fn flush_sync_callbacks() {
-    unsafe {
-        if !IS_FLUSHING_SYNC_QUEUE && !SYNC_QUEUE.is_empty() {
-            IS_FLUSHING_SYNC_QUEUE = true;
-            for callback in SYNC_QUEUE.iter_mut() {
+pub fn flush_sync_callbacks(state: &RootState) {
+    if !state.is_flushing_sync_queue.get() && !state.sync_queue.borrow().is_empty() {
+        state.is_flushing_sync_queue.set(true);
+        // The callbacks may schedule new sync callbacks, e.g. setState in useLayoutEffect
+        loop {
+            let queue = std::mem::take(&mut *state.sync_queue.borrow_mut());
+            if queue.is_empty() {
+                break;
+            }
+            for mut callback in queue {
                 callback();
             }
-            SYNC_QUEUE = vec![];
-            IS_FLUSHING_SYNC_QUEUE = false;
         }
+        state.is_flushing_sync_queue.set(false);
     }
 }
@@ -2,24 +2,22 @@ use shared::derive_from_js_value;
 use wasm_bindgen::JsValue;
 use web_sys::js_sys::{wasm_bindgen::prelude::*, Function, Reflect};
 
+use crate::root_state::RootState;
+
 #[wasm_bindgen]
 extern "C" {
     pub static SUSPENSE_EXCEPTION: JsValue;
 }
 
-static mut SUSPENDED_THENABLE: Option<JsValue> = None;
-
-pub fn get_suspense_thenable() -> JsValue {
-    if unsafe { SUSPENDED_THENABLE.is_none() } {
-        panic!("Should have SUSPENDED_THENABLE");
+pub fn get_suspense_thenable(state: &RootState) -> JsValue {
+    let thenable = state.suspended_thenable.borrow_mut().take();
+    if thenable.is_none() {
+        panic!("Should have suspended thenable");
     }
-
-    let thenable = unsafe { SUSPENDED_THENABLE.clone() };
-    unsafe { SUSPENDED_THENABLE = None };
     thenable.unwrap()
 }
 
-pub fn track_used_thenable(thenable: JsValue) -> Result<JsValue, JsValue> {
+pub fn track_used_thenable(state: &RootState, thenable: JsValue) -> Result<JsValue, JsValue> {
     let status = derive_from_js_value(&thenable, "status");
     if status.is_string() {
         if status == "fulfilled" {
@@ -68,6 +66,6 @@ pub fn track_used_thenable(thenable: JsValue) -> Result<JsValue, JsValue> {
 
         then.call2(&thenable, &on_resolve, &on_reject);
     }
-    unsafe { SUSPENDED_THENABLE = Some(thenable.clone()) };
+    *state.suspended_thenable.borrow_mut() = Some(thenable.clone());
     Err(SUSPENSE_EXCEPTION.__inner.with(JsValue::clone))
 }
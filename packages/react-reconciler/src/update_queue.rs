@@ -3,23 +3,37 @@ use std::rc::Rc;
 
 use shared::log;
 use std::fmt::{write, Debug, Formatter};
-use wasm_bindgen::{JsCast, JsValue};
-use web_sys::js_sys::Function;
+use wasm_bindgen::JsValue;
+use web_sys::js_sys::{Function, Object};
 
 use crate::fiber::{FiberNode, MemoizedState};
-use crate::fiber_hooks::Effect;
+use crate::fiber_hooks::{basic_state_reducer, Effect};
 use crate::fiber_lanes::{is_subset_of_lanes, merge_lanes, Lane};
 
 #[derive(Clone, Debug)]
 pub struct UpdateAction;
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum UpdateTag {
+    UpdateState,
+    // this.forceUpdate() of class component
+    ForceUpdate,
+    // The action is the error captured by the error boundary
+    CaptureUpdate,
+}
+
 #[derive(Clone)]
 pub struct Update {
+    pub tag: UpdateTag,
     pub action: Option<JsValue>,
     pub lane: Lane,
     pub next: Option<Rc<RefCell<Update>>>,
     pub has_eager_state: bool,
     pub eager_state: Option<JsValue>,
+    // The reducer used to compute the eager state, None means basic_state_reducer
+    pub eager_reducer: Option<Function>,
+    // The second argument of this.setState/this.forceUpdate
+    pub callback: Option<Function>,
 }
 
 impl Debug for Update {
@@ -39,15 +53,30 @@ pub struct UpdateQueue {
     pub dispatch: Option<Function>,
     pub last_effect: Option<Rc<RefCell<Effect>>>,
     pub last_rendered_state: Option<JsValue>,
+    // The reducer of useReducer in the last render, None means basic_state_reducer of useState
+    pub last_rendered_reducer: Option<Function>,
+    // For class component, the state and updates left by the last render
+    pub base_state: Option<JsValue>,
+    pub base_queue: Option<Rc<RefCell<Update>>>,
+    // The callbacks of processed updates, called in the layout phase
+    pub callbacks: Vec<Function>,
+    // Whether a forceUpdate has been processed in the current render of the class component
+    pub has_force_update: bool,
+    // The getSnapshot and the rendered snapshot of useSyncExternalStore, checked after
+    // a concurrent render
+    pub stores: Vec<(Function, JsValue)>,
 }
 
 pub fn create_update(action: JsValue, lane: Lane) -> Update {
     Update {
+        tag: UpdateTag::UpdateState,
         action: Some(action),
         lane,
         next: None,
         has_eager_state: false,
         eager_state: None,
+        eager_reducer: None,
+        callback: None,
     }
 }
 
@@ -85,6 +114,12 @@ pub fn create_update_queue() -> Rc<RefCell<UpdateQueue>> {
         dispatch: None,
         last_effect: None,
         last_rendered_state: None,
+        last_rendered_reducer: None,
+        base_state: None,
+        base_queue: None,
+        callbacks: vec![],
+        has_force_update: false,
+        stores: vec![],
     }))
 }
 
@@ -123,8 +158,10 @@ pub fn process_update_queue(
     base_state: Option<MemoizedState>,
     pending_update: Option<Rc<RefCell<Update>>>,
     render_lanes: Lane,
-    on_skip_update: Option<fn(update: Rc<RefCell<Update>>) -> ()>,
-) -> ReturnOfProcessUpdateQueue {
+    on_skip_update: Option<&dyn Fn(Rc<RefCell<Update>>)>,
+    // None means basic_state_reducer, e.g. useState and HostRoot
+    reducer: Option<Function>,
+) -> Result<ReturnOfProcessUpdateQueue, JsValue> {
     let mut result = ReturnOfProcessUpdateQueue {
         memoized_state: base_state.clone(),
         base_state: base_state.clone(),
@@ -163,7 +200,7 @@ pub fn process_update_queue(
                 if new_base_queue_last.is_none() {
                     new_base_queue_first = Some(clone.clone());
                     new_base_queue_last = Some(clone.clone());
-                    new_base_state = result.memoized_state.clone();
+                    new_base_state = new_state.clone();
                 } else {
                     new_base_queue_last.clone().unwrap().borrow_mut().next = Some(clone.clone());
                     new_base_queue_last = Some(clone.clone());
@@ -178,7 +215,9 @@ pub fn process_update_queue(
                     new_base_queue_last = Some(clone.clone())
                 }
 
-                if update.borrow().has_eager_state {
+                if update.borrow().has_eager_state
+                    && is_same_reducer(update.borrow().eager_reducer.as_ref(), reducer.as_ref())
+                {
                     new_state = Some(MemoizedState::MemoizedJsValue(
                         update.borrow().eager_state.clone().unwrap(),
                     ));
@@ -204,27 +243,7 @@ pub fn process_update_queue(
                     new_state = match update.borrow().action.clone() {
                         None => None,
                         Some(action) => {
-                            let f = action.dyn_ref::<Function>();
-                            match f {
-                                None => Some(MemoizedState::MemoizedJsValue(action.clone())),
-                                Some(f) => match result.memoized_state.as_ref() {
-                                    Some(memoized_state) => {
-                                        if let MemoizedState::MemoizedJsValue(base_state) =
-                                            memoized_state
-                                        {
-                                            Some(MemoizedState::MemoizedJsValue(
-                                                f.call1(&JsValue::null(), base_state).unwrap(),
-                                            ))
-                                        } else {
-                                            log!("process_update_queue, base_state is not JsValue");
-                                            None
-                                        }
-                                    }
-                                    None => Some(MemoizedState::MemoizedJsValue(
-                                        f.call1(&JsValue::null(), &JsValue::undefined()).unwrap(),
-                                    )),
-                                },
-                            }
+                            apply_reducer(reducer.as_ref(), new_state.as_ref(), &action)?
                         }
                     };
                 }
@@ -273,5 +292,33 @@ pub fn process_update_queue(
         result.base_queue = new_base_queue_last.clone();
     }
     // log!("result:{:?}", result);
-    result
+    Ok(result)
+}
+
+pub fn is_same_reducer(a: Option<&Function>, b: Option<&Function>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => Object::is(a, b),
+        _ => false,
+    }
+}
+
+fn apply_reducer(
+    reducer: Option<&Function>,
+    state: Option<&MemoizedState>,
+    action: &JsValue,
+) -> Result<Option<MemoizedState>, JsValue> {
+    let state = match state {
+        Some(MemoizedState::MemoizedJsValue(state)) => state.clone(),
+        Some(_) => {
+            log!("process_update_queue, base_state is not JsValue");
+            return Ok(None);
+        }
+        None => JsValue::undefined(),
+    };
+    let new_state = match reducer {
+        Some(reducer) => reducer.call2(&JsValue::null(), &state, action)?,
+        None => basic_state_reducer(&state, action)?,
+    };
+    Ok(Some(MemoizedState::MemoizedJsValue(new_state)))
 }
@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 use wasm_bindgen::closure::Closure;
@@ -6,46 +6,84 @@ use wasm_bindgen::{JsCast, JsValue};
 use web_sys::js_sys::{Function, Object};
 
 use scheduler::{
-    unstable_cancel_callback, unstable_schedule_callback_no_delay, unstable_should_yield_to_host,
-    Priority,
+    unstable_cancel_callback, unstable_run_with_priority, unstable_schedule_callback_no_delay,
+    unstable_should_yield_to_host, Priority,
 };
 use shared::{derive_from_js_value, is_dev, log, type_of};
 
 use crate::begin_work::begin_work;
 use crate::commit_work::{
-    commit_hook_effect_list_destroy, commit_hook_effect_list_mount,
+    commit_before_mutation_effects, commit_hook_effect_list_destroy, commit_hook_effect_list_mount,
     commit_hook_effect_list_unmount, commit_layout_effects, commit_mutation_effects,
 };
+use crate::complete_work::complete_work;
 use crate::fiber::{FiberNode, FiberRootNode, PendingPassiveEffects, StateNode};
+use crate::fiber_class_component::enqueue_class_update;
+use crate::fiber_context::reset_context_stack;
 use crate::fiber_flags::{get_host_effect_mask, get_mutation_mask, get_passive_mask, Flags};
 use crate::fiber_hooks::reset_hooks_on_unwind;
+use crate::fiber_host_context::reset_host_context;
 use crate::fiber_lanes::{
     get_highest_priority, lanes_to_scheduler_priority, mark_root_suspended, merge_lanes, Lane,
 };
-use crate::fiber_throw::throw_exception;
+use crate::fiber_throw::{
+    create_class_error_update, get_component_stack, is_error_boundary, report_uncaught_error,
+    reset_root_uncaught_errors, take_root_uncaught_errors, throw_exception,
+};
+use crate::fiber_tree_context::reset_tree_context;
 use crate::fiber_unwind_work::unwind_work;
+use crate::root_state::RootState;
+use crate::suspense_context::reset_suspense_handler_stack;
 use crate::sync_task_queue::{flush_sync_callbacks, schedule_sync_callback};
 use crate::thenable::{get_suspense_thenable, SUSPENSE_EXCEPTION};
+use crate::update_queue::{create_update, enqueue_update};
 use crate::work_tags::WorkTag;
-use crate::{COMPLETE_WORK, HOST_CONFIG};
-
-static mut WORK_IN_PROGRESS: Option<Rc<RefCell<FiberNode>>> = None;
-static mut WORK_IN_PROGRESS_ROOT_RENDER_LANE: Lane = Lane::NoLane;
-static mut ROOT_DOES_HAVE_PASSIVE_EFFECTS: bool = false;
-static mut WORK_IN_PROGRESS_ROOT_EXIT_STATUS: u8 = ROOT_IN_PROGRESS;
-static mut WORK_IN_PROGRESS_SUSPENDED_REASON: u8 = NOT_SUSPENDED;
-static mut WORK_IN_PROGRESS_THROWN_VALUE: Option<JsValue> = None;
 
 static ROOT_IN_PROGRESS: u8 = 0;
 static ROOT_INCOMPLETE: u8 = 1;
 static ROOT_COMPLETED: u8 = 2;
 static ROOT_DID_NOT_COMPLETE: u8 = 3;
+static ROOT_ERRORED: u8 = 4;
 
 static NOT_SUSPENDED: u8 = 0;
 static SUSPENDED_ON_ERROR: u8 = 1;
 static SUSPENDED_ON_DATA: u8 = 2;
 static SUSPENDED_ON_DEPRECATED_THROW_PROMISE: u8 = 4;
 
+// The render in progress of the root, it's resumed when the root is worked on again, even if
+// the other roots have rendered in the middle
+pub struct WorkLoopState {
+    work_in_progress: RefCell<Option<Rc<RefCell<FiberNode>>>>,
+    render_lane: RefCell<Lane>,
+    exit_status: Cell<u8>,
+    suspended_reason: Cell<u8>,
+    thrown_value: RefCell<Option<JsValue>>,
+}
+
+impl WorkLoopState {
+    pub fn new() -> Self {
+        WorkLoopState {
+            work_in_progress: RefCell::new(None),
+            render_lane: RefCell::new(Lane::NoLane),
+            exit_status: Cell::new(ROOT_IN_PROGRESS),
+            suspended_reason: Cell::new(NOT_SUSPENDED),
+            thrown_value: RefCell::new(None),
+        }
+    }
+
+    fn work_in_progress(&self) -> Option<Rc<RefCell<FiberNode>>> {
+        self.work_in_progress.borrow().clone()
+    }
+
+    fn set_work_in_progress(&self, work_in_progress: Option<Rc<RefCell<FiberNode>>>) {
+        *self.work_in_progress.borrow_mut() = work_in_progress;
+    }
+
+    fn render_lane(&self) -> Lane {
+        self.render_lane.borrow().clone()
+    }
+}
+
 pub fn schedule_update_on_fiber(fiber: Rc<RefCell<FiberNode>>, lane: Lane) {
     if is_dev() {
         log!("schedule_update_on_fiber, {:?} {:?}", fiber, lane);
@@ -59,6 +97,32 @@ pub fn schedule_update_on_fiber(fiber: Rc<RefCell<FiberNode>>, lane: Lane) {
     ensure_root_is_scheduled(root.unwrap())
 }
 
+// Errors thrown in commit phase and effects are dispatched as updates to the nearest error
+// boundary, if there is none, the root will be unmounted
+pub fn capture_commit_phase_error(source_fiber: Rc<RefCell<FiberNode>>, error: JsValue) {
+    let component_stack = get_component_stack(source_fiber.clone());
+    let mut node = source_fiber.borrow()._return.clone();
+    while let Some(fiber) = node {
+        let tag = { fiber.borrow().tag.clone() };
+        if tag == WorkTag::HostRoot {
+            report_uncaught_error(&error);
+            let update_queue = { fiber.borrow().update_queue.clone().unwrap() };
+            let update = create_update(JsValue::null(), Lane::SyncLane);
+            enqueue_update(update_queue, update, fiber.clone(), Lane::SyncLane);
+            schedule_update_on_fiber(fiber, Lane::SyncLane);
+            return;
+        }
+        if is_error_boundary(fiber.clone()) {
+            let update =
+                create_class_error_update(fiber.clone(), error, component_stack, Lane::SyncLane);
+            enqueue_class_update(fiber, update, Lane::SyncLane);
+            return;
+        }
+        node = fiber.borrow()._return.clone();
+    }
+    report_uncaught_error(&error);
+}
+
 pub fn mark_update_lane_from_fiber_to_root(
     fiber: Rc<RefCell<FiberNode>>,
     lane: Lane,
@@ -101,6 +165,7 @@ pub fn mark_update_lane_from_fiber_to_root(
 }
 
 pub fn ensure_root_is_scheduled(root: Rc<RefCell<FiberRootNode>>) {
+    let state = { root.borrow().state.clone() };
     let root_cloned = root.clone();
     let update_lanes = root_cloned.borrow().get_next_lanes();
     let existing_callback = root_cloned.borrow().callback_node.clone();
@@ -132,15 +197,16 @@ pub fn ensure_root_is_scheduled(root: Rc<RefCell<FiberRootNode>>) {
         if is_dev() {
             log!("Schedule in microtask, priority {:?}", update_lanes);
         }
-        schedule_sync_callback(Box::new(move || {
-            perform_sync_work_on_root(root_cloned.clone(), update_lanes.clone());
-        }));
-        unsafe {
-            HOST_CONFIG
-                .as_ref()
-                .unwrap()
-                .schedule_microtask(Box::new(|| flush_sync_callbacks()));
-        }
+        schedule_sync_callback(
+            &state,
+            Box::new(move || {
+                perform_sync_work_on_root(root_cloned.clone(), update_lanes.clone());
+            }),
+        );
+        let state_cloned = state.clone();
+        state
+            .host_config
+            .schedule_microtask(Box::new(move || flush_sync_callbacks(&state_cloned)));
     } else {
         if is_dev() {
             log!("Schedule in macrotask, priority {:?}", update_lanes);
@@ -163,7 +229,12 @@ pub fn ensure_root_is_scheduled(root: Rc<RefCell<FiberRootNode>>) {
     root.borrow_mut().callback_priority = cur_priority;
 }
 
-fn render_root(root: Rc<RefCell<FiberRootNode>>, lane: Lane, should_time_slice: bool) -> u8 {
+fn render_root(
+    state: &RootState,
+    root: Rc<RefCell<FiberRootNode>>,
+    lane: Lane,
+    should_time_slice: bool,
+) -> u8 {
     if is_dev() {
         log!(
             "Start {:?} render",
@@ -175,69 +246,113 @@ fn render_root(root: Rc<RefCell<FiberRootNode>>, lane: Lane, should_time_slice:
         );
     }
 
-    if unsafe { WORK_IN_PROGRESS_ROOT_RENDER_LANE != lane } {
-        prepare_fresh_stack(root.clone(), lane.clone());
+    let work_loop = &state.work_loop;
+    // The render of the root is resumed if it was interrupted with the same lane
+    if work_loop.render_lane() != lane {
+        prepare_fresh_stack(state, root.clone(), lane.clone());
     }
 
     loop {
-        unsafe {
-            if WORK_IN_PROGRESS_SUSPENDED_REASON != NOT_SUSPENDED && WORK_IN_PROGRESS.is_some() {
-                let thrown_value = WORK_IN_PROGRESS_THROWN_VALUE.clone().unwrap();
-
-                WORK_IN_PROGRESS_SUSPENDED_REASON = NOT_SUSPENDED;
-                WORK_IN_PROGRESS_THROWN_VALUE = None;
-
-                // TODO
-                mark_update_lane_from_fiber_to_root(
-                    WORK_IN_PROGRESS.clone().unwrap(),
-                    lane.clone(),
-                );
-
-                throw_and_unwind_work_loop(
-                    root.clone(),
-                    WORK_IN_PROGRESS.clone().unwrap(),
-                    thrown_value,
-                    lane.clone(),
-                );
+        let work_in_progress = work_loop.work_in_progress();
+        if work_loop.suspended_reason.get() != NOT_SUSPENDED && work_in_progress.is_some() {
+            let thrown_value = { work_loop.thrown_value.borrow_mut().take().unwrap() };
+
+            if work_loop.suspended_reason.get() == SUSPENDED_ON_ERROR {
+                work_loop.exit_status.set(ROOT_ERRORED);
             }
+            work_loop.suspended_reason.set(NOT_SUSPENDED);
+
+            // TODO
+            mark_update_lane_from_fiber_to_root(work_in_progress.clone().unwrap(), lane.clone());
+
+            throw_and_unwind_work_loop(
+                state,
+                root.clone(),
+                work_in_progress.unwrap(),
+                thrown_value,
+                lane.clone(),
+            );
         }
         match if should_time_slice {
-            work_loop_concurrent()
+            work_loop_concurrent(state)
         } else {
-            work_loop_sync()
+            work_loop_sync(state)
         } {
             Ok(_) => {
                 break;
             }
             Err(e) => {
                 log!("e {:?}", e);
-                handle_throw(root.clone(), e)
+                handle_throw(state, root.clone(), e)
             }
         };
     }
 
     log!("render over {:?}", *root.clone().borrow());
 
-    unsafe {
-        // WORK_IN_PROGRESS_ROOT_RENDER_LANE = Lane::NoLane;
+    if should_time_slice && work_loop.work_in_progress().is_some() {
+        return ROOT_INCOMPLETE;
+    }
 
-        if should_time_slice && WORK_IN_PROGRESS.is_some() {
-            return ROOT_INCOMPLETE;
-        }
+    if !should_time_slice && work_loop.work_in_progress().is_some() {
+        log!("The WIP is not null when render finishing")
+    }
 
-        if !should_time_slice && WORK_IN_PROGRESS.is_some() {
-            log!("The WIP is not null when render finishing")
-        }
+    if work_loop.exit_status.get() == ROOT_ERRORED {
+        return ROOT_ERRORED;
     }
 
     ROOT_COMPLETED
 }
 
+// The error may be caused by a data race of concurrent render, render again synchronously
+// before committing the error boundaries
+fn recover_from_error(state: &RootState, root: Rc<RefCell<FiberRootNode>>, lane: Lane) -> u8 {
+    if is_dev() {
+        log!("Recover from render error");
+    }
+    prepare_fresh_stack(state, root.clone(), lane.clone());
+    render_root(state, root, lane, false)
+}
+
+fn is_render_consistent_with_external_stores(finished_work: Rc<RefCell<FiberNode>>) -> bool {
+    let flags = finished_work.borrow().flags.clone();
+    if flags.contains(Flags::StoreConsistency) {
+        let update_queue = { finished_work.borrow().update_queue.clone() };
+        if let Some(update_queue) = update_queue {
+            for (get_snapshot, rendered_snapshot) in update_queue.borrow().stores.iter() {
+                match get_snapshot.call0(&JsValue::null()) {
+                    Ok(snapshot) => {
+                        if !Object::is(&snapshot, rendered_snapshot) {
+                            return false;
+                        }
+                    }
+                    // Render again to throw the error in the render phase
+                    Err(_) => return false,
+                }
+            }
+        }
+    }
+
+    let subtree_flags = finished_work.borrow().subtree_flags.clone();
+    if subtree_flags.contains(Flags::StoreConsistency) {
+        let mut child = { finished_work.borrow().child.clone() };
+        while let Some(c) = child {
+            if !is_render_consistent_with_external_stores(c.clone()) {
+                return false;
+            }
+            child = c.borrow().sibling.clone();
+        }
+    }
+    true
+}
+
 fn perform_concurrent_work_on_root(root: Rc<RefCell<FiberRootNode>>, did_timeout: bool) -> JsValue {
+    let state = { root.borrow().state.clone() };
     // 开始执行具体工作前，保证上一次的useEffct都执行了
     // 同时要注意useEffect执行时触发的更新优先级是否大于当前更新的优先级
     let did_flush_passive_effects =
-        flush_passive_effects(root.borrow().pending_passive_effects.clone());
+        flush_passive_effects(&state, root.borrow().pending_passive_effects.clone());
     let cur_callback_node = root.borrow().callback_node.clone();
 
     // 这个分支好像走不到
@@ -254,7 +369,22 @@ fn perform_concurrent_work_on_root(root: Rc<RefCell<FiberRootNode>>, did_timeout
     }
 
     let should_time_slice = !did_timeout;
-    let exit_status = render_root(root.clone(), lanes.clone(), should_time_slice);
+    let mut exit_status = render_root(&state, root.clone(), lanes.clone(), should_time_slice);
+    if exit_status == ROOT_ERRORED {
+        exit_status = recover_from_error(&state, root.clone(), lanes.clone());
+    }
+    if exit_status == ROOT_COMPLETED {
+        let finished_work = { root.borrow().current.borrow().alternate.clone().unwrap() };
+        if !is_render_consistent_with_external_stores(finished_work) {
+            // The stores were mutated during the time sliced render, render again
+            // synchronously so the stores can't be mutated in the middle
+            prepare_fresh_stack(&state, root.clone(), lanes.clone());
+            exit_status = render_root(&state, root.clone(), lanes.clone(), false);
+            if exit_status == ROOT_ERRORED {
+                exit_status = recover_from_error(&state, root.clone(), lanes.clone());
+            }
+        }
+    }
 
     ensure_root_is_scheduled(root.clone());
     if exit_status == ROOT_INCOMPLETE {
@@ -274,7 +404,7 @@ fn perform_concurrent_work_on_root(root: Rc<RefCell<FiberRootNode>>, did_timeout
         return function.into();
     }
 
-    if exit_status == ROOT_COMPLETED {
+    if exit_status == ROOT_COMPLETED || exit_status == ROOT_ERRORED {
         let finished_work = {
             root.clone()
                 .borrow()
@@ -286,8 +416,8 @@ fn perform_concurrent_work_on_root(root: Rc<RefCell<FiberRootNode>>, did_timeout
         };
         root.clone().borrow_mut().finished_work = finished_work;
         root.clone().borrow_mut().finished_lanes = lanes;
-        unsafe { WORK_IN_PROGRESS_ROOT_RENDER_LANE = Lane::NoLane };
-        commit_root(root);
+        reset_work_in_progress_root(&state);
+        commit_root(&state, root);
     } else {
         todo!("Unsupported status of concurrent render")
     }
@@ -296,6 +426,7 @@ fn perform_concurrent_work_on_root(root: Rc<RefCell<FiberRootNode>>, did_timeout
 }
 
 fn perform_sync_work_on_root(root: Rc<RefCell<FiberRootNode>>, lanes: Lane) {
+    let state = { root.borrow().state.clone() };
     let next_lane = root.borrow().get_next_lanes();
 
     if next_lane != Lane::SyncLane {
@@ -303,9 +434,12 @@ fn perform_sync_work_on_root(root: Rc<RefCell<FiberRootNode>>, lanes: Lane) {
         return;
     }
 
-    let exit_status = render_root(root.clone(), lanes.clone(), false);
+    let mut exit_status = render_root(&state, root.clone(), lanes.clone(), false);
+    if exit_status == ROOT_ERRORED {
+        exit_status = recover_from_error(&state, root.clone(), lanes.clone());
+    }
 
-    if exit_status == ROOT_COMPLETED {
+    if exit_status == ROOT_COMPLETED || exit_status == ROOT_ERRORED {
         let finished_work = {
             root.clone()
                 .borrow()
@@ -317,8 +451,8 @@ fn perform_sync_work_on_root(root: Rc<RefCell<FiberRootNode>>, lanes: Lane) {
         };
         root.clone().borrow_mut().finished_work = finished_work;
         root.clone().borrow_mut().finished_lanes = lanes;
-        unsafe { WORK_IN_PROGRESS_ROOT_RENDER_LANE = Lane::NoLane };
-        commit_root(root);
+        reset_work_in_progress_root(&state);
+        commit_root(&state, root);
     } else if exit_status == ROOT_DID_NOT_COMPLETE {
         // unsafe { WORK_IN_PROGRESS_ROOT_RENDER_LANE = Lane::NoLane };
         mark_root_suspended(root.clone(), next_lane);
@@ -328,30 +462,41 @@ fn perform_sync_work_on_root(root: Rc<RefCell<FiberRootNode>>, lanes: Lane) {
     }
 }
 
-fn flush_passive_effects(pending_passive_effects: Rc<RefCell<PendingPassiveEffects>>) -> bool {
-    unsafe {
-        let mut did_flush_passive_effects = false;
-        for effect in &pending_passive_effects.borrow().unmount {
-            did_flush_passive_effects = true;
-            commit_hook_effect_list_destroy(Flags::Passive, effect.clone());
-        }
-        pending_passive_effects.borrow_mut().unmount = vec![];
-
-        for effect in &pending_passive_effects.borrow().update {
-            did_flush_passive_effects = true;
-            commit_hook_effect_list_unmount(Flags::Passive | Flags::HookHasEffect, effect.clone());
-        }
-        for effect in &pending_passive_effects.borrow().update {
-            did_flush_passive_effects = true;
-            commit_hook_effect_list_mount(Flags::Passive | Flags::HookHasEffect, effect.clone());
-        }
-        pending_passive_effects.borrow_mut().update = vec![];
-        flush_sync_callbacks();
-        did_flush_passive_effects
+fn flush_passive_effects(
+    state: &RootState,
+    pending_passive_effects: Rc<RefCell<PendingPassiveEffects>>,
+) -> bool {
+    let mut did_flush_passive_effects = false;
+    let unmount = { pending_passive_effects.borrow().unmount.clone() };
+    pending_passive_effects.borrow_mut().unmount = vec![];
+    for (fiber, effect) in &unmount {
+        did_flush_passive_effects = true;
+        commit_hook_effect_list_destroy(Flags::Passive, fiber.clone(), effect.clone());
+    }
+
+    let update = { pending_passive_effects.borrow().update.clone() };
+    pending_passive_effects.borrow_mut().update = vec![];
+    for (fiber, effect) in &update {
+        did_flush_passive_effects = true;
+        commit_hook_effect_list_unmount(
+            Flags::Passive | Flags::HookHasEffect,
+            fiber.clone(),
+            effect.clone(),
+        );
+    }
+    for (fiber, effect) in &update {
+        did_flush_passive_effects = true;
+        commit_hook_effect_list_mount(
+            Flags::Passive | Flags::HookHasEffect,
+            fiber.clone(),
+            effect.clone(),
+        );
     }
+    flush_sync_callbacks(state);
+    did_flush_passive_effects
 }
 
-fn commit_root(root: Rc<RefCell<FiberRootNode>>) {
+fn commit_root(state: &RootState, root: Rc<RefCell<FiberRootNode>>) {
     let cloned = root.clone();
     if cloned.borrow().finished_work.is_none() {
         return;
@@ -379,10 +524,14 @@ fn commit_root(root: Rc<RefCell<FiberRootNode>>) {
     if flags.clone() & passive_mask.clone() != Flags::NoFlags
         || subtree_flags.clone() & passive_mask != Flags::NoFlags
     {
-        if unsafe { !ROOT_DOES_HAVE_PASSIVE_EFFECTS } {
-            unsafe { ROOT_DOES_HAVE_PASSIVE_EFFECTS = true }
+        if !root.borrow().does_have_passive_effects {
+            root.borrow_mut().does_have_passive_effects = true;
             let closure = Closure::wrap(Box::new(move || {
-                flush_passive_effects(root_cloned.borrow().pending_passive_effects.clone());
+                let state = { root_cloned.borrow().state.clone() };
+                root_cloned.borrow_mut().does_have_passive_effects = false;
+                let pending_passive_effects =
+                    { root_cloned.borrow().pending_passive_effects.clone() };
+                flush_passive_effects(&state, pending_passive_effects);
             }) as Box<dyn Fn()>);
             let function = closure.as_ref().unchecked_ref::<Function>().clone();
             closure.forget();
@@ -390,171 +539,183 @@ fn commit_root(root: Rc<RefCell<FiberRootNode>>) {
         }
     }
 
-    let subtree_has_effect = get_mutation_mask().contains(subtree_flags);
-    let root_has_effect = get_mutation_mask().contains(flags);
+    let effect_mask =
+        Flags::BeforeMutationMask | get_mutation_mask() | Flags::LayoutMask | get_passive_mask();
+    let subtree_has_effect = subtree_flags.intersects(effect_mask.clone());
+    let root_has_effect = flags.intersects(effect_mask);
 
     if subtree_has_effect || root_has_effect {
         // effect
 
         // 1/3: Before Mutation
+        commit_before_mutation_effects(state, finished_work.clone(), root.clone());
 
         // 2/3: Mutation
-        commit_mutation_effects(finished_work.clone(), root.clone());
+        commit_mutation_effects(state, finished_work.clone(), root.clone());
 
         // Switch Fiber Tree
         cloned.borrow_mut().current = finished_work.clone();
 
         // 3/3: Layout
-        commit_layout_effects(finished_work.clone(), root.clone());
+        // The updates scheduled in useLayoutEffect and componentDidMount/Update are SyncLane
+        let finished_work_cloned = finished_work.clone();
+        let root_cloned = root.clone();
+        let closure = Closure::wrap(Box::new(move || {
+            let state = { root_cloned.borrow().state.clone() };
+            commit_layout_effects(&state, finished_work_cloned.clone(), root_cloned.clone());
+        }) as Box<dyn Fn()>);
+        unstable_run_with_priority(
+            Priority::ImmediatePriority,
+            closure.as_ref().unchecked_ref::<Function>(),
+        );
     } else {
         cloned.borrow_mut().current = finished_work.clone();
     }
 
-    unsafe {
-        ROOT_DOES_HAVE_PASSIVE_EFFECTS = false;
+    // The errors which are not caught by any error boundary, the root has been unmounted
+    for error in take_root_uncaught_errors(state) {
+        report_uncaught_error(&error);
     }
+
     ensure_root_is_scheduled(root);
+    // Flush the sync updates scheduled in the layout phase before the browser paints
+    flush_sync_callbacks(state);
 }
 
-fn prepare_fresh_stack(root: Rc<RefCell<FiberRootNode>>, lane: Lane) {
-    let root = root.clone();
-    unsafe {
-        WORK_IN_PROGRESS = Some(FiberNode::create_work_in_progress(
-            root.borrow().current.clone(),
-            JsValue::null(),
-        ));
-        WORK_IN_PROGRESS_ROOT_RENDER_LANE = lane;
+fn reset_work_in_progress_root(state: &RootState) {
+    *state.work_loop.render_lane.borrow_mut() = Lane::NoLane;
+}
 
-        WORK_IN_PROGRESS_ROOT_EXIT_STATUS = ROOT_IN_PROGRESS;
-        WORK_IN_PROGRESS_SUSPENDED_REASON = NOT_SUSPENDED;
-        WORK_IN_PROGRESS_THROWN_VALUE = None;
-    }
+fn prepare_fresh_stack(state: &RootState, root: Rc<RefCell<FiberRootNode>>, lane: Lane) {
+    let work_loop = &state.work_loop;
+    let current = { root.borrow().current.clone() };
+    work_loop.set_work_in_progress(Some(FiberNode::create_work_in_progress(
+        current,
+        JsValue::null(),
+    )));
+    *work_loop.render_lane.borrow_mut() = lane;
+    work_loop.exit_status.set(ROOT_IN_PROGRESS);
+    work_loop.suspended_reason.set(NOT_SUSPENDED);
+    *work_loop.thrown_value.borrow_mut() = None;
+
+    reset_host_context(state);
+    reset_context_stack(state);
+    reset_suspense_handler_stack(state);
+    reset_root_uncaught_errors(state);
+    // Nothing has been committed to the root, the ids of useId are derived from the tree
+    let is_first_render = { root.borrow().current.borrow().child.is_none() };
+    reset_tree_context(state, is_first_render);
 }
 
-fn work_loop_sync() -> Result<(), JsValue> {
-    unsafe {
-        while WORK_IN_PROGRESS.is_some() {
-            perform_unit_of_work(WORK_IN_PROGRESS.clone().unwrap())?;
-        }
+fn work_loop_sync(state: &RootState) -> Result<(), JsValue> {
+    while let Some(work_in_progress) = state.work_loop.work_in_progress() {
+        perform_unit_of_work(state, work_in_progress)?;
     }
     Ok(())
 }
 
-fn work_loop_concurrent() -> Result<(), JsValue> {
-    unsafe {
-        while WORK_IN_PROGRESS.is_some() && !unstable_should_yield_to_host() {
-            perform_unit_of_work(WORK_IN_PROGRESS.clone().unwrap())?;
+fn work_loop_concurrent(state: &RootState) -> Result<(), JsValue> {
+    while let Some(work_in_progress) = state.work_loop.work_in_progress() {
+        if unstable_should_yield_to_host() {
+            break;
         }
+        perform_unit_of_work(state, work_in_progress)?;
     }
     Ok(())
 }
 
-fn perform_unit_of_work(fiber: Rc<RefCell<FiberNode>>) -> Result<(), JsValue> {
-    let next = begin_work(fiber.clone(), unsafe {
-        WORK_IN_PROGRESS_ROOT_RENDER_LANE.clone()
-    })?;
+fn perform_unit_of_work(state: &RootState, fiber: Rc<RefCell<FiberNode>>) -> Result<(), JsValue> {
+    let next = begin_work(state, fiber.clone(), state.work_loop.render_lane())?;
     let pending_props = { fiber.clone().borrow().pending_props.clone() };
     fiber.clone().borrow_mut().memoized_props = pending_props;
     if next.is_none() {
-        complete_unit_of_work(fiber.clone());
+        complete_unit_of_work(state, fiber.clone());
     } else {
-        // self.work_in_progress = Some(next.unwrap());
-        unsafe { WORK_IN_PROGRESS = Some(next.unwrap()) }
+        state.work_loop.set_work_in_progress(next);
     }
     Ok(())
 }
 
-fn complete_unit_of_work(fiber: Rc<RefCell<FiberNode>>) {
+fn complete_unit_of_work(state: &RootState, fiber: Rc<RefCell<FiberNode>>) {
     let mut node: Option<Rc<RefCell<FiberNode>>> = Some(fiber);
 
-    unsafe {
-        loop {
-            let next = COMPLETE_WORK
-                .as_ref()
-                .unwrap()
-                .complete_work(node.clone().unwrap().clone());
-
-            if next.is_some() {
-                // self.work_in_progress = next.clone();
-                WORK_IN_PROGRESS = next.clone();
-                return;
-            }
+    loop {
+        let next = complete_work(state, node.clone().unwrap().clone());
 
-            let sibling = node.clone().unwrap().clone().borrow().sibling.clone();
-            if sibling.is_some() {
-                // self.work_in_progress = next.clone();
-                WORK_IN_PROGRESS = sibling.clone();
-                return;
-            }
+        if next.is_some() {
+            state.work_loop.set_work_in_progress(next);
+            return;
+        }
 
-            let _return = node.clone().unwrap().clone().borrow()._return.clone();
-            // log!("complete_unit_of_work {:?} {:?}", node, _return);
-            if _return.is_none() {
-                // node = None;
-                // self.work_in_progress = None;
-                WORK_IN_PROGRESS = None;
-                break;
-            } else {
-                node = _return;
-                // self.work_in_progress = node.clone();
-                WORK_IN_PROGRESS = node.clone();
-            }
+        let sibling = node.clone().unwrap().clone().borrow().sibling.clone();
+        if sibling.is_some() {
+            state.work_loop.set_work_in_progress(sibling);
+            return;
+        }
+
+        let _return = node.clone().unwrap().clone().borrow()._return.clone();
+        // log!("complete_unit_of_work {:?} {:?}", node, _return);
+        if _return.is_none() {
+            state.work_loop.set_work_in_progress(None);
+            break;
+        } else {
+            node = _return;
+            state.work_loop.set_work_in_progress(node.clone());
         }
     }
 }
 
-fn handle_throw(root: Rc<RefCell<FiberRootNode>>, mut thrown_value: JsValue) {
+fn handle_throw(state: &RootState, root: Rc<RefCell<FiberRootNode>>, mut thrown_value: JsValue) {
     /*
         throw possibilities:
             1. use thenable
             2. error (Error Boundary)
     */
+    let work_loop = &state.work_loop;
     if Object::is(&thrown_value, &SUSPENSE_EXCEPTION) {
-        unsafe { WORK_IN_PROGRESS_SUSPENDED_REASON = SUSPENDED_ON_DATA };
-        thrown_value = get_suspense_thenable();
+        work_loop.suspended_reason.set(SUSPENDED_ON_DATA);
+        thrown_value = get_suspense_thenable(state);
     } else {
         let is_wakeable = !thrown_value.is_null()
             && type_of(&thrown_value, "object")
             && derive_from_js_value(&thrown_value, "then").is_function();
-        unsafe {
-            WORK_IN_PROGRESS_SUSPENDED_REASON = if is_wakeable {
-                SUSPENDED_ON_DEPRECATED_THROW_PROMISE
-            } else {
-                SUSPENDED_ON_ERROR
-            };
-        };
+        work_loop.suspended_reason.set(if is_wakeable {
+            SUSPENDED_ON_DEPRECATED_THROW_PROMISE
+        } else {
+            SUSPENDED_ON_ERROR
+        });
     }
 
-    unsafe {
-        WORK_IN_PROGRESS_THROWN_VALUE = Some(thrown_value);
-    }
+    *work_loop.thrown_value.borrow_mut() = Some(thrown_value);
 }
 
 fn throw_and_unwind_work_loop(
+    state: &RootState,
     root: Rc<RefCell<FiberRootNode>>,
     unit_of_work: Rc<RefCell<FiberNode>>,
     thrown_value: JsValue,
     lane: Lane,
 ) {
-    reset_hooks_on_unwind(unit_of_work.clone());
+    reset_hooks_on_unwind(state, unit_of_work.clone());
     throw_exception(
+        state,
         root.clone(),
         unit_of_work.clone(),
         thrown_value,
         lane.clone(),
     );
-    unwind_unit_of_work(unit_of_work);
+    unwind_unit_of_work(state, unit_of_work);
 }
 
-fn unwind_unit_of_work(unit_of_work: Rc<RefCell<FiberNode>>) {
+fn unwind_unit_of_work(state: &RootState, unit_of_work: Rc<RefCell<FiberNode>>) {
     let mut incomplete_work = Some(unit_of_work);
     loop {
         let unwrapped_work = incomplete_work.clone().unwrap();
-        let next = unwind_work(unwrapped_work.clone());
+        let next = unwind_work(state, unwrapped_work.clone());
         if next.is_some() {
             let next = next.unwrap();
             next.borrow_mut().flags &= get_host_effect_mask();
-            unsafe { WORK_IN_PROGRESS = Some(next) };
+            state.work_loop.set_work_in_progress(Some(next));
             return;
         }
 
@@ -572,8 +733,6 @@ fn unwind_unit_of_work(unit_of_work: Rc<RefCell<FiberNode>>) {
         }
     }
 
-    unsafe {
-        WORK_IN_PROGRESS = None;
-        WORK_IN_PROGRESS_ROOT_EXIT_STATUS = ROOT_DID_NOT_COMPLETE;
-    }
+    state.work_loop.set_work_in_progress(None);
+    state.work_loop.exit_status.set(ROOT_DID_NOT_COMPLETE);
 }
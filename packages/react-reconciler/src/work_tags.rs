@@ -1,11 +1,15 @@
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum WorkTag {
     FunctionComponent = 0,
+    ClassComponent = 1,
     HostRoot = 3,
+    HostPortal = 4,
     HostComponent = 5,
     HostText = 6,
     Fragment = 7,
     ContextProvider = 8,
+    ContextConsumer = 9,
+    ForwardRef = 11,
     SuspenseComponent = 13,
     OffscreenComponent = 14,
     MemoComponent = 15,
@@ -0,0 +1,250 @@
+use js_sys::{Array, Error, Function, Object};
+use wasm_bindgen::prelude::*;
+
+use shared::{derive_from_js_value, to_string, REACT_ELEMENT_TYPE, REACT_PORTAL_TYPE};
+
+use crate::{clone_and_replace_key, is_valid_element};
+
+static SEPARATOR: &str = ".";
+static SUB_SEPARATOR: &str = ":";
+
+// The key of the element is escaped, so it can't be mixed up with the index of the children
+// without key, e.g. `$1` and `1`
+fn escape(key: &str) -> String {
+    let escaped: String = key
+        .chars()
+        .map(|c| match c {
+            '=' => "=0".to_string(),
+            ':' => "=2".to_string(),
+            _ => c.to_string(),
+        })
+        .collect();
+    format!("${}", escaped)
+}
+
+// The keys returned by the callback are prefixed to the key of the child, the slashes in them
+// are doubled, so the prefix can't be mixed up with the separator
+fn escape_user_provided_key(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        result.push(c);
+        if c == '/' && chars.peek() != Some(&'/') {
+            result.push('/');
+        }
+    }
+    result
+}
+
+fn to_base_36(mut number: u32) -> String {
+    let digits = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    if number == 0 {
+        return "0".to_string();
+    }
+    let mut result = vec![];
+    while number > 0 {
+        result.push(digits[(number % 36) as usize]);
+        number /= 36;
+    }
+    result.reverse();
+    String::from_utf8(result).unwrap()
+}
+
+fn get_element_key(element: &JsValue, index: u32) -> String {
+    if element.is_object() {
+        let key = derive_from_js_value(element, "key");
+        if !key.is_null() && !key.is_undefined() {
+            return escape(&to_string(&key));
+        }
+    }
+    to_base_36(index)
+}
+
+fn is_single_child(children: &JsValue) -> bool {
+    if children.is_null() || children.is_string() || children.as_f64().is_some() {
+        return true;
+    }
+    if !children.is_object() {
+        return false;
+    }
+    let type_of = derive_from_js_value(children, "$$typeof").as_string();
+    match type_of {
+        Some(type_of) => type_of == REACT_ELEMENT_TYPE || type_of == REACT_PORTAL_TYPE,
+        None => false,
+    }
+}
+
+fn map_into_array(
+    children: &JsValue,
+    array: &Array,
+    escaped_prefix: &str,
+    name_so_far: &str,
+    callback: &mut dyn FnMut(&JsValue) -> Result<JsValue, JsValue>,
+) -> Result<u32, JsValue> {
+    // undefined and booleans are treated as null
+    let children = if children.is_undefined() || children.as_bool().is_some() {
+        JsValue::null()
+    } else {
+        children.clone()
+    };
+
+    if is_single_child(&children) {
+        let mapped_child = callback(&children)?;
+        let child_key = if name_so_far.is_empty() {
+            format!("{}{}", SEPARATOR, get_element_key(&children, 0))
+        } else {
+            name_so_far.to_string()
+        };
+        if mapped_child.is_array() {
+            let escaped_child_key = format!("{}/", escape_user_provided_key(&child_key));
+            map_into_array(&mapped_child, array, &escaped_child_key, "", &mut |c| {
+                Ok(c.clone())
+            })?;
+        } else if !mapped_child.is_null() && !mapped_child.is_undefined() {
+            let mapped_child = if is_valid_element(&mapped_child) {
+                // Keep the key returned by the callback if it is different from the child's
+                let mapped_key = derive_from_js_value(&mapped_child, "key");
+                let child_key_value = if children.is_object() {
+                    derive_from_js_value(&children, "key")
+                } else {
+                    JsValue::undefined()
+                };
+                let user_key = if !mapped_key.is_null()
+                    && !mapped_key.is_undefined()
+                    && !Object::is(&mapped_key, &child_key_value)
+                {
+                    format!("{}/", escape_user_provided_key(&to_string(&mapped_key)))
+                } else {
+                    "".to_string()
+                };
+                clone_and_replace_key(
+                    &mapped_child,
+                    &JsValue::from_str(&format!("{}{}{}", escaped_prefix, user_key, child_key)),
+                )
+            } else {
+                mapped_child
+            };
+            array.push(&mapped_child);
+        }
+        return Ok(1);
+    }
+
+    let next_name_prefix = if name_so_far.is_empty() {
+        SEPARATOR.to_string()
+    } else {
+        format!("{}{}", name_so_far, SUB_SEPARATOR)
+    };
+
+    let mut subtree_count = 0;
+    if let Some(children) = children.dyn_ref::<Array>() {
+        for (i, child) in children.iter().enumerate() {
+            let next_name = format!("{}{}", next_name_prefix, get_element_key(&child, i as u32));
+            subtree_count += map_into_array(&child, array, escaped_prefix, &next_name, callback)?;
+        }
+    } else if let Ok(Some(iter)) = js_sys::try_iter(&children) {
+        for (i, child) in iter.enumerate() {
+            let child = child?;
+            let next_name = format!("{}{}", next_name_prefix, get_element_key(&child, i as u32));
+            subtree_count += map_into_array(&child, array, escaped_prefix, &next_name, callback)?;
+        }
+    } else if children.is_object() {
+        return Err(Error::new(&format!(
+            "Objects are not valid as a React child (found: {}).",
+            to_string(&children)
+        ))
+        .into());
+    }
+    Ok(subtree_count)
+}
+
+fn map_children(
+    children: &JsValue,
+    callback: &mut dyn FnMut(&JsValue, u32) -> Result<JsValue, JsValue>,
+) -> Result<JsValue, JsValue> {
+    if children.is_null() || children.is_undefined() {
+        return Ok(children.clone());
+    }
+    let result = Array::new();
+    let mut count = 0;
+    map_into_array(children, &result, "", "", &mut |child| {
+        let mapped_child = callback(child, count);
+        count += 1;
+        mapped_child
+    })?;
+    Ok(result.into())
+}
+
+#[wasm_bindgen]
+pub struct Children;
+
+#[wasm_bindgen]
+impl Children {
+    pub fn map(children: &JsValue, func: &Function, context: &JsValue) -> Result<JsValue, JsValue> {
+        map_children(children, &mut |child, index| {
+            func.call2(context, child, &JsValue::from(index))
+        })
+    }
+
+    #[wasm_bindgen(js_name = forEach)]
+    pub fn for_each(children: &JsValue, func: &Function, context: &JsValue) -> Result<(), JsValue> {
+        map_children(children, &mut |child, index| {
+            func.call2(context, child, &JsValue::from(index))?;
+            Ok(JsValue::undefined())
+        })?;
+        Ok(())
+    }
+
+    pub fn count(children: &JsValue) -> Result<u32, JsValue> {
+        let mut n = 0;
+        map_children(children, &mut |_, _| {
+            n += 1;
+            Ok(JsValue::undefined())
+        })?;
+        Ok(n)
+    }
+
+    #[wasm_bindgen(js_name = toArray)]
+    pub fn to_array(children: &JsValue) -> Result<JsValue, JsValue> {
+        let result = map_children(children, &mut |child, _| Ok(child.clone()))?;
+        if result.is_array() {
+            Ok(result)
+        } else {
+            Ok(Array::new().into())
+        }
+    }
+
+    pub fn only(children: &JsValue) -> Result<JsValue, JsValue> {
+        if !is_valid_element(children) {
+            return Err(Error::new(
+                "React.Children.only expected to receive a single React element child.",
+            )
+            .into());
+        }
+        Ok(children.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a"), "$a");
+        assert_eq!(escape("a=b:c"), "$a=0b=2c");
+    }
+
+    #[test]
+    fn test_escape_user_provided_key() {
+        assert_eq!(escape_user_provided_key("a"), "a");
+        assert_eq!(escape_user_provided_key("a/b"), "a//b");
+        assert_eq!(escape_user_provided_key("a//b/"), "a///b//");
+    }
+
+    #[test]
+    fn test_to_base_36() {
+        assert_eq!(to_base_36(0), "0");
+        assert_eq!(to_base_36(35), "z");
+        assert_eq!(to_base_36(36), "10");
+    }
+}
@@ -5,12 +5,19 @@ use wasm_bindgen::JsValue;
 #[derive(Debug)]
 pub struct Dispatcher {
     pub use_state: Function,
+    pub use_reducer: Function,
     pub use_effect: Function,
+    pub use_layout_effect: Function,
+    pub use_insertion_effect: Function,
     pub use_ref: Function,
     pub use_memo: Function,
     pub use_callback: Function,
     pub use_context: Function,
     pub use_transition: Function,
+    pub use_id: Function,
+    pub use_sync_external_store: Function,
+    pub use_deferred_value: Function,
+    pub use_imperative_handle: Function,
     pub _use: Function,
 }
 
@@ -19,22 +26,36 @@ unsafe impl Send for Dispatcher {}
 impl Dispatcher {
     pub fn new(
         use_state: Function,
+        use_reducer: Function,
         use_effect: Function,
+        use_layout_effect: Function,
+        use_insertion_effect: Function,
         use_ref: Function,
         use_memo: Function,
         use_callback: Function,
         use_context: Function,
         use_transition: Function,
+        use_id: Function,
+        use_sync_external_store: Function,
+        use_deferred_value: Function,
+        use_imperative_handle: Function,
         _use: Function,
     ) -> Self {
         Dispatcher {
             use_state,
+            use_reducer,
             use_effect,
+            use_layout_effect,
+            use_insertion_effect,
             use_ref,
             use_memo,
             use_callback,
             use_context,
             use_transition,
+            use_id,
+            use_sync_external_store,
+            use_deferred_value,
+            use_imperative_handle,
             _use,
         }
     }
@@ -56,21 +77,35 @@ fn derive_function_from_js_value(js_value: &JsValue, name: &str) -> Function {
 #[wasm_bindgen(js_name = updateDispatcher)]
 pub unsafe fn update_dispatcher(args: &JsValue) {
     let use_state = derive_function_from_js_value(args, "use_state");
+    let use_reducer = derive_function_from_js_value(args, "use_reducer");
     let use_effect = derive_function_from_js_value(args, "use_effect");
+    let use_layout_effect = derive_function_from_js_value(args, "use_layout_effect");
+    let use_insertion_effect = derive_function_from_js_value(args, "use_insertion_effect");
     let use_ref = derive_function_from_js_value(args, "use_ref");
     let use_memo = derive_function_from_js_value(args, "use_memo");
     let use_callback = derive_function_from_js_value(args, "use_callback");
     let use_context = derive_function_from_js_value(args, "use_context");
     let use_transition = derive_function_from_js_value(args, "use_transition");
+    let use_id = derive_function_from_js_value(args, "use_id");
+    let use_sync_external_store = derive_function_from_js_value(args, "use_sync_external_store");
+    let use_deferred_value = derive_function_from_js_value(args, "use_deferred_value");
+    let use_imperative_handle = derive_function_from_js_value(args, "use_imperative_handle");
     let _use = derive_function_from_js_value(args, "use");
     CURRENT_DISPATCHER.current = Some(Box::new(Dispatcher::new(
         use_state,
+        use_reducer,
         use_effect,
+        use_layout_effect,
+        use_insertion_effect,
         use_ref,
         use_memo,
         use_callback,
         use_context,
         use_transition,
+        use_id,
+        use_sync_external_store,
+        use_deferred_value,
+        use_imperative_handle,
         _use,
     )))
 }
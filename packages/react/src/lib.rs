@@ -1,14 +1,15 @@
-use js_sys::{Array, Function, Object, Reflect, JSON};
+use js_sys::{Array, Error, Function, Object, Reflect, JSON};
 use lazy::{lazy_initializer, UNINITIALIZED};
 use wasm_bindgen::prelude::*;
 
 use shared::{
-    derive_from_js_value, REACT_CONTEXT_TYPE, REACT_ELEMENT_TYPE, REACT_LAZY_TYPE, REACT_MEMO_TYPE,
-    REACT_PROVIDER_TYPE,
+    derive_from_js_value, to_string, REACT_CONSUMER_TYPE, REACT_CONTEXT_TYPE, REACT_ELEMENT_TYPE,
+    REACT_FORWARD_REF_TYPE, REACT_LAZY_TYPE, REACT_MEMO_TYPE, REACT_PROVIDER_TYPE,
 };
 
 use crate::current_dispatcher::CURRENT_DISPATCHER;
 
+mod children;
 pub mod current_batch_config;
 pub mod current_dispatcher;
 mod lazy;
@@ -105,6 +106,105 @@ pub fn jsx(_type: &JsValue, config: &JsValue, maybe_children: &JsValue) -> JsVal
     jsx_dev(_type, config, &JsValue::undefined())
 }
 
+fn create_react_element(
+    _type: &JsValue,
+    key: &JsValue,
+    _ref: &JsValue,
+    props: &JsValue,
+) -> JsValue {
+    let react_element = Object::new();
+    Reflect::set(
+        &react_element,
+        &"$$typeof".into(),
+        &JsValue::from_str(REACT_ELEMENT_TYPE),
+    )
+    .expect("$$typeof panic");
+    Reflect::set(&react_element, &"type".into(), _type).expect("type panic");
+    Reflect::set(&react_element, &"props".into(), props).expect("props panic");
+    Reflect::set(&react_element, &"ref".into(), _ref).expect("ref panic");
+    Reflect::set(&react_element, &"key".into(), key).expect("key panic");
+    react_element.into()
+}
+
+pub(crate) fn clone_and_replace_key(old_element: &JsValue, new_key: &JsValue) -> JsValue {
+    create_react_element(
+        &derive_from_js_value(old_element, "type"),
+        new_key,
+        &derive_from_js_value(old_element, "ref"),
+        &derive_from_js_value(old_element, "props"),
+    )
+}
+
+// The key and ref of the element are kept unless they are overridden by the config
+#[wasm_bindgen(js_name = cloneElement, variadic)]
+pub fn clone_element(
+    element: &JsValue,
+    config: &JsValue,
+    maybe_children: &JsValue,
+) -> Result<JsValue, JsValue> {
+    if element.is_null() || element.is_undefined() {
+        return Err(Error::new(&format!(
+            "The argument must be a React element, but you passed {}.",
+            to_string(element)
+        ))
+        .into());
+    }
+
+    let props = Object::assign(
+        &Object::new(),
+        &derive_from_js_value(element, "props").into(),
+    );
+    let mut key = derive_from_js_value(element, "key");
+    let mut _ref = derive_from_js_value(element, "ref");
+
+    if let Some(conf) = config.dyn_ref::<Object>() {
+        for prop in Object::keys(conf) {
+            let val = Reflect::get(conf, &prop);
+            match prop.as_string() {
+                None => {}
+                Some(k) => {
+                    if k == "ref" && val.is_ok() {
+                        let val = val.unwrap();
+                        if !val.is_undefined() {
+                            _ref = val;
+                        }
+                    } else if k == "key" && val.is_ok() {
+                        let val = val.unwrap();
+                        if !val.is_undefined() {
+                            key = resolve_key(&val);
+                        }
+                    } else if val.is_ok() {
+                        Reflect::set(&props, &JsValue::from(k), &val.unwrap())
+                            .expect("props panic");
+                    }
+                }
+            }
+        }
+    }
+
+    let length = derive_from_js_value(maybe_children, "length");
+    match length.as_f64() {
+        None => {}
+        Some(length) => {
+            if length == 1.0 {
+                let children = maybe_children.dyn_ref::<Array>().unwrap();
+                Reflect::set(&props, &"children".into(), &children.get(0))
+                    .expect("TODO: panic children");
+            } else if length > 1.0 {
+                Reflect::set(&props, &"children".into(), maybe_children)
+                    .expect("TODO: panic set children");
+            }
+        }
+    }
+
+    Ok(create_react_element(
+        &derive_from_js_value(element, "type"),
+        &key,
+        &_ref,
+        &props.into(),
+    ))
+}
+
 #[wasm_bindgen(js_name = isValidElement)]
 pub fn is_valid_element(object: &JsValue) -> bool {
     object.is_object()
@@ -123,12 +223,42 @@ pub unsafe fn use_state(initial_state: &JsValue) -> Result<JsValue, JsValue> {
     use_state.call1(&JsValue::null(), initial_state)
 }
 
+#[wasm_bindgen(js_name = useReducer)]
+pub unsafe fn use_reducer(
+    reducer: &JsValue,
+    initial_arg: &JsValue,
+    init: &JsValue,
+) -> Result<JsValue, JsValue> {
+    let use_reducer = &CURRENT_DISPATCHER.current.as_ref().unwrap().use_reducer;
+    use_reducer.call3(&JsValue::null(), reducer, initial_arg, init)
+}
+
 #[wasm_bindgen(js_name = useEffect)]
 pub unsafe fn use_effect(create: &JsValue, deps: &JsValue) {
     let use_effect = &CURRENT_DISPATCHER.current.as_ref().unwrap().use_effect;
     use_effect.call2(&JsValue::null(), create, deps);
 }
 
+#[wasm_bindgen(js_name = useLayoutEffect)]
+pub unsafe fn use_layout_effect(create: &JsValue, deps: &JsValue) {
+    let use_layout_effect = &CURRENT_DISPATCHER
+        .current
+        .as_ref()
+        .unwrap()
+        .use_layout_effect;
+    use_layout_effect.call2(&JsValue::null(), create, deps);
+}
+
+#[wasm_bindgen(js_name = useInsertionEffect)]
+pub unsafe fn use_insertion_effect(create: &JsValue, deps: &JsValue) {
+    let use_insertion_effect = &CURRENT_DISPATCHER
+        .current
+        .as_ref()
+        .unwrap()
+        .use_insertion_effect;
+    use_insertion_effect.call2(&JsValue::null(), create, deps);
+}
+
 #[wasm_bindgen(js_name = useRef)]
 pub unsafe fn use_ref(initial_value: &JsValue) -> Result<JsValue, JsValue> {
     let use_ref = &CURRENT_DISPATCHER.current.as_ref().unwrap().use_ref;
@@ -165,6 +295,222 @@ pub unsafe fn use_transition() -> Result<JsValue, JsValue> {
     use_transition.call0(&JsValue::null())
 }
 
+#[wasm_bindgen(js_name = useId)]
+pub unsafe fn use_id() -> Result<JsValue, JsValue> {
+    let use_id = &CURRENT_DISPATCHER.current.as_ref().unwrap().use_id;
+    use_id.call0(&JsValue::null())
+}
+
+#[wasm_bindgen(js_name = useSyncExternalStore)]
+pub unsafe fn use_sync_external_store(
+    subscribe: &JsValue,
+    get_snapshot: &JsValue,
+    get_server_snapshot: &JsValue,
+) -> Result<JsValue, JsValue> {
+    let use_sync_external_store = &CURRENT_DISPATCHER
+        .current
+        .as_ref()
+        .unwrap()
+        .use_sync_external_store;
+    use_sync_external_store.call3(
+        &JsValue::null(),
+        subscribe,
+        get_snapshot,
+        get_server_snapshot,
+    )
+}
+
+#[wasm_bindgen(js_name = useDeferredValue)]
+pub unsafe fn use_deferred_value(
+    value: &JsValue,
+    initial_value: &JsValue,
+) -> Result<JsValue, JsValue> {
+    let use_deferred_value = &CURRENT_DISPATCHER
+        .current
+        .as_ref()
+        .unwrap()
+        .use_deferred_value;
+    use_deferred_value.call2(&JsValue::null(), value, initial_value)
+}
+
+#[wasm_bindgen(js_name = useImperativeHandle)]
+pub unsafe fn use_imperative_handle(_ref: &JsValue, create: &JsValue, deps: &JsValue) {
+    let use_imperative_handle = &CURRENT_DISPATCHER
+        .current
+        .as_ref()
+        .unwrap()
+        .use_imperative_handle;
+    use_imperative_handle.call3(&JsValue::null(), _ref, create, deps);
+}
+
+// Object refs created outside of hooks, e.g. in the constructor of class components
+#[wasm_bindgen(js_name = createRef)]
+pub fn create_ref() -> JsValue {
+    let ref_object = Object::new();
+    Reflect::set(&ref_object, &"current".into(), &JsValue::null());
+    Object::seal(&ref_object);
+    ref_object.into()
+}
+
+fn set_state(
+    updater: &JsValue,
+    partial_state: &JsValue,
+    callback: &JsValue,
+) -> Result<(), JsValue> {
+    if !partial_state.is_object()
+        && !partial_state.is_function()
+        && !partial_state.is_null()
+        && !partial_state.is_undefined()
+    {
+        return Err(Error::new("takes an object of state variables to update or a function which returns an object of state variables.").into());
+    }
+    if updater.is_null() || updater.is_undefined() {
+        web_sys::console::error_1(
+            &"Can't call setState on a component that is not yet mounted.".into(),
+        );
+        return Ok(());
+    }
+    derive_from_js_value(updater, "enqueueSetState")
+        .dyn_ref::<Function>()
+        .unwrap()
+        .call2(updater, partial_state, callback)?;
+    Ok(())
+}
+
+fn force_update(updater: &JsValue, callback: &JsValue) -> Result<(), JsValue> {
+    if updater.is_null() || updater.is_undefined() {
+        web_sys::console::error_1(
+            &"Can't call forceUpdate on a component that is not yet mounted.".into(),
+        );
+        return Ok(());
+    }
+    derive_from_js_value(updater, "enqueueForceUpdate")
+        .dyn_ref::<Function>()
+        .unwrap()
+        .call1(updater, callback)?;
+    Ok(())
+}
+
+// The updater is injected by the reconciler when the instance is created
+#[wasm_bindgen(getter_with_clone)]
+pub struct Component {
+    pub props: JsValue,
+    pub context: JsValue,
+    pub refs: JsValue,
+    pub updater: JsValue,
+}
+
+#[wasm_bindgen]
+impl Component {
+    #[wasm_bindgen(constructor)]
+    pub fn new(props: JsValue, context: JsValue, updater: JsValue) -> Component {
+        Component {
+            props,
+            context,
+            refs: Object::new().into(),
+            updater: if updater.is_undefined() {
+                JsValue::null()
+            } else {
+                updater
+            },
+        }
+    }
+
+    #[wasm_bindgen(js_name = setState)]
+    pub fn set_state(&self, partial_state: &JsValue, callback: &JsValue) -> Result<(), JsValue> {
+        set_state(&self.updater, partial_state, callback)
+    }
+
+    #[wasm_bindgen(js_name = forceUpdate)]
+    pub fn force_update(&self, callback: &JsValue) -> Result<(), JsValue> {
+        force_update(&self.updater, callback)
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct PureComponent {
+    pub props: JsValue,
+    pub context: JsValue,
+    pub refs: JsValue,
+    pub updater: JsValue,
+}
+
+#[wasm_bindgen]
+impl PureComponent {
+    #[wasm_bindgen(constructor)]
+    pub fn new(props: JsValue, context: JsValue, updater: JsValue) -> PureComponent {
+        PureComponent {
+            props,
+            context,
+            refs: Object::new().into(),
+            updater: if updater.is_undefined() {
+                JsValue::null()
+            } else {
+                updater
+            },
+        }
+    }
+
+    #[wasm_bindgen(js_name = setState)]
+    pub fn set_state(&self, partial_state: &JsValue, callback: &JsValue) -> Result<(), JsValue> {
+        set_state(&self.updater, partial_state, callback)
+    }
+
+    #[wasm_bindgen(js_name = forceUpdate)]
+    pub fn force_update(&self, callback: &JsValue) -> Result<(), JsValue> {
+        force_update(&self.updater, callback)
+    }
+}
+
+// The prototype of an exported class is reached through a temporary instance, which is freed
+// right away so that its Rust value doesn't leak
+fn get_prototype_of_instance(instance: JsValue) -> Object {
+    let prototype = Object::get_prototype_of(&instance);
+    derive_from_js_value(&instance, "free")
+        .dyn_ref::<Function>()
+        .unwrap()
+        .call0(&instance)
+        .expect("TODO: panic free instance");
+    prototype
+}
+
+// The classes exported by wasm-bindgen can't have data properties on their prototypes, so
+// the markers checked by the reconciler are added when the module is instantiated
+#[wasm_bindgen(start)]
+fn init_component_prototypes() {
+    let component_prototype = get_prototype_of_instance(
+        Component::new(
+            JsValue::undefined(),
+            JsValue::undefined(),
+            JsValue::undefined(),
+        )
+        .into(),
+    );
+    Reflect::set(
+        &component_prototype,
+        &"isReactComponent".into(),
+        &Object::new(),
+    )
+    .expect("TODO: panic set isReactComponent");
+
+    let pure_component_prototype = get_prototype_of_instance(
+        PureComponent::new(
+            JsValue::undefined(),
+            JsValue::undefined(),
+            JsValue::undefined(),
+        )
+        .into(),
+    );
+    // PureComponent instances are also instances of Component
+    Object::set_prototype_of(&pure_component_prototype, &component_prototype);
+    Reflect::set(
+        &pure_component_prototype,
+        &"isPureReactComponent".into(),
+        &JsValue::from_bool(true),
+    )
+    .expect("TODO: panic set isPureReactComponent");
+}
+
 #[wasm_bindgen(js_name = createContext)]
 pub unsafe fn create_context(default_value: &JsValue) -> JsValue {
     let context = Object::new();
@@ -182,6 +528,14 @@ pub unsafe fn create_context(default_value: &JsValue) -> JsValue {
     );
     Reflect::set(&provider, &"_context".into(), &context);
     Reflect::set(&context, &"Provider".into(), &provider);
+    let consumer = Object::new();
+    Reflect::set(
+        &consumer,
+        &"$$typeof".into(),
+        &JsValue::from_str(REACT_CONSUMER_TYPE),
+    );
+    Reflect::set(&consumer, &"_context".into(), &context);
+    Reflect::set(&context, &"Consumer".into(), &consumer);
     context.into()
 }
 
@@ -209,6 +563,19 @@ pub fn memo(_type: &JsValue, compare: &JsValue) -> JsValue {
     fiber_type.into()
 }
 
+#[wasm_bindgen(js_name = forwardRef)]
+pub fn forward_ref(render: &JsValue) -> JsValue {
+    let fiber_type = Object::new();
+
+    Reflect::set(
+        &fiber_type,
+        &"$$typeof".into(),
+        &JsValue::from_str(REACT_FORWARD_REF_TYPE),
+    );
+    Reflect::set(&fiber_type, &"render".into(), render);
+    fiber_type.into()
+}
+
 #[wasm_bindgen]
 pub fn lazy(ctor: &JsValue) -> JsValue {
     let payload = Object::new();